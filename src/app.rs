@@ -1,21 +1,45 @@
 use std::path::PathBuf;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::fs;
-use std::process::Command;
 use std::time::Instant;
 use eframe::egui;
 use crate::models::GameStats;
 
 use crate::graphics_presets::GraphicsConfig;
-use crate::config::{get_config_path, save_config, load_config, get_mame_data_dir};
-use crate::models::{AppConfig, MameExecutable, GameMetadata, RomSetType, IconInfo};
-use crate::mame_utils::{get_mame_version, load_mame_metadata_parallel_with_exec};
+use crate::config::{assign_data_roots, check_directory, DirCheck, get_config_path, save_config, load_config, get_mame_data_dir, get_executables_dir, get_managed_ini_dir, get_metadata_cache_dir, get_archive_cache_path};
+use crate::models::{AppConfig, MameExecutable, GameMetadata, CustomCollection, DirScanSettings, DirRole, ManagedDir, LaunchProfile, RomSetType, IconInfo, AuditStatus, AuditReport, SetAuditResult};
+use crate::mame_utils::{get_mame_showconfig, load_mame_metadata_with_progress, MetadataProgress};
 use crate::rom_utils::{
     collect_roms_with_zip_scan, load_roms_from_audit, detect_rom_set_type,
     load_rom_icon, get_parent_rom, ico_to_rgba_bytes
 };
+use crate::rom_utils::archive::{find_directory_sets, is_rom_archive};
+use crate::rom_utils::audit::{
+    audit_rom_sets, write_available_ini, write_fix_dat, save_audit_report, load_audit_report,
+    load_archive_cache, save_archive_cache,
+};
+use crate::rom_utils::dat::{load_collection, CollectionProgress, LoadedCollection};
+use crate::rom_utils::hygiene::{scan_rom_hygiene, HygieneProgress, HygieneReport};
+use crate::rom_utils::scan::expand_rom_dirs;
+use crate::launch::{
+    describe_exit, finish_session_log, list_session_logs, log_tail, managed_inipath,
+    LaunchFailure, LaunchOptions, LaunchPlan, RunningGame, SessionLog, QUICK_EXIT_SECS,
+};
+use crate::mame_ini::{expand_path_entry, parse_showconfig, plan_ini_files, write_ini_files, ConfigOption, IniFile};
+use crate::diagnosis::{check_dependencies, diagnose, Problem};
+use crate::rom_utils::rebuild::{rebuild_rom_sets, RebuildOptions, RebuildProgress, RebuildReport};
+use crate::rom_utils::watcher::{start_dir_watcher, DirWatcher, WatchEvent, WatchKey};
+
+/// ROMs found by a scan as (display name, set name), plus the sets stored as folders
+pub type RomScanResult = (Vec<(String, String)>, HashSet<String>);
+/// A managed directory and what checking it found
+pub type DirCheckResult = ((PathBuf, DirRole), DirCheck);
+/// An executable's path, with its options as its own ini files set them and as a launch sees them
+pub type MameConfigResult = (String, Result<Vec<ConfigOption>, String>, Result<Vec<ConfigOption>, String>);
 
 pub struct MyApp {
     pub config: AppConfig,
@@ -23,10 +47,11 @@ pub struct MyApp {
     pub mame_titles: HashMap<String, String>,
     pub roms: Vec<(String, String)>,
     pub roms_loading: bool,
-    pub roms_tx: Option<mpsc::Receiver<Vec<(String, String)>>>,
+    pub roms_tx: Option<mpsc::Receiver<RomScanResult>>,
+    pub directory_sets: HashSet<String>,  // Sets stored as uncompressed folders
     pub screenshot: Option<egui::ColorImage>,
     pub texture_handle: Option<egui::TextureHandle>,
-    pub game_metadata: HashMap<String, GameMetadata>,
+    pub game_metadata: Arc<HashMap<String, GameMetadata>>,
     pub art_texture: Option<egui::TextureHandle>,
     pub all_manufacturers: Vec<String>,
     pub show_about: bool,
@@ -35,7 +60,7 @@ pub struct MyApp {
     pub show_rom_set_info: bool,
     pub total_games_count: usize,
     pub working_games_count: usize,
-    pub running_games: HashMap<String, (std::process::Child, std::time::Instant)>,
+    pub running_games: HashMap<String, RunningGame>,
     pub mame_version: String,
     pub show_context_menu: bool,
     pub context_menu_position: egui::Pos2,
@@ -55,6 +80,90 @@ pub struct MyApp {
     pub default_icon_texture: Option<egui::TextureHandle>,
     pub audit_start_time: Option<std::time::Instant>, // NEW
     pub last_audit_progress: String, // NEW
+    pub audit_results: HashMap<String, SetAuditResult>,
+    pub audit_details_rom: Option<String>,
+
+    // Background metadata loading
+    pub metadata_loading: bool,
+    pub metadata_progress: String,
+    pub metadata_fraction: Option<f32>,
+    pub metadata_tx: Option<mpsc::Receiver<MetadataProgress>>,
+    pub metadata_cancel: Arc<AtomicBool>,
+    pub metadata_loading_path: Option<String>,
+    pub metadata_load_queue: VecDeque<(String, bool)>,
+    pub metadata_start_time: Option<Instant>,
+    pub metadata_error: Option<(String, String)>, // (executable path, message)
+
+    // ROM set rebuilder
+    pub show_rebuild_dialog: bool,
+    pub rebuild_options: RebuildOptions,
+    pub rebuild_tx: Option<mpsc::Receiver<RebuildProgress>>,
+    pub rebuild_progress: String,
+    pub rebuild_report: Option<Result<RebuildReport, String>>,
+
+    // Custom DAT collections
+    pub show_collections_dialog: bool,
+    pub collection: Option<LoadedCollection>,  // Shown in the ROM list instead of the MAME sets
+    pub collection_tx: Option<mpsc::Receiver<CollectionProgress>>,
+    pub collection_progress: String,
+    pub collection_draft: CustomCollection,  // Being filled in by the collections dialog
+
+    // ROM directory hygiene report
+    pub show_hygiene_dialog: bool,
+    pub hygiene_tx: Option<mpsc::Receiver<HygieneProgress>>,
+    pub hygiene_progress: String,
+    pub hygiene_report: Option<HygieneReport>,
+    pub quarantine_dir: PathBuf,
+
+    // Background watcher for the ROM and asset directories
+    pub dir_watcher: Option<DirWatcher>,
+    // Expanded scan and rompath directories, kept until the key they were built for changes
+    pub expanded_dirs: Option<(WatchKey, Vec<PathBuf>, Vec<PathBuf>)>,
+
+    // Per-directory scan settings being edited
+    pub show_scan_settings_dialog: bool,
+    pub scan_settings_draft: HashMap<PathBuf, DirScanSettings>,
+
+    // Launch command dialog: the plan shown and its arguments as edited, one per line
+    pub launch_preview: Option<LaunchPlan>,
+    pub launch_preview_args: String,
+
+    // Launch profile being edited in the game settings dialog
+    pub game_settings_rom: Option<String>,
+    pub game_settings_draft: LaunchProfile,
+
+    // MAME ini files as they would be written, while the preview is open
+    pub ini_preview: Option<Vec<IniFile>>,
+
+    // MAME configuration dialog: the executable's effective options from -showconfig,
+    // the edits made to them, and what the launcher passes as (option, value, layer)
+    pub show_mame_config: bool,
+    pub mame_config: Result<Vec<ConfigOption>, String>,
+    pub mame_own_config: Option<(String, Result<Vec<ConfigOption>, String>)>,  // Executable path and what its own ini files set
+    pub mame_config_tx: Option<mpsc::Receiver<MameConfigResult>>,
+    pub mame_config_edits: HashMap<String, String>,
+    pub mame_config_launch: Vec<(String, String, String)>,
+    pub mame_config_filter: String,
+    pub mame_config_message: Option<String>,
+
+    // A launch held back by the pre-launch checks, with what they found missing
+    pub pending_launch: Option<LaunchPlan>,
+    pub pending_launch_mame: Option<usize>,  // Becomes the game's preferred executable if the held launch goes ahead
+    pub pending_launch_problems: Vec<Problem>,
+
+    // Launch output: the last failed launch, and the session log browser
+    pub launch_failure: Option<LaunchFailure>,
+    pub session_logs_rom: Option<String>,
+    pub session_logs: Vec<SessionLog>,
+    pub session_log_selected: usize,
+    pub session_log_text: String,
+
+    // Directories manager
+    pub show_directories_dialog: bool,
+    pub directories_draft: Vec<ManagedDir>,
+    pub directory_checks: HashMap<(PathBuf, DirRole), DirCheck>,  // Filled in as paths are shown
+    pub directory_check_channel: (mpsc::Sender<DirCheckResult>, mpsc::Receiver<DirCheckResult>),
+    pub new_directory_role: DirRole,
 
     // NEW: Icon management fields
     pub icon_load_queue: VecDeque<String>,
@@ -65,7 +174,9 @@ pub struct MyApp {
 impl MyApp {
     pub fn new() -> Self {
         let config_path = get_config_path();
-        let config = load_config(&config_path).unwrap_or_default();
+        let mut config = load_config(&config_path).unwrap_or_default();
+        config.sync_directory_lists();
+        let roots_assigned = assign_data_roots(&mut config.mame_executables, &get_executables_dir());
 
         let mut app = Self {
             config,
@@ -73,9 +184,10 @@ impl MyApp {
             roms: vec![],
             roms_loading: false,
             roms_tx: None,
+            directory_sets: HashSet::new(),
             screenshot: None,
             texture_handle: None,
-            game_metadata: HashMap::new(),
+            game_metadata: Arc::default(),
             art_texture: None,
             all_manufacturers: Vec::new(),
             show_about: false,
@@ -105,6 +217,71 @@ impl MyApp {
                 graphics_config: GraphicsConfig::default(),
                 audit_start_time: None, // NEW
                 last_audit_progress: String::new(), // NEW
+                audit_results: HashMap::new(),
+                audit_details_rom: None,
+                // Background metadata loading
+                metadata_loading: false,
+                metadata_progress: String::new(),
+                metadata_fraction: None,
+                metadata_tx: None,
+                metadata_cancel: Arc::new(AtomicBool::new(false)),
+                metadata_loading_path: None,
+                metadata_load_queue: VecDeque::new(),
+                metadata_start_time: None,
+                metadata_error: None,
+
+                show_rebuild_dialog: false,
+                rebuild_options: RebuildOptions::default(),
+                rebuild_tx: None,
+                rebuild_progress: String::new(),
+                rebuild_report: None,
+
+                show_collections_dialog: false,
+                collection: None,
+                collection_tx: None,
+                collection_progress: String::new(),
+                collection_draft: CustomCollection::default(),
+
+                show_hygiene_dialog: false,
+                hygiene_tx: None,
+                hygiene_progress: String::new(),
+                hygiene_report: None,
+                quarantine_dir: get_mame_data_dir().join("quarantine"),
+
+                dir_watcher: None,
+                expanded_dirs: None,
+
+                show_scan_settings_dialog: false,
+                scan_settings_draft: HashMap::new(),
+
+                launch_preview: None,
+                launch_preview_args: String::new(),
+                game_settings_rom: None,
+                game_settings_draft: LaunchProfile::default(),
+                ini_preview: None,
+                show_mame_config: false,
+                mame_config: Ok(Vec::new()),
+                mame_own_config: None,
+                mame_config_tx: None,
+                mame_config_edits: HashMap::new(),
+                mame_config_launch: Vec::new(),
+                mame_config_filter: String::new(),
+                mame_config_message: None,
+                pending_launch: None,
+                pending_launch_mame: None,
+                pending_launch_problems: Vec::new(),
+
+                launch_failure: None,
+                session_logs_rom: None,
+                session_logs: vec![],
+                session_log_selected: 0,
+                session_log_text: String::new(),
+
+                show_directories_dialog: false,
+                directories_draft: vec![],
+                directory_checks: HashMap::new(),
+                directory_check_channel: mpsc::channel(),
+                new_directory_role: DirRole::Roms,
 
                 // NEW: Initialize icon management fields
                 icon_load_queue: VecDeque::new(),
@@ -112,15 +289,13 @@ impl MyApp {
                 last_icon_cleanup: Instant::now(),
         };
 
-        // Load metadata if we have MAME configured
-        if !app.config.mame_executables.is_empty() && app.config.selected_mame_index < app.config.mame_executables.len() {
-            let mame_path = app.config.mame_executables[app.config.selected_mame_index].path.clone();
-            app.load_mame_data(&mame_path);
+        if roots_assigned {
+            app.save_config();
+        }
 
-            // Load ROMs if paths are configured
-            if !app.config.rom_dirs.is_empty() {
-                app.reload_roms();
-            }
+        // Load metadata if we have MAME configured; ROMs are reloaded once it arrives
+        if !app.config.mame_executables.is_empty() && app.config.selected_mame_index < app.config.mame_executables.len() {
+            app.load_mame_data(app.config.selected_mame_index, false);
         }
 
         app
@@ -177,16 +352,32 @@ impl MyApp {
         // Take ownership of running_games temporarily
         let running_games = std::mem::take(&mut self.running_games);
 
-        for (rom_name, (mut child, start_time)) in running_games {
-            match child.try_wait() {
-                Ok(Some(_)) => {
+        for (rom_name, mut game) in running_games {
+            match game.child.try_wait() {
+                Ok(Some(status)) => {
                     // Game has finished
-                    let play_time = start_time.elapsed().as_secs() as u32;
-                    finished_games.push((rom_name, play_time));
+                    let play_time = game.started.elapsed().as_secs();
+                    let exit = describe_exit(&status);
+                    finish_session_log(&game.log_path, &exit, play_time);
+                    println!("{} finished with {} after {}s", rom_name, exit, play_time);
+
+                    // The time counts however the game ended; a failure also explains itself
+                    finished_games.push((rom_name.clone(), play_time as u32));
+                    if !status.success() || play_time < QUICK_EXIT_SECS {
+                        let output = fs::read_to_string(&game.log_path).unwrap_or_default();
+                        self.launch_failure = Some(LaunchFailure {
+                            problems: diagnose(&output, &game.plan, &self.game_metadata),
+                            tail: log_tail(&game.log_path, 40),
+                            rom: rom_name,
+                            log_path: game.log_path,
+                            exit,
+                            run_secs: play_time,
+                        });
+                    }
                 }
                 Ok(None) => {
                     // Still running, keep it
-                    still_running.insert(rom_name, (child, start_time));
+                    still_running.insert(rom_name, game);
                 }
                 Err(e) => {
                     println!("Error checking game process: {}", e);
@@ -203,6 +394,20 @@ impl MyApp {
         }
     }
 
+    /// Open the session log browser for `rom`, showing its most recent log
+    pub fn open_session_logs(&mut self, rom: &str) {
+        self.session_logs = list_session_logs(rom);
+        self.session_logs_rom = Some(rom.to_string());
+        self.select_session_log(0);
+    }
+
+    pub fn select_session_log(&mut self, index: usize) {
+        self.session_log_selected = index;
+        self.session_log_text = self.session_logs.get(index)
+        .and_then(|log| fs::read_to_string(&log.path).ok())
+        .unwrap_or_default();
+    }
+
     // NEW: Load the default icon texture
     fn load_default_icon(&mut self, ctx: &egui::Context) {
         // Try to load a default.ico file first
@@ -431,19 +636,182 @@ impl MyApp {
         self.save_config();
     }
 
-    pub fn load_mame_data(&mut self, mame_path: &str) {
-        self.mame_version = get_mame_version(mame_path);
-        self.game_metadata = load_mame_metadata_parallel_with_exec(mame_path);
-        self.mame_titles = self.game_metadata.iter().map(|(k, v)| (k.clone(), v.description.clone())).collect();
+    /// Queue a background metadata load for one executable.
+    ///
+    /// Loads run one at a time; results are picked up by `handle_metadata_progress`.
+    pub fn load_mame_data(&mut self, mame_index: usize, force_rebuild: bool) {
+        let Some(mame_path) = self.config.mame_executables.get(mame_index).map(|m| m.path.clone()) else {
+            return;
+        };
+
+        if self.metadata_loading_path.as_deref() == Some(mame_path.as_str()) && !force_rebuild {
+            return;
+        }
+        if let Some(queued) = self.metadata_load_queue.iter_mut().find(|(path, _)| *path == mame_path) {
+            queued.1 |= force_rebuild;
+            return;
+        }
+
+        self.metadata_load_queue.push_back((mame_path, force_rebuild));
+        self.start_next_metadata_load();
+    }
+
+    fn start_next_metadata_load(&mut self) {
+        if self.metadata_loading {
+            return;
+        }
+
+        while let Some((mame_path, force_rebuild)) = self.metadata_load_queue.pop_front() {
+            // The executable may have been removed while the load was queued
+            let Some(mame_index) = self.config.mame_executables.iter().position(|m| m.path == mame_path) else {
+                continue;
+            };
+            let Some(cache_path) = self.get_metadata_cache_path(mame_index) else {
+                continue;
+            };
+
+            let (tx, rx) = mpsc::channel();
+            let cancel = Arc::new(AtomicBool::new(false));
+            self.metadata_tx = Some(rx);
+            self.metadata_cancel = cancel.clone();
+            self.metadata_loading = true;
+            self.metadata_loading_path = Some(mame_path.clone());
+            self.metadata_progress = "Starting MAME...".to_string();
+            self.metadata_fraction = None;
+            self.metadata_start_time = Some(Instant::now());
+            self.metadata_error = None;
+
+            thread::spawn(move || {
+                let result = load_mame_metadata_with_progress(&mame_path, &cache_path, force_rebuild, &tx, &cancel);
+                let message = match result {
+                    _ if cancel.load(Ordering::Relaxed) => MetadataProgress::Cancelled,
+                    Ok((version, metadata)) => MetadataProgress::Done { version, metadata },
+                    Err(e) => MetadataProgress::Failed(e),
+                };
+                let _ = tx.send(message);
+            });
+            return;
+        }
+    }
+
+    /// Ask the running metadata load to stop and drop anything still queued
+    pub fn cancel_metadata_load(&mut self) {
+        self.metadata_load_queue.clear();
+        self.metadata_cancel.store(true, Ordering::Relaxed);
+        self.metadata_progress = "Cancelling...".to_string();
+    }
+
+    /// Drain progress from the background metadata load, applying the result when it finishes
+    pub fn handle_metadata_progress(&mut self) {
+        let Some(rx) = &self.metadata_tx else {
+            return;
+        };
+
+        let mut finished = None;
+        while let Ok(message) = rx.try_recv() {
+            match message {
+                MetadataProgress::LoadingCache => {
+                    self.metadata_progress = "Reading metadata cache...".to_string();
+                    self.metadata_fraction = None;
+                }
+                MetadataProgress::RunningMame => {
+                    self.metadata_progress = "Running MAME -listxml...".to_string();
+                    self.metadata_fraction = None;
+                }
+                MetadataProgress::Parsing { parsed, bytes_received } => {
+                    self.metadata_progress = format!(
+                        "Parsing machines: {} ({:.1} MB received)...",
+                        parsed,
+                        bytes_received as f64 / (1024.0 * 1024.0)
+                    );
+                    self.metadata_fraction = None;
+                }
+                MetadataProgress::Indexing => {
+                    self.metadata_progress = "Indexing machines...".to_string();
+                    self.metadata_fraction = Some(1.0);
+                }
+                other => {
+                    finished = Some(other);
+                    break;
+                }
+            }
+        }
+
+        let Some(outcome) = finished else {
+            return;
+        };
 
+        self.metadata_loading = false;
+        self.metadata_tx = None;
+        self.metadata_start_time = None;
+        let mame_path = self.metadata_loading_path.take().unwrap_or_default();
+        let mame_index = self.config.mame_executables.iter().position(|m| m.path == mame_path);
+        let is_selected = mame_index == Some(self.config.selected_mame_index);
+
+        match outcome {
+            MetadataProgress::Done { version, metadata } => {
+                if let Some(idx) = mame_index {
+                    self.apply_loaded_metadata(idx, version, metadata);
+                    self.save_config();
+                    if is_selected && !self.config.rom_dirs.is_empty() {
+                        self.reload_roms();
+                    }
+                }
+            }
+            MetadataProgress::Failed(message) => {
+                eprintln!("Metadata load failed for {}: {}", mame_path, message);
+                if is_selected {
+                    // Don't leave another executable's machines behind
+                    self.clear_loaded_metadata();
+                    if !self.config.rom_dirs.is_empty() {
+                        self.reload_roms();
+                    }
+                }
+                self.metadata_error = Some((mame_path, message));
+            }
+            _ => {
+                println!("Metadata load cancelled for {}", mame_path);
+                self.metadata_progress.clear();
+            }
+        }
+
+        self.start_next_metadata_load();
+    }
+
+    /// Retry the load that produced the current error, rebuilding the cache
+    pub fn retry_metadata_load(&mut self) {
+        if let Some((mame_path, _)) = self.metadata_error.take() {
+            if let Some(idx) = self.config.mame_executables.iter().position(|m| m.path == mame_path) {
+                self.load_mame_data(idx, true);
+            }
+        }
+    }
+
+    fn apply_loaded_metadata(&mut self, mame_index: usize, version: String, metadata: HashMap<String, GameMetadata>) {
         // Count total and working games
-        self.total_games_count = self.game_metadata.iter()
+        let total = metadata.iter()
         .filter(|(_, meta)| !meta.is_device && !meta.is_bios)
         .count();
-        self.working_games_count = self.game_metadata.iter()
-        .filter(|(_, meta)| !meta.is_device && !meta.is_bios && !meta.is_mechanical && !meta.runnable)
+        let working = metadata.iter()
+        .filter(|(_, meta)| !meta.is_device && !meta.is_bios && !meta.is_mechanical && meta.is_working())
         .count();
 
+        // Keep the executable's stored counts in sync with what we just loaded
+        if let Some(mame) = self.config.mame_executables.get_mut(mame_index) {
+            mame.total_games = total;
+            mame.working_games = working;
+        }
+
+        if mame_index != self.config.selected_mame_index {
+            return;
+        }
+
+        self.mame_version = version;
+        self.game_metadata = Arc::new(metadata);
+        self.mame_titles = self.game_metadata.iter().map(|(k, v)| (k.clone(), v.description.clone())).collect();
+        self.total_games_count = total;
+        self.working_games_count = working;
+
         // Extract unique manufacturers
         let mut manufacturers: Vec<String> = self.game_metadata.values()
         .map(|m| m.manufacturer.clone())
@@ -452,14 +820,29 @@ impl MyApp {
         manufacturers.sort();
         manufacturers.dedup();
         self.all_manufacturers = manufacturers;
+
+        self.load_audit_report();
+    }
+
+    fn clear_loaded_metadata(&mut self) {
+        self.game_metadata = Arc::default();
+        self.mame_titles.clear();
+        self.all_manufacturers.clear();
+        self.total_games_count = 0;
+        self.working_games_count = 0;
+        self.mame_version.clear();
+        self.audit_results.clear();
     }
 
     pub fn reload_roms(&mut self) {
         // Clear icon cache when reloading ROMs
         self.clear_icon_cache();
+        // A full rescan also picks up subfolders created since the lists were expanded
+        self.expanded_dirs = None;
 
         if !self.config.mame_executables.is_empty() && self.config.selected_mame_index < self.config.mame_executables.len() {
-            let rom_dirs = self.config.rom_dirs.clone();
+            let configured_dirs = self.config.rom_dirs.clone();
+            let scan_settings = self.config.dir_scan_settings.clone();
             let mame_titles = self.mame_titles.clone();
             let mame_executable = self.config.mame_executables[self.config.selected_mame_index].path.clone();
             let use_audit = self.config.use_mame_audit;
@@ -477,6 +860,7 @@ impl MyApp {
 
             thread::spawn(move || {
                 let _ = progress_tx.send("Scanning directories...".to_string()); // NEW
+                let rom_dirs = expand_rom_dirs(&configured_dirs, &scan_settings, &metadata);
                 let roms = if use_audit {
                     if let Some(audit_path) = audit_file_path {
                         // Try to load from audit data
@@ -505,7 +889,8 @@ impl MyApp {
                 };
 
                 println!("Total ROMs found: {}", roms.len());
-                let _ = tx.send(roms);
+                let directory_sets = find_directory_sets(&rom_dirs, &metadata);
+                let _ = tx.send((roms, directory_sets));
             });
 
             // Store progress receiver
@@ -532,6 +917,39 @@ impl MyApp {
         }
     }
 
+    /// Get the path to the structured audit report for a specific MAME executable
+    pub fn get_audit_report_path(&self, mame_index: usize) -> Option<PathBuf> {
+        self.config.mame_executables.get(mame_index).map(|mame| {
+            let mame_id = self.get_mame_identifier(mame);
+            get_mame_data_dir().join("ui").join(format!("audit_{}.json", mame_id))
+        })
+    }
+
+    /// Load the last audit report for the selected MAME executable, if there is one
+    pub fn load_audit_report(&mut self) {
+        self.audit_results = self.get_audit_report_path(self.config.selected_mame_index)
+        .and_then(|path| load_audit_report(&path))
+        .map(|report| report.results)
+        .unwrap_or_default();
+    }
+
+    /// Get the path to the metadata cache for a specific MAME executable
+    pub fn get_metadata_cache_path(&self, mame_index: usize) -> Option<PathBuf> {
+        self.config.mame_executables.get(mame_index).map(|mame| {
+            let mame_id = self.get_mame_identifier(mame);
+            get_metadata_cache_dir().join(format!("metadata_{}.json", mame_id))
+        })
+    }
+
+    /// Throw away the cached -listxml for the current MAME and parse it again
+    pub fn rebuild_metadata_cache(&mut self) {
+        if self.config.selected_mame_index >= self.config.mame_executables.len() {
+            return;
+        }
+
+        self.load_mame_data(self.config.selected_mame_index, true);
+    }
+
     /// Generate a unique identifier for a MAME executable
     pub fn get_mame_identifier(&self, mame: &MameExecutable) -> String {
         // Use a combination of name and version, sanitized for filesystem
@@ -551,23 +969,22 @@ impl MyApp {
         }
     }
 
-    pub fn run_mame_audit(&mut self) {
+    /// Audit the rompath against the loaded listxml checksums, without running MAME
+    pub fn run_rom_audit(&mut self) {
         if self.config.mame_executables.is_empty() || self.audit_in_progress {
             return;
         }
 
         let mame_index = self.config.selected_mame_index;
-        let mame_path = self.config.mame_executables[mame_index].path.clone();
-        let rom_dirs = self.config.rom_dirs.clone();
-        let extra_dirs = self.config.extra_rom_dirs.clone();
+        let rom_dirs = self.rompath_dirs();
+        let metadata = self.game_metadata.clone();
+        let mame_version = self.mame_version.clone();
 
         // Get our custom audit file location
-        let audit_file_path = self.get_audit_file_path(mame_index);
-        if audit_file_path.is_none() {
+        let (Some(audit_file_path), Some(report_path)) = (self.get_audit_file_path(mame_index), self.get_audit_report_path(mame_index)) else {
             println!("Error: Could not determine audit file path");
             return;
-        }
-        let audit_file_path = audit_file_path.unwrap();
+        };
 
         self.audit_in_progress = true;
         self.audit_progress = "Starting ROM audit...".to_string();
@@ -577,121 +994,725 @@ impl MyApp {
         self.audit_tx = Some(rx);
 
         thread::spawn(move || {
-            let _ = tx.send("Preparing MAME audit...".to_string());
-
-            // Build rompath argument
-            let all_dirs = rom_dirs.iter().chain(extra_dirs.iter());
-            // MAME uses semicolon as separator on all platforms
-            let separator = ";";
-            let rom_paths = all_dirs
-            .map(|p| p.to_string_lossy())
-            .collect::<Vec<_>>()
-            .join(separator);
-
-            // MAME needs a ui directory to write mame_avail.ini
-            let mame_dir = std::path::Path::new(&mame_path).parent().unwrap_or(std::path::Path::new("."));
-            let mame_ui_dir = mame_dir.join("ui");
-            let _ = fs::create_dir_all(&mame_ui_dir);
-
-            // Remove any existing mame_avail.ini to ensure fresh audit
-            let mame_avail_path = mame_ui_dir.join("mame_avail.ini");
-            let _ = fs::remove_file(&mame_avail_path);
-
-            let _ = tx.send("Running MAME audit (scanning inside all ROM archives)...".to_string());
-
-            // Run MAME audit - this scans inside every ZIP and creates mame_avail.ini
-            let output = Command::new(&mame_path)
-            .current_dir(&mame_dir)  // Run in MAME's directory
-            .arg("-rompath")
-            .arg(&rom_paths)
-            .arg("-verifyroms")
-            .output();
-
-            match output {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-
-                    // Parse audit results from output
-                    let mut total_roms = 0;
-                    let mut good_roms = 0;
-                    let mut bad_roms = 0;
-                    let mut not_found = 0;
-
-                    for line in stdout.lines() {
-                        if line.contains("romset") {
-                            total_roms += 1;
-                            if line.contains("is good") || line.contains("is best available") {
-                                good_roms += 1;
-                            } else if line.contains("is bad") {
-                                bad_roms += 1;
-                            } else if line.contains("NOT FOUND") {
-                                not_found += 1;
-                            }
-                        }
-                    }
-
-                    let _ = tx.send(format!(
-                        "Audit complete: {} ROMs scanned, {} good, {} bad, {} not found",
-                        total_roms, good_roms, bad_roms, not_found
-                    ));
-
-                    // Give MAME a moment to finish writing the file
-                    thread::sleep(std::time::Duration::from_millis(500));
-
-                    // Check if MAME created mame_avail.ini and copy it to our location
-                    if mame_avail_path.exists() {
-                        match fs::copy(&mame_avail_path, &audit_file_path) {
-                            Ok(_) => {
-                                let _ = tx.send(format!("Audit file saved for this MAME version"));
+            if metadata.is_empty() {
+                let _ = tx.send("No MAME metadata loaded, cannot audit".to_string());
+                let _ = tx.send("AUDIT_FAILED".to_string());
+                return;
+            }
 
-                                // Clean up MAME's ui directory
-                                let _ = fs::remove_file(&mame_avail_path);
-                            }
-                            Err(e) => {
-                                let _ = tx.send(format!("Failed to copy audit file: {}", e));
-                            }
-                        }
-                    } else {
-                        // Sometimes MAME writes to a different location, check common alternatives
-                        let alt_locations = vec![
-                            PathBuf::from(".mame").join("ui").join("mame_avail.ini"),
-                      dirs::home_dir().unwrap_or_default().join(".mame").join("ui").join("mame_avail.ini"),
-                      PathBuf::from("ui").join("mame_avail.ini"),
-                        ];
-
-                        let mut found = false;
-                        for alt_path in alt_locations {
-                            if alt_path.exists() {
-                                match fs::copy(&alt_path, &audit_file_path) {
-                                    Ok(_) => {
-                                        let _ = tx.send(format!("Audit file found at {:?} and saved", alt_path));
-                                        let _ = fs::remove_file(&alt_path);
-                                        found = true;
-                                        break;
-                                    }
-                                    Err(e) => {
-                                        println!("Failed to copy from {:?}: {}", alt_path, e);
-                                    }
-                                }
-                            }
-                        }
+            let progress = |msg: String| {
+                let _ = tx.send(msg);
+            };
+            // Archives that haven't changed since the last audit are not reopened
+            let cache_path = get_archive_cache_path();
+            let mut archive_cache = load_archive_cache(&cache_path);
+            let results = audit_rom_sets(&rom_dirs, &metadata, &mut archive_cache, &progress);
+            if let Err(e) = save_archive_cache(&archive_cache, &cache_path) {
+                eprintln!("Failed to save archive cache {:?}: {}", cache_path, e);
+            }
 
-                        if !found {
-                            let _ = tx.send("Warning: Could not find mame_avail.ini after audit".to_string());
-                            let _ = tx.send("MAME may have written it to a different location".to_string());
-                        }
-                    }
+            let count = |status: AuditStatus| results.values().filter(|r| r.status == status).count();
+            let _ = tx.send(format!(
+                "Audit complete: {} good, {} best available, {} incorrect, {} missing files, {} not found",
+                count(AuditStatus::Good),
+                count(AuditStatus::BestAvailable),
+                count(AuditStatus::Incorrect),
+                count(AuditStatus::MissingFiles),
+                count(AuditStatus::NotFound),
+            ));
+
+            if let Err(e) = write_available_ini(&results, &audit_file_path) {
+                let _ = tx.send(format!("Failed to write audit file: {}", e));
+                let _ = tx.send("AUDIT_FAILED".to_string());
+                return;
+            }
 
+            let report = AuditReport {
+                mame_version,
+                audited_at: chrono::Local::now().to_rfc3339(),
+                results,
+            };
+            match save_audit_report(&report, &report_path) {
+                Ok(()) => {
+                    let _ = tx.send("Audit file saved for this MAME version".to_string());
                     let _ = tx.send("AUDIT_COMPLETE".to_string());
                 }
                 Err(e) => {
-                    let _ = tx.send(format!("Failed to run MAME audit: {}", e));
+                    let _ = tx.send(format!("Failed to write audit report: {}", e));
                     let _ = tx.send("AUDIT_FAILED".to_string());
                 }
             }
         });
     }
 
+    /// Sets shown in the ROM list: the open custom collection's, or the MAME sets
+    pub fn list_roms(&self) -> &[(String, String)] {
+        self.collection.as_ref().map(|c| c.roms.as_slice()).unwrap_or(&self.roms)
+    }
+
+    /// Metadata for the sets in the ROM list
+    pub fn list_metadata(&self) -> &HashMap<String, GameMetadata> {
+        self.collection.as_ref().map(|c| &c.metadata).unwrap_or(&self.game_metadata)
+    }
+
+    /// Audit results for the sets in the ROM list
+    pub fn list_audit_results(&self) -> &HashMap<String, SetAuditResult> {
+        self.collection.as_ref().map(|c| &c.results).unwrap_or(&self.audit_results)
+    }
+
+    pub fn get_collection_report_path(&self, index: usize) -> Option<PathBuf> {
+        self.config.custom_collections.get(index)
+        .map(|c| get_mame_data_dir().join("ui").join(format!("collection_{}.json", c.slug())))
+    }
+
+    /// Archive listings for a collection's ROM directory, kept apart from the MAME rompath's
+    pub fn get_collection_archive_cache_path(&self, index: usize) -> Option<PathBuf> {
+        self.config.custom_collections.get(index)
+        .map(|c| get_archive_cache_path().with_file_name(format!("archives_{}.json", c.slug())))
+    }
+
+    /// Load a custom collection into the ROM list, verifying its directory first if asked
+    pub fn open_collection(&mut self, index: usize, verify: bool) {
+        if self.collection_tx.is_some() {
+            return;
+        }
+        let (Some(collection), Some(report_path), Some(cache_path)) = (
+            self.config.custom_collections.get(index).cloned(),
+            self.get_collection_report_path(index),
+            self.get_collection_archive_cache_path(index),
+        ) else {
+            return;
+        };
+
+        self.collection_progress = format!("Loading {}...", collection.name);
+        let (tx, rx) = mpsc::channel();
+        self.collection_tx = Some(rx);
+
+        thread::spawn(move || {
+            let progress = |msg: String| {
+                let _ = tx.send(CollectionProgress::Message(msg));
+            };
+            let result = load_collection(&collection, index, &report_path, &cache_path, verify, &progress);
+            let _ = tx.send(match result {
+                Ok(loaded) => CollectionProgress::Loaded(loaded),
+                Err(e) => CollectionProgress::Failed(format!("{}: {}", collection.name, e)),
+            });
+        });
+    }
+
+    /// Forget a custom collection and its saved verification
+    pub fn remove_collection(&mut self, index: usize) {
+        if index >= self.config.custom_collections.len() {
+            return;
+        }
+
+        for path in [self.get_collection_report_path(index), self.get_collection_archive_cache_path(index)].into_iter().flatten() {
+            let _ = fs::remove_file(path);
+        }
+        match self.collection.as_ref().map(|c| c.index) {
+            Some(open) if open == index => self.close_collection(),
+            Some(open) if open > index => {
+                if let Some(collection) = self.collection.as_mut() {
+                    collection.index -= 1;
+                }
+            }
+            _ => {}
+        }
+
+        self.config.custom_collections.remove(index);
+        self.save_config();
+    }
+
+    /// Go back to listing the MAME sets
+    pub fn close_collection(&mut self) {
+        if self.collection.take().is_some() {
+            self.config.selected_rom = None;
+            self.clear_icon_cache();
+        }
+    }
+
+    /// Drain progress from a collection load or verification
+    pub fn handle_collection_progress(&mut self) {
+        let Some(rx) = &self.collection_tx else {
+            return;
+        };
+
+        let mut finished = None;
+        while let Ok(update) = rx.try_recv() {
+            match update {
+                CollectionProgress::Message(msg) => self.collection_progress = msg,
+                CollectionProgress::Loaded(loaded) => finished = Some(Ok(loaded)),
+                CollectionProgress::Failed(e) => finished = Some(Err(e)),
+            }
+        }
+
+        match finished {
+            Some(Ok(loaded)) => {
+                self.collection_tx = None;
+                self.collection_progress = match &loaded.cache_error {
+                    Some(e) => format!("{} sets loaded. {}", loaded.roms.len(), e),
+                    None => format!("{} sets loaded", loaded.roms.len()),
+                };
+                self.config.selected_rom = None;
+                self.clear_icon_cache();
+                self.collection = Some(loaded);
+            }
+            Some(Err(e)) => {
+                self.collection_tx = None;
+                eprintln!("Failed to load collection {}", e);
+                self.collection_progress = format!("Failed to load {}", e);
+            }
+            None => {}
+        }
+    }
+
+    /// Save the missing and bad files from the last audit as a Logiqx fix-DAT
+    pub fn export_fix_dat(&mut self) {
+        if self.audit_results.is_empty() {
+            return;
+        }
+
+        let default_name = format!("fix_{}.dat", self.mame_version.replace(' ', "_"));
+        let Some(path) = rfd::FileDialog::new()
+        .add_filter("Logiqx DAT", &["dat", "xml"])
+        .set_file_name(default_name)
+        .save_file() else {
+            return;
+        };
+
+        let message = match write_fix_dat(&self.audit_results, &self.game_metadata, &self.mame_version, &path) {
+            Ok(0) => "Nothing to fix: every audited set is complete.".to_string(),
+            Ok(count) => format!("Wrote {} incomplete sets to {}", count, path.display()),
+            Err(e) => format!("Failed to write fix-DAT: {}", e),
+        };
+        println!("{}", message);
+        rfd::MessageDialog::new()
+        .set_title("Export fix-DAT")
+        .set_description(message)
+        .show();
+    }
+
+    /// Start editing the directory list in the directories manager
+    pub fn open_directories_dialog(&mut self) {
+        self.directories_draft = self.config.directories.clone();
+        self.directory_checks.clear();
+        self.show_directories_dialog = true;
+    }
+
+    /// Check the draft directories that haven't been checked yet on a background thread.
+    /// Checking a CHD directory opens every subfolder, which is too slow for the UI thread.
+    pub fn check_draft_directories(&mut self) {
+        let pending: Vec<(PathBuf, DirRole)> = self.directories_draft.iter()
+        .map(|dir| (dir.path.clone(), dir.role))
+        .filter(|key| !self.directory_checks.contains_key(key))
+        .collect();
+        if pending.is_empty() {
+            return;
+        }
+
+        for key in &pending {
+            self.directory_checks.insert(key.clone(), DirCheck::Checking);
+        }
+        let tx = self.directory_check_channel.0.clone();
+        thread::spawn(move || {
+            for (path, role) in pending {
+                let check = check_directory(&path, role);
+                if tx.send(((path, role), check)).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    /// Pick up the directory checks finished since the last frame
+    pub fn handle_directory_checks(&mut self) {
+        while let Ok((key, check)) = self.directory_check_channel.1.try_recv() {
+            self.directory_checks.insert(key, check);
+        }
+    }
+
+    /// Store the edited directory list and reload whatever depends on it
+    pub fn apply_directories(&mut self) {
+        self.config.directories = std::mem::take(&mut self.directories_draft);
+        self.config.sync_directory_lists();
+        // Forget the scan settings of directories that were removed
+        let directories = &self.config.directories;
+        self.config.dir_scan_settings.retain(|dir, _| directories.iter().any(|d| &d.path == dir));
+
+        self.screenshot = None;
+        self.art_texture = None;
+        self.save_config();
+        self.reload_roms();
+    }
+
+    /// The executable a game launches with: the one its launch profile names, then its
+    /// preferred one, then the selected one
+    pub fn preferred_mame_index(&self, rom: &str) -> usize {
+        self.config.game_profiles.get(rom)
+        .and_then(|p| p.mame_index)
+        .filter(|&i| i < self.config.mame_executables.len())
+        .or_else(|| self.config.game_preferred_mame.get(rom).copied())
+        .unwrap_or(self.config.selected_mame_index)
+    }
+
+    /// Work out the MAME ini files the current settings call for and show what would change
+    pub fn open_ini_preview(&mut self) {
+        // The managed mame.ini carries over what the selected executable's own ini files set
+        // until they've been read the plan is made without them, then again once they're in
+        let selected = self.config.mame_executables.get(self.config.selected_mame_index).map(|m| m.path.clone());
+        if selected.is_some() && self.mame_config_tx.is_none() && self.mame_own_config.as_ref().map(|(path, _)| path) != selected.as_ref() {
+            self.load_mame_config();
+        }
+        let own = match &self.mame_own_config {
+            Some((_, Ok(options))) => options.as_slice(),
+            _ => &[],
+        };
+        self.ini_preview = Some(plan_ini_files(&self.config, &self.rompath_dirs(), &get_managed_ini_dir(), own));
+    }
+
+    /// Write the previewed ini files, then show what is on disk now
+    pub fn write_ini_preview(&mut self) {
+        let Some(files) = &self.ini_preview else {
+            return;
+        };
+        match write_ini_files(files) {
+            Ok(count) => println!("Updated {} MAME ini files", count),
+            Err(e) => {
+                eprintln!("Failed to write MAME ini files: {}", e);
+                rfd::MessageDialog::new()
+                .set_title("MAME ini Files")
+                .set_description(format!("Failed to write the ini files: {}", e))
+                .show();
+            }
+        }
+        self.open_ini_preview();
+    }
+
+    /// Open the MAME configuration dialog for the selected executable
+    pub fn open_mame_config(&mut self) {
+        self.mame_config_edits = self.config.mame_ini_options.clone();
+        self.mame_config_message = None;
+        self.load_mame_config();
+        self.show_mame_config = true;
+    }
+
+    /// Read the selected executable's effective options in the background. MAME is run
+    /// with -showconfig twice, which is too slow for the UI thread.
+    pub fn load_mame_config(&mut self) {
+        let Some(path) = self.config.mame_executables.get(self.config.selected_mame_index).map(|m| m.path.clone()) else {
+            self.mame_config = Err("No MAME executable configured".to_string());
+            return;
+        };
+        let use_ini_files = self.config.use_ini_files;
+
+        let (tx, rx) = mpsc::channel();
+        self.mame_config_tx = Some(rx);
+        thread::spawn(move || {
+            // What MAME reads by itself, which includes the -inipath the managed directory
+            // is put in front of
+            let own = get_mame_showconfig(&path, &[]).map(|text| parse_showconfig(&text));
+
+            // MAME has to look for its ini files where it does at launch
+            let effective = if use_ini_files {
+                let own_inipath = own.as_ref().ok()
+                .and_then(|options| options.iter().find(|o| o.name == "inipath"))
+                .map(|o| o.value.as_str());
+                let inipath = ["-inipath".to_string(), managed_inipath(own_inipath)];
+                get_mame_showconfig(&path, &inipath).map(|text| parse_showconfig(&text))
+            } else {
+                own.clone()
+            };
+            let _ = tx.send((path, own, effective));
+        });
+    }
+
+    /// Take in the options read by `load_mame_config`, along with the launcher's own
+    /// options they're compared with
+    pub fn handle_mame_config_progress(&mut self) {
+        let Some(Ok((path, own, effective))) = self.mame_config_tx.as_ref().map(|rx| rx.try_recv()) else {
+            return;
+        };
+        self.mame_config_tx = None;
+        self.set_mame_own_config(&path, own);
+        self.mame_config = effective;
+
+        if let Some(mame) = self.config.mame_executables.get(self.config.selected_mame_index) {
+            let launch = LaunchOptions::resolve(None, Some(mame), &self.config, &self.rompath_dirs());
+            self.mame_config_launch = launch.ini_values().into_iter()
+            .zip(launch.sources())
+            .map(|((name, value), (_, layer))| (name, value, layer))
+            .collect();
+        }
+
+        // A preview planned while MAME was being asked lacks its own settings
+        if self.ini_preview.is_some() {
+            self.open_ini_preview();
+        }
+    }
+
+    /// Remember what the executable at `path` reads from its own ini files, and the
+    /// -inipath it searches, which launches append to the managed directory
+    fn set_mame_own_config(&mut self, path: &str, own: Result<Vec<ConfigOption>, String>) {
+        let ini_path = own.as_ref().ok()
+        .and_then(|options| options.iter().find(|o| o.name == "inipath"))
+        .map(|o| o.value.clone());
+        let mut changed = false;
+        for mame in self.config.mame_executables.iter_mut().filter(|m| m.path == path) {
+            if ini_path.is_some() && mame.ini_path != ini_path {
+                mame.ini_path.clone_from(&ini_path);
+                changed = true;
+            }
+        }
+        if changed {
+            self.save_config();
+        }
+        self.mame_own_config = Some((path.to_string(), own));
+    }
+
+    /// Keep the edited options that differ from what MAME reports and preview the
+    /// managed mame.ini they're written to
+    pub fn save_mame_config(&mut self) {
+        let mut edits = std::mem::take(&mut self.mame_config_edits);
+        if let Ok(options) = &self.mame_config {
+            edits.retain(|name, value| options.iter().find(|o| &o.name == name).is_none_or(|o| &o.value != value));
+        }
+        self.config.mame_ini_options = edits.clone();
+        self.mame_config_edits = edits;
+        self.save_config();
+        self.open_ini_preview();
+    }
+
+    /// Add the directories in MAME's own rompath to the directories manager as ROM
+    /// directories. Variables and `~` are expanded as MAME does, and relative entries
+    /// are taken from where MAME is started, as at launch.
+    pub fn import_mame_rompath(&mut self) {
+        let Some(rompath) = self.mame_config.as_ref().ok()
+        .and_then(|options| options.iter().find(|o| o.name == "rompath"))
+        .map(|o| o.value.clone()) else {
+            return;
+        };
+        let base = std::env::current_dir().unwrap_or_default();
+
+        let mut directories = self.config.directories.clone();
+        let mut added = 0;
+        let mut missing = Vec::new();
+        for entry in rompath.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let path = base.join(expand_path_entry(entry));
+            if !path.is_dir() {
+                missing.push(entry.to_string());
+            } else if !directories.iter().any(|d| d.path == path) {
+                directories.push(ManagedDir { path, role: DirRole::Roms, enabled: true });
+                added += 1;
+            }
+        }
+
+        let mut message = format!("Imported {} ROM directories from MAME's rompath.", added);
+        if !missing.is_empty() {
+            message.push_str(&format!(" Not found: {}", missing.join(", ")));
+        }
+        println!("{}", message);
+        self.mame_config_message = Some(message);
+        if added > 0 {
+            self.directories_draft = directories;
+            self.apply_directories();
+        }
+    }
+
+    /// Start editing the launch profile of `rom` in the game settings dialog
+    pub fn open_game_settings(&mut self, rom: &str) {
+        self.game_settings_draft = self.config.game_profiles.get(rom).cloned().unwrap_or_default();
+        self.game_settings_rom = Some(rom.to_string());
+    }
+
+    /// Store the edited launch profile; a profile left at the global settings is removed
+    pub fn apply_game_settings(&mut self) {
+        let Some(rom) = self.game_settings_rom.take() else {
+            return;
+        };
+        let mut profile = std::mem::take(&mut self.game_settings_draft);
+        profile.env.retain(|(key, _)| !key.trim().is_empty());
+
+        if profile.is_empty() {
+            self.config.game_profiles.remove(&rom);
+        } else {
+            self.config.game_profiles.insert(rom, profile);
+        }
+        self.save_config();
+    }
+
+    /// The resolved command for launching `rom` on the executable at `mame_index`
+    pub fn launch_plan(&self, rom: &str, mame_index: usize) -> Option<LaunchPlan> {
+        let mame = self.config.mame_executables.get(mame_index)?;
+        Some(LaunchPlan::build(rom, mame, &self.config, &self.rompath_dirs()))
+    }
+
+    /// Launch `rom` on the executable at `mame_index` and track it as a running game.
+    /// When sets it depends on are missing the launch is held for the user to confirm,
+    /// and false is returned.
+    pub fn launch_rom(&mut self, rom: &str, mame_index: usize) -> bool {
+        let Some(mame) = self.config.mame_executables.get(mame_index) else {
+            println!("Failed to launch game: no MAME executable configured");
+            return false;
+        };
+        let rompath = self.rompath_dirs();
+        let plan = LaunchPlan::build(rom, mame, &self.config, &rompath);
+
+        let sample_dirs = self.config.dirs_with_role(DirRole::Samples);
+        let problems = check_dependencies(rom, &self.game_metadata, &rompath, &sample_dirs);
+        if !problems.is_empty() {
+            println!("Holding launch of {}: {} missing dependencies", rom, problems.len());
+            self.pending_launch = Some(plan);
+            self.pending_launch_mame = None;
+            self.pending_launch_problems = problems;
+            return false;
+        }
+        self.spawn_launch(&plan)
+    }
+
+    /// Launch `rom` on the executable picked for it, which becomes the game's preferred
+    /// one once the game starts, including after a held launch is confirmed
+    pub fn launch_rom_with(&mut self, rom: &str, mame_index: usize) {
+        if self.launch_rom(rom, mame_index) {
+            self.config.game_preferred_mame.insert(rom.to_string(), mame_index);
+            self.save_config();
+        } else if self.pending_launch.as_ref().is_some_and(|plan| plan.rom == rom) {
+            self.pending_launch_mame = Some(mame_index);
+        }
+    }
+
+    /// Open the launch command dialog for `rom` on its preferred executable
+    pub fn show_launch_command(&mut self, rom: &str) {
+        let plan = self.launch_plan(rom, self.preferred_mame_index(rom));
+        self.launch_preview_args = plan.as_ref().map(|p| p.args.join("\n")).unwrap_or_default();
+        self.launch_preview = plan;
+    }
+
+    /// Start a resolved launch plan and track the game while it runs
+    pub fn spawn_launch(&mut self, plan: &LaunchPlan) -> bool {
+        match plan.spawn() {
+            Ok(game) => {
+                self.running_games.insert(plan.rom.clone(), game);
+                println!("Started tracking game: {}", plan.rom);
+                true
+            }
+            Err(e) => {
+                println!("Failed to launch game: {}", e);
+                false
+            }
+        }
+    }
+
+    /// Store the edited scan settings, dropping blank patterns, and rescan with them
+    pub fn apply_scan_settings(&mut self) {
+        let mut settings = std::mem::take(&mut self.scan_settings_draft);
+        for dir_settings in settings.values_mut() {
+            for patterns in [&mut dir_settings.include, &mut dir_settings.exclude] {
+                *patterns = patterns.iter()
+                .map(|p| p.trim().to_string())
+                .filter(|p| !p.is_empty())
+                .collect();
+            }
+        }
+        // Directories left at the defaults don't need an entry
+        settings.retain(|_, s| *s != DirScanSettings::default());
+
+        self.config.dir_scan_settings = settings;
+        self.save_config();
+        self.reload_roms();
+    }
+
+    /// ROM directories to scan for sets, with the subfolders their scan settings include
+    pub fn scan_rom_dirs(&self) -> Vec<PathBuf> {
+        match &self.expanded_dirs {
+            Some((key, scan_dirs, _)) if *key == self.watch_key() => scan_dirs.clone(),
+            _ => self.expand_scan_dirs(),
+        }
+    }
+
+    /// ROM and extra ROM directories in the order MAME searches them, without repeats.
+    /// This is the -rompath used for launches.
+    pub fn rompath_dirs(&self) -> Vec<PathBuf> {
+        match &self.expanded_dirs {
+            Some((key, _, rompath)) if *key == self.watch_key() => rompath.clone(),
+            _ => self.expand_rompath_dirs(),
+        }
+    }
+
+    // Walking the subfolders reads the disk, so these only run when the cached lists are stale
+    fn expand_scan_dirs(&self) -> Vec<PathBuf> {
+        expand_rom_dirs(&self.config.rom_dirs, &self.config.dir_scan_settings, &self.game_metadata)
+    }
+
+    fn expand_rompath_dirs(&self) -> Vec<PathBuf> {
+        let dirs: Vec<PathBuf> = self.config.rom_dirs.iter()
+        .chain(self.config.extra_rom_dirs.iter())
+        .cloned()
+        .collect();
+        expand_rom_dirs(&dirs, &self.config.dir_scan_settings, &self.game_metadata)
+    }
+
+    /// Everything the watcher and the expanded directory lists depend on
+    fn watch_key(&self) -> WatchKey {
+        WatchKey {
+            rom_dirs: self.config.rom_dirs.clone(),
+            extra_rom_dirs: self.config.extra_rom_dirs.clone(),
+            asset_dirs: self.config.extra_asset_dirs.clone(),
+            scan_settings: self.config.dir_scan_settings.clone(),
+            mame_version: self.mame_version.clone(),
+            metadata_len: self.game_metadata.len(),
+            assume_merged: self.config.assume_merged_sets,
+        }
+    }
+
+    /// Keep the directory watcher in step with the configured directories and apply
+    /// whatever it has noticed since the last frame
+    pub fn update_dir_watcher(&mut self) {
+        let key = self.watch_key();
+
+        // The status bar asks for the scan directories every frame; expand them once per change
+        if self.expanded_dirs.as_ref().map(|(k, _, _)| k) != Some(&key) {
+            self.expanded_dirs = Some((key.clone(), self.expand_scan_dirs(), self.expand_rompath_dirs()));
+        }
+
+        // Until metadata is loaded nothing can be matched to a set
+        if self.game_metadata.is_empty() || self.metadata_loading || key.rom_dirs.is_empty() {
+            self.dir_watcher = None;
+            return;
+        }
+        if self.dir_watcher.as_ref().map(|w| &w.key) != Some(&key) {
+            println!("Watching {} ROM and {} asset directories for changes", key.rom_dirs.len() + key.extra_rom_dirs.len(), key.asset_dirs.len());
+            self.dir_watcher = Some(start_dir_watcher(key, self.mame_titles.clone(), self.game_metadata.clone()));
+        }
+
+        // A full scan in progress will replace the list anyway
+        if self.roms_loading {
+            return;
+        }
+
+        let events: Vec<WatchEvent> = match &self.dir_watcher {
+            Some(watcher) => watcher.rx.try_iter().collect(),
+            None => return,
+        };
+        for event in events {
+            match event {
+                WatchEvent::Roms(delta) => {
+                    println!("ROM directories changed: updating {} sets", delta.affected.len());
+                    self.roms.retain(|(_, name)| !delta.affected.contains(name));
+                    self.roms.extend(delta.roms);
+                    self.roms.sort_by_key(|(display, _)| display.to_lowercase());
+                    self.directory_sets.retain(|name| !delta.affected.contains(name));
+                    self.directory_sets.extend(delta.folder_sets);
+                }
+                WatchEvent::AssetsChanged => {
+                    println!("Asset directories changed: reloading artwork and icons");
+                    self.clear_icon_cache();
+                    self.art_texture = None;
+                }
+            }
+        }
+    }
+
+    /// Look for unknown, stray, duplicated and unneeded files in the ROM directories
+    pub fn start_hygiene_scan(&mut self) {
+        if self.hygiene_tx.is_some() {
+            return;
+        }
+
+        let rom_dirs = self.rompath_dirs();
+        let metadata = self.game_metadata.clone();
+        self.hygiene_progress = "Scanning ROM directories...".to_string();
+        self.hygiene_report = None;
+
+        let (tx, rx) = mpsc::channel();
+        self.hygiene_tx = Some(rx);
+
+        thread::spawn(move || {
+            let progress = |msg: String| {
+                let _ = tx.send(HygieneProgress::Message(msg));
+            };
+            let cache_path = get_archive_cache_path();
+            let mut archive_cache = load_archive_cache(&cache_path);
+            let report = scan_rom_hygiene(&rom_dirs, &metadata, &mut archive_cache, &progress);
+            if let Err(e) = save_archive_cache(&archive_cache, &cache_path) {
+                eprintln!("Failed to save archive cache {:?}: {}", cache_path, e);
+            }
+            let _ = tx.send(HygieneProgress::Done(report));
+        });
+    }
+
+    /// Drain progress from a running hygiene scan
+    pub fn handle_hygiene_progress(&mut self) {
+        let Some(rx) = &self.hygiene_tx else {
+            return;
+        };
+
+        let mut finished = None;
+        while let Ok(update) = rx.try_recv() {
+            match update {
+                HygieneProgress::Message(msg) => self.hygiene_progress = msg,
+                HygieneProgress::Done(report) => finished = Some(report),
+            }
+        }
+
+        if let Some(report) = finished {
+            self.hygiene_tx = None;
+            self.hygiene_report = Some(report);
+        }
+    }
+
+    /// Rebuild the ROM directories into the target chosen in the rebuild dialog
+    pub fn start_rom_rebuild(&mut self) {
+        if self.rebuild_tx.is_some() {
+            return;
+        }
+
+        let options = self.rebuild_options.clone();
+        let metadata = self.game_metadata.clone();
+
+        self.rebuild_progress = "Starting rebuild...".to_string();
+        self.rebuild_report = None;
+
+        let (tx, rx) = mpsc::channel();
+        self.rebuild_tx = Some(rx);
+
+        thread::spawn(move || {
+            let progress = |msg: String| {
+                let _ = tx.send(RebuildProgress::Message(msg));
+            };
+            let result = rebuild_rom_sets(&options, &metadata, &progress);
+            let _ = tx.send(match result {
+                Ok(report) => RebuildProgress::Done(report),
+                Err(e) => RebuildProgress::Failed(e.to_string()),
+            });
+        });
+    }
+
+    /// Drain progress from a running rebuild
+    pub fn handle_rebuild_progress(&mut self) {
+        let Some(rx) = &self.rebuild_tx else {
+            return;
+        };
+
+        let mut finished = None;
+        while let Ok(update) = rx.try_recv() {
+            match update {
+                RebuildProgress::Message(msg) => self.rebuild_progress = msg,
+                RebuildProgress::Done(report) => finished = Some(Ok(report)),
+                RebuildProgress::Failed(e) => finished = Some(Err(e)),
+            }
+        }
+
+        if let Some(result) = finished {
+            self.rebuild_tx = None;
+            // Removed sources change what the list can show
+            let changed_sources = matches!(&result, Ok(report) if report.sources_removed > 0);
+            self.rebuild_report = Some(result);
+            if changed_sources {
+                self.reload_roms();
+            }
+        }
+    }
+
     /// Clean up audit file when removing a MAME executable
     pub fn cleanup_audit_file(&mut self, mame_index: usize) {
         if let Some(audit_path) = self.get_audit_file_path(mame_index) {
@@ -703,6 +1724,24 @@ impl MyApp {
             }
         }
 
+        if let Some(report_path) = self.get_audit_report_path(mame_index) {
+            if report_path.exists() {
+                match fs::remove_file(&report_path) {
+                    Ok(_) => println!("Removed audit report: {:?}", report_path),
+                    Err(e) => println!("Failed to remove audit report: {}", e),
+                }
+            }
+        }
+
+        if let Some(cache_path) = self.get_metadata_cache_path(mame_index) {
+            if cache_path.exists() {
+                match fs::remove_file(&cache_path) {
+                    Ok(_) => println!("Removed metadata cache: {:?}", cache_path),
+                    Err(e) => println!("Failed to remove metadata cache: {}", e),
+                }
+            }
+        }
+
         // Also remove from the audit times map
         if let Some(mame) = self.config.mame_executables.get(mame_index) {
             let mame_id = self.get_mame_identifier(mame);
@@ -719,7 +1758,9 @@ impl MyApp {
             for entry in entries.flatten() {
                 let path = entry.path();
                 if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                    if filename.starts_with("mame_avail_") && filename.ends_with(".ini") {
+                    let is_ini = filename.starts_with("mame_avail_") && filename.ends_with(".ini");
+                    let is_report = filename.starts_with("audit_") && filename.ends_with(".json");
+                    if is_ini || is_report {
                         audit_files.push((filename.to_string(), path));
                     }
                 }
@@ -736,7 +1777,7 @@ impl MyApp {
 
         // Collect valid audit file names
         for (idx, _) in self.config.mame_executables.iter().enumerate() {
-            if let Some(audit_path) = self.get_audit_file_path(idx) {
+            for audit_path in [self.get_audit_file_path(idx), self.get_audit_report_path(idx)].into_iter().flatten() {
                 if let Some(filename) = audit_path.file_name().and_then(|n| n.to_str()) {
                     valid_audit_files.insert(filename.to_string());
                 }
@@ -752,6 +1793,28 @@ impl MyApp {
                 }
             }
         }
+
+        // Same for metadata caches
+        let valid_caches: std::collections::HashSet<PathBuf> = (0..self.config.mame_executables.len())
+        .filter_map(|idx| self.get_metadata_cache_path(idx))
+        .collect();
+
+        if let Ok(entries) = fs::read_dir(get_metadata_cache_dir()) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let is_cache = path.file_name()
+                .and_then(|n| n.to_str())
+                .map(|n| n.starts_with("metadata_"))
+                .unwrap_or(false);
+
+                if is_cache && !valid_caches.contains(&path) {
+                    match fs::remove_file(&path) {
+                        Ok(_) => println!("Removed orphaned metadata cache: {:?}", path),
+                        Err(e) => println!("Failed to remove orphaned metadata cache {:?}: {}", path, e),
+                    }
+                }
+            }
+        }
     }
 
     pub fn debug_rom_loading(&self) -> String {
@@ -829,20 +1892,15 @@ impl MyApp {
         report.push_str("ROM Directories:\n");
         for dir in &self.config.rom_dirs {
             if dir.exists() {
-                let zip_count = std::fs::read_dir(dir)
+                let archive_count = std::fs::read_dir(dir)
                 .map(|entries| {
                     entries.filter_map(|e| e.ok())
-                    .filter(|e| {
-                        e.path().extension()
-                        .and_then(|ext| ext.to_str())
-                        .map(|ext| ext.eq_ignore_ascii_case("zip"))
-                        .unwrap_or(false)
-                    })
+                    .filter(|e| is_rom_archive(&e.path()))
                     .count()
                 })
                 .unwrap_or(0);
 
-                report.push_str(&format!("  ✓ {:?} - {} ZIP files found\n", dir, zip_count));
+                report.push_str(&format!("  ✓ {:?} - {} ZIP/7z archives found\n", dir, archive_count));
             } else {
                 report.push_str(&format!("  ✗ {:?} - DIRECTORY NOT FOUND\n", dir));
             }
@@ -963,7 +2021,7 @@ impl MyApp {
     }
 
     pub fn get_rom_set_type(&self) -> RomSetType {
-        detect_rom_set_type(&self.config.rom_dirs, &self.game_metadata)
+        detect_rom_set_type(&self.scan_rom_dirs(), &self.game_metadata)
     }
 
     pub fn get_missing_parent_roms(&self) -> Vec<(String, String)> {
@@ -994,7 +2052,7 @@ impl MyApp {
         let mut clone_count = 0;
         let mut parent_to_clones: std::collections::HashMap<String, Vec<String>> = std::collections::HashMap::new();
 
-        for (name, meta) in &self.game_metadata {
+        for (name, meta) in self.game_metadata.iter() {
             if meta.is_clone {
                 clone_count += 1;
                 if let Some(parent) = &meta.parent {
@@ -1076,9 +2134,10 @@ impl Default for MyApp {
             roms: Vec::new(),
             roms_loading: false,
             roms_tx: None,
+            directory_sets: HashSet::new(),
             screenshot: None,
             texture_handle: None,
-            game_metadata: HashMap::new(),
+            game_metadata: Arc::default(),
             art_texture: None,
             all_manufacturers: Vec::new(),
             show_about: false,
@@ -1107,6 +2166,71 @@ impl Default for MyApp {
             default_icon_texture: None,
                 audit_start_time: None,
                 last_audit_progress: String::new(),
+                audit_results: HashMap::new(),
+                audit_details_rom: None,
+                // Background metadata loading
+                metadata_loading: false,
+                metadata_progress: String::new(),
+                metadata_fraction: None,
+                metadata_tx: None,
+                metadata_cancel: Arc::new(AtomicBool::new(false)),
+                metadata_loading_path: None,
+                metadata_load_queue: VecDeque::new(),
+                metadata_start_time: None,
+                metadata_error: None,
+
+                show_rebuild_dialog: false,
+                rebuild_options: RebuildOptions::default(),
+                rebuild_tx: None,
+                rebuild_progress: String::new(),
+                rebuild_report: None,
+
+                show_collections_dialog: false,
+                collection: None,
+                collection_tx: None,
+                collection_progress: String::new(),
+                collection_draft: CustomCollection::default(),
+
+                show_hygiene_dialog: false,
+                hygiene_tx: None,
+                hygiene_progress: String::new(),
+                hygiene_report: None,
+                quarantine_dir: get_mame_data_dir().join("quarantine"),
+
+                dir_watcher: None,
+                expanded_dirs: None,
+
+                show_scan_settings_dialog: false,
+                scan_settings_draft: HashMap::new(),
+
+                launch_preview: None,
+                launch_preview_args: String::new(),
+                game_settings_rom: None,
+                game_settings_draft: LaunchProfile::default(),
+                ini_preview: None,
+                show_mame_config: false,
+                mame_config: Ok(Vec::new()),
+                mame_own_config: None,
+                mame_config_tx: None,
+                mame_config_edits: HashMap::new(),
+                mame_config_launch: Vec::new(),
+                mame_config_filter: String::new(),
+                mame_config_message: None,
+                pending_launch: None,
+                pending_launch_mame: None,
+                pending_launch_problems: Vec::new(),
+
+                launch_failure: None,
+                session_logs_rom: None,
+                session_logs: vec![],
+                session_log_selected: 0,
+                session_log_text: String::new(),
+
+                show_directories_dialog: false,
+                directories_draft: vec![],
+                directory_checks: HashMap::new(),
+                directory_check_channel: mpsc::channel(),
+                new_directory_role: DirRole::Roms,
 
                 // NEW: Initialize icon management fields
                 icon_load_queue: VecDeque::new(),
@@ -1,6 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs;
-use crate::models::{AppConfig, VideoSettings};
+use crate::models::{AppConfig, DataFolder, DataLayout, DirRole, MameExecutable, VideoSettings};
 
 pub fn get_config_path() -> PathBuf {
     let config_dir = dirs::config_dir()
@@ -19,6 +19,49 @@ pub fn save_config(config: &AppConfig, path: &PathBuf) -> Result<(), Box<dyn std
     Ok(())
 }
 
+/// Replace an argument string from an older config with the argument list it stands for.
+/// Older versions split these strings on whitespace and nothing else, so that is done here
+/// too: shell quoting would eat the backslashes in Windows paths like C:\MAME\roms.
+fn migrate_arg_string(value: &mut serde_json::Value, what: &str) {
+    let Some(text) = value.as_str() else {
+        return;
+    };
+    let args: Vec<String> = text.split_whitespace().map(String::from).collect();
+    println!("Migrated config: {} is now a list of {} arguments", what, args.len());
+    *value = serde_json::json!(args);
+}
+
+/// Managed directory entries for the separate path lists older configs kept. Paths that
+/// were added as both ROM and extra ROM directories become a single ROM entry.
+fn legacy_directories(map: &serde_json::Map<String, serde_json::Value>) -> Vec<serde_json::Value> {
+    let paths = |key: &str| -> Vec<String> {
+        map.get(key)
+        .and_then(|v| v.as_array())
+        .map(|a| a.iter().filter_map(|p| p.as_str().map(String::from)).collect())
+        .unwrap_or_default()
+    };
+
+    let rom_dirs = paths("rom_dirs");
+    let chd_dirs: Vec<String> = paths("extra_rom_dirs").into_iter().filter(|p| !rom_dirs.contains(p)).collect();
+    let icons: Vec<String> = map.get("icons_path").and_then(|v| v.as_str()).map(String::from).into_iter().collect();
+    // Before directories had their own entries, switching one off was a scan setting
+    let enabled = |path: &str| -> bool {
+        map.get("dir_scan_settings")
+        .and_then(|s| s.get(path))
+        .and_then(|s| s.get("enabled"))
+        .and_then(|e| e.as_bool())
+        .unwrap_or(true)
+    };
+
+    let mut directories = Vec::new();
+    for (role, list) in [("Roms", rom_dirs.clone()), ("Chds", chd_dirs), ("Artwork", paths("extra_asset_dirs")), ("Icons", icons)] {
+        for path in list {
+            directories.push(serde_json::json!({ "path": path, "role": role, "enabled": enabled(&path) }));
+        }
+    }
+    directories
+}
+
 // Handle loading old config files with migration
 pub fn load_config(path: &PathBuf) -> Result<AppConfig, Box<dyn std::error::Error>> {
     let json = fs::read_to_string(path)?;
@@ -75,6 +118,93 @@ pub fn load_config(path: &PathBuf) -> Result<AppConfig, Box<dyn std::error::Erro
                                serde_json::to_value(VideoSettings::default()).unwrap());
                     println!("Migrated config: Added video_settings field with defaults");
                 }
+
+                if !map.contains_key("custom_collections") {
+                    map.insert("custom_collections".to_string(), serde_json::json!([]));
+                    println!("Migrated config: Added custom_collections field (default: none)");
+                }
+
+                // Executables gained default launch arguments
+                if let Some(serde_json::Value::Array(executables)) = map.get_mut("mame_executables") {
+                    for exe in executables.iter_mut().filter_map(|e| e.as_object_mut()) {
+                        if !exe.contains_key("data_layout") {
+                            exe.insert("data_layout".to_string(), serde_json::to_value(DataLayout::default()).unwrap());
+                        }
+                        // Folders that weren't isolated used to be moved to the shared directory
+                        if let Some(layout) = exe.get_mut("data_layout").and_then(|l| l.as_object_mut()) {
+                            if !layout.contains_key("shared") {
+                                layout.insert("shared".to_string(), serde_json::json!([]));
+                                println!("Migrated config: Added data_layout.shared field (default: MAME's own folders)");
+                            }
+                        }
+                        if !exe.contains_key("ini_path") {
+                            exe.insert("ini_path".to_string(), serde_json::Value::Null);
+                        }
+                        match exe.get_mut("default_args") {
+                            Some(args) => migrate_arg_string(args, "executable default arguments"),
+                            None => {
+                                exe.insert("default_args".to_string(), serde_json::json!([]));
+                            }
+                        }
+                    }
+                }
+
+                // Custom arguments used to be a single string split on spaces
+                if let Some(args) = map.get_mut("video_settings").and_then(|v| v.get_mut("custom_args")) {
+                    migrate_arg_string(args, "video custom arguments");
+                }
+
+                if !map.contains_key("directories") {
+                    let directories = legacy_directories(map);
+                    println!("Migrated config: Added directories field ({} paths from the old directory lists)", directories.len());
+                    map.insert("directories".to_string(), serde_json::Value::Array(directories));
+                }
+
+                if !map.contains_key("dir_scan_settings") {
+                    map.insert("dir_scan_settings".to_string(), serde_json::json!({}));
+                    println!("Migrated config: Added dir_scan_settings field (default: flat scan of every directory)");
+                }
+
+                // Graphics overrides per game became part of the game's launch profile
+                if !map.contains_key("game_profiles") {
+                    let overrides = map.get_mut("graphics_config")
+                    .and_then(|g| g.get_mut("game_overrides"))
+                    .map(std::mem::take);
+                    let mut profiles = serde_json::Map::new();
+                    if let Some(serde_json::Value::Object(overrides)) = overrides {
+                        for (rom, game_override) in overrides {
+                            profiles.insert(rom, serde_json::json!({
+                                "graphics_preset": game_override.get("preset_name"),
+                                "extra_args": game_override.get("custom_args").cloned().unwrap_or(serde_json::json!([])),
+                                "video": {},
+                                "env": [],
+                            }));
+                        }
+                    }
+                    if let Some(graphics) = map.get_mut("graphics_config").and_then(|g| g.as_object_mut()) {
+                        graphics.insert("game_overrides".to_string(), serde_json::json!({}));
+                    }
+                    println!("Migrated config: Added game_profiles field ({} from graphics overrides)", profiles.len());
+                    map.insert("game_profiles".to_string(), serde_json::Value::Object(profiles));
+                }
+
+                if !map.contains_key("use_ini_files") {
+                    map.insert("use_ini_files".to_string(), serde_json::json!(false));
+                    println!("Migrated config: Added use_ini_files field (default: false)");
+                }
+
+                if !map.contains_key("mame_ini_options") {
+                    map.insert("mame_ini_options".to_string(), serde_json::json!({}));
+                    println!("Migrated config: Added mame_ini_options field (default: none)");
+                }
+
+                // Audit status filter was added to the existing filter settings
+                if let Some(serde_json::Value::Object(filters)) = map.get_mut("filter_settings") {
+                    if !filters.contains_key("audit_filter") {
+                        filters.insert("audit_filter".to_string(), serde_json::json!("All"));
+                        println!("Migrated config: Added filter_settings.audit_filter field (default: All)");
+                    }
+                }
             }
 
             // Now try to deserialize the modified JSON
@@ -83,11 +213,16 @@ pub fn load_config(path: &PathBuf) -> Result<AppConfig, Box<dyn std::error::Erro
     }
 }
 
-pub fn get_mame_data_dir() -> PathBuf {
-    let data_dir = dirs::home_dir()
+/// The launcher's data directory, without creating anything in it
+fn mame_data_dir_path() -> PathBuf {
+    dirs::home_dir()
     .unwrap_or_else(|| PathBuf::from("."))
     .join(".mame")
-    .join("rust-mame-launcher");
+    .join("rust-mame-launcher")
+}
+
+pub fn get_mame_data_dir() -> PathBuf {
+    let data_dir = mame_data_dir_path();
 
     // Create the directory structure if it doesn't exist
     let subdirs = [
@@ -102,6 +237,18 @@ pub fn get_mame_data_dir() -> PathBuf {
     data_dir
 }
 
+/// Directory holding per-executable metadata caches
+pub fn get_metadata_cache_dir() -> PathBuf {
+    let cache_dir = dirs::cache_dir()
+    .unwrap_or_else(|| PathBuf::from("."))
+    .join("rust-mame-launcher")
+    .join("metadata");
+
+    let _ = fs::create_dir_all(&cache_dir);
+
+    cache_dir
+}
+
 // NEW: Helper function to validate and clean up icon path
 pub fn validate_icon_path(path: &Option<PathBuf>) -> Option<PathBuf> {
     if let Some(p) = path {
@@ -167,3 +314,196 @@ pub fn get_default_icons_path(rom_dirs: &[PathBuf]) -> Option<PathBuf> {
 
     None
 }
+
+/// Where the captured output of each game's launches is kept
+pub fn get_session_log_dir(rom_name: &str) -> PathBuf {
+    let log_dir = get_mame_data_dir().join("logs").join(rom_name);
+    let _ = fs::create_dir_all(&log_dir);
+    log_dir
+}
+
+/// Where the executables' own directories go by default
+pub fn get_executables_dir() -> PathBuf {
+    get_mame_data_dir().join("executables")
+}
+
+fn executable_dir_name(mame: &MameExecutable) -> String {
+    mame.name.chars()
+    .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c.to_ascii_lowercase() } else { '_' })
+    .collect()
+}
+
+/// The directory an executable's isolated data folders go in. Until `assign_data_roots`
+/// fixes it, it is derived from the executable's name.
+pub fn get_executable_data_dir(mame: &MameExecutable) -> PathBuf {
+    if let Some(root) = &mame.data_layout.root {
+        return root.clone();
+    }
+    get_executables_dir().join(executable_dir_name(mame))
+}
+
+/// Fix the directory in `executables_dir` of every executable that doesn't have one yet,
+/// so renaming it later doesn't leave its data behind. Names that come out the same get
+/// a numbered suffix. Returns whether any directory was fixed.
+pub fn assign_data_roots(executables: &mut [MameExecutable], executables_dir: &Path) -> bool {
+    let mut changed = false;
+    for index in 0..executables.len() {
+        if executables[index].data_layout.root.is_some() {
+            continue;
+        }
+        let base = executables_dir.join(executable_dir_name(&executables[index]));
+        let mut root = base.clone();
+        let mut suffix = 2;
+        while executables.iter().any(|m| m.data_layout.root.as_ref() == Some(&root)) {
+            root = PathBuf::from(format!("{}-{}", base.display(), suffix));
+            suffix += 1;
+        }
+        executables[index].data_layout.root = Some(root);
+        changed = true;
+    }
+    changed
+}
+
+/// Where `folder` is for `mame`, shared or isolated as its data layout says, or None
+/// when MAME's own settings decide. MAME creates the folder itself the first time it
+/// writes there.
+pub fn get_data_folder(mame: &MameExecutable, folder: DataFolder) -> Option<PathBuf> {
+    let base = if mame.data_layout.isolated.contains(&folder) {
+        get_executable_data_dir(mame)
+    } else if mame.data_layout.shared.contains(&folder) {
+        mame_data_dir_path()
+    } else {
+        return None;
+    };
+    Some(base.join(folder.dir_name()))
+}
+
+/// Where the MAME ini files generated from the launcher's settings are written
+pub fn get_managed_ini_dir() -> PathBuf {
+    let ini_dir = get_mame_data_dir().join("ini");
+    let _ = fs::create_dir_all(&ini_dir);
+    ini_dir
+}
+
+/// File remembering the member listing of every scanned ROM archive
+pub fn get_archive_cache_path() -> PathBuf {
+    let cache_dir = dirs::cache_dir()
+    .unwrap_or_else(|| PathBuf::from("."))
+    .join("rust-mame-launcher");
+
+    let _ = fs::create_dir_all(&cache_dir);
+
+    cache_dir.join("archives.json")
+}
+
+/// Result of checking a managed directory against what its role expects to find
+#[derive(Clone, Debug)]
+pub enum DirCheck {
+    Ok(String),
+    Warning(String),
+    Missing,
+    Checking,
+}
+
+/// Look for the files a directory with `role` normally holds
+pub fn check_directory(path: &Path, role: DirRole) -> DirCheck {
+    let Ok(entries) = fs::read_dir(path) else {
+        return DirCheck::Missing;
+    };
+
+    let mut files = Vec::new();
+    let mut folders = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+            folders.push(name);
+        } else {
+            files.push(name);
+        }
+    }
+    let count = |exts: &[&str]| files.iter().filter(|f| exts.iter().any(|e| f.ends_with(e))).count();
+
+    match role {
+        DirRole::Roms => match count(&[".zip", ".7z"]) + folders.len() {
+            0 => DirCheck::Warning("No .zip or .7z sets or set folders found".to_string()),
+            n => DirCheck::Ok(format!("{} sets or folders", n)),
+        },
+        DirRole::Chds => {
+            // CHDs live in a folder named after their set
+            let chds = count(&[".chd"]) + folders.iter()
+            .filter(|f| fs::read_dir(path.join(f)).into_iter().flatten().flatten()
+                .any(|e| e.file_name().to_string_lossy().to_lowercase().ends_with(".chd")))
+            .count();
+            match chds {
+                0 => DirCheck::Warning("No .chd files found".to_string()),
+                n => DirCheck::Ok(format!("{} sets with CHDs", n)),
+            }
+        }
+        DirRole::Samples => match count(&[".zip"]) + folders.len() {
+            0 => DirCheck::Warning("No sample archives or folders found".to_string()),
+            n => DirCheck::Ok(format!("{} sample sets", n)),
+        },
+        DirRole::Artwork => {
+            let known = ["snap", "cabinets", "titles", "artwork", "icons", "marquees", "flyers"];
+            let found: Vec<&str> = known.iter()
+            .filter(|k| folders.iter().any(|f| f == *k) || files.iter().any(|f| *f == format!("{}.zip", k)))
+            .copied()
+            .collect();
+            if found.is_empty() {
+                DirCheck::Warning("No snap, cabinets, titles or artwork folders found".to_string())
+            } else {
+                DirCheck::Ok(found.join(", "))
+            }
+        }
+        DirRole::Icons => match count(&[".ico"]) + count(&["icons.zip"]) {
+            0 => DirCheck::Warning("No .ico files found".to_string()),
+            n => DirCheck::Ok(format!("{} icons", n)),
+        },
+        DirRole::Cheats => match count(&[".7z", ".zip", ".xml"]) + folders.len() {
+            0 => DirCheck::Warning("No cheat.7z, cheat.zip or cheat files found".to_string()),
+            n => DirCheck::Ok(format!("{} cheat files", n)),
+        },
+        DirRole::History => match count(&[".xml", ".dat"]) {
+            0 => DirCheck::Warning("No .xml or .dat files found".to_string()),
+            n => DirCheck::Ok(format!("{} DAT files", n)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrated_arg_strings_keep_windows_paths() {
+        let mut value = serde_json::json!(r"-rompath C:\MAME\roms  -video 'd3d'");
+        migrate_arg_string(&mut value, "test arguments");
+        assert_eq!(value, serde_json::json!(["-rompath", r"C:\MAME\roms", "-video", "'d3d'"]));
+    }
+
+    #[test]
+    fn data_roots_stay_put_and_apart() {
+        let named = |name: &str| MameExecutable { name: name.to_string(), ..MameExecutable::default() };
+        let mut executables = vec![named("MAME 0.261"), named("MAME 0.261"), named("MAME/0.261")];
+        let dir = Path::new("/data/executables");
+        assert!(assign_data_roots(&mut executables, dir));
+
+        let roots: Vec<PathBuf> = executables.iter().map(|m| m.data_layout.root.clone().unwrap()).collect();
+        assert_eq!(roots, [dir.join("mame_0.261"), dir.join("mame_0.261-2"), dir.join("mame_0.261-3")]);
+
+        executables[0].name = "Renamed".to_string();
+        assert!(!assign_data_roots(&mut executables, dir));
+        assert_eq!(get_executable_data_dir(&executables[0]), roots[0]);
+    }
+
+    #[test]
+    fn data_folders_follow_mame_unless_placed() {
+        let mut mame = MameExecutable { data_layout: DataLayout { root: Some(PathBuf::from("/own")), ..DataLayout::default() }, ..MameExecutable::default() };
+        assert_eq!(get_data_folder(&mame, DataFolder::Nvram), None);
+
+        mame.data_layout.shared.push(DataFolder::Nvram);
+        mame.data_layout.isolated.push(DataFolder::Cfg);
+        assert_eq!(get_data_folder(&mame, DataFolder::Nvram), Some(mame_data_dir_path().join("nvram")));
+        assert_eq!(get_data_folder(&mame, DataFolder::Cfg), Some(PathBuf::from("/own/cfg")));
+    }
+}
@@ -0,0 +1,569 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::fs;
+use crate::launch::LaunchPlan;
+use crate::models::{DumpStatus, GameMetadata, RomEntry};
+use crate::rom_utils::archive::{archive_set_name, ROM_ARCHIVE_EXTENSIONS};
+
+// ============= LAUNCH FAILURE DIAGNOSIS =============
+//
+// MAME explains why a machine didn't start in its output, one line per problem. The
+// session log of a failed launch is read back here and each line MAME is known to print
+// is turned into a Problem, with the set a missing file comes from worked out from the
+// listxml data and rejected options traced back to the launch layer that set them.
+
+/// Where a set's file is loaded from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FileSource {
+    Own,
+    Parent,
+    Bios,
+    Device,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Problem {
+    /// A ROM file MAME couldn't find, with the set that should hold it
+    MissingFile { file: String, set: String, source: FileSource, tried_in: Vec<String> },
+    /// A ROM file that was found but isn't the dump MAME expects
+    BadDump { file: String, set: String, source: FileSource },
+    MissingChd { disk: String, set: String },
+    /// A parent, BIOS or device set needed by the game that isn't in any ROM directory
+    MissingSet { set: String, source: FileSource },
+    MissingSamples { set: String },
+    /// An option MAME doesn't know, with the layer that added it
+    UnknownOption { option: String, layer: Option<String> },
+    BadOptionValue { option: String, layer: Option<String> },
+    /// An argument with no option before it, left out of the command by the launcher
+    IgnoredArgument { arg: String, layer: String },
+    /// The video backend failed to start
+    VideoBackend { backend: Option<String>, message: String, layer: Option<String> },
+    UnknownSystem(String),
+}
+
+impl Problem {
+    /// One line describing the problem
+    pub fn summary(&self) -> String {
+        match self {
+            Problem::MissingFile { file, set, source, tried_in } => {
+                let searched = if tried_in.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (searched {})", tried_in.join(", "))
+                };
+                format!("Missing {} from {}{}", file, describe_set(set, *source), searched)
+            }
+            Problem::BadDump { file, set, source } => {
+                format!("Wrong checksum or length for {} from {}", file, describe_set(set, *source))
+            }
+            Problem::MissingChd { disk, set } => format!("Missing CHD {} for {}", disk, set),
+            Problem::MissingSet { set, source } => match source {
+                FileSource::Own => format!("Missing set {}", set),
+                FileSource::Parent => format!("Missing parent set {}", set),
+                FileSource::Bios => format!("Missing BIOS set {}", set),
+                FileSource::Device => format!("Missing device set {}", set),
+            },
+            Problem::MissingSamples { set } => format!("Missing sample archive {}", set),
+            Problem::UnknownOption { option, .. } => format!("MAME doesn't know the option -{}", option),
+            Problem::BadOptionValue { option, .. } => format!("MAME rejected the value given for -{}", option),
+            Problem::IgnoredArgument { arg, layer } => format!("'{}' in {} has no option before it and was left out", arg, layer),
+            Problem::VideoBackend { message, .. } => format!("The video backend failed: {}", message),
+            Problem::UnknownSystem(name) => format!("MAME has no machine named '{}'", name),
+        }
+    }
+
+    /// What to do about the problem
+    pub fn suggestion(&self, rom: &str) -> String {
+        match self {
+            Problem::MissingFile { set, source, .. } | Problem::MissingSet { set, source } => match source {
+                FileSource::Own => format!("The {} set is incomplete: replace it with one that matches this MAME version.", set),
+                FileSource::Parent => format!("Add the parent set {} to a ROM directory; {} loads shared files from it.", set, rom),
+                FileSource::Bios => format!("Add the BIOS set {} ({}.zip) to a ROM directory.", set, set),
+                FileSource::Device => format!("Add the device set {} ({}.zip) to a ROM directory.", set, set),
+            },
+            Problem::BadDump { file, set, .. } => {
+                format!("{} in {} is a different dump than this MAME version expects: update the {} set.", file, set, set)
+            }
+            Problem::MissingChd { disk, set } => {
+                format!("Put {}.chd in a folder named {} inside a ROM or CHD directory.", disk, set)
+            }
+            Problem::MissingSamples { set } => {
+                format!("Add {}.zip to a samples directory; the game runs without it but some sounds will be silent.", set)
+            }
+            Problem::UnknownOption { option, layer } => match layer {
+                Some(layer) => format!("Remove -{} from {}; this MAME version doesn't support it.", option, layer),
+                None => format!("Remove -{} from the launch command; this MAME version doesn't support it.", option),
+            },
+            Problem::BadOptionValue { option, layer } => match layer {
+                Some(layer) => format!("Correct the value of -{} in {}.", option, layer),
+                None => format!("Correct the value of -{} in the launch command.", option),
+            },
+            Problem::IgnoredArgument { arg, layer } => {
+                format!("Put the option that takes '{}' before it in {}, or remove it.", arg, layer)
+            }
+            Problem::VideoBackend { backend, layer, .. } => {
+                let backend = backend.as_deref().map(|b| format!(" '{}'", b)).unwrap_or_default();
+                match layer {
+                    Some(layer) => format!("Change the video backend{} set in {} to auto or another backend.", backend, layer),
+                    None => "Set a video backend that this system supports, or leave it on auto.".to_string(),
+                }
+            }
+            Problem::UnknownSystem(name) if name == rom => {
+                format!("Launch {} with a MAME executable that includes it, or refresh the game list.", rom)
+            }
+            Problem::UnknownSystem(name) => {
+                format!("'{}' was read as the machine name: check the launch arguments for a stray value.", name)
+            }
+        }
+    }
+}
+
+fn describe_set(set: &str, source: FileSource) -> String {
+    match source {
+        FileSource::Own => set.to_string(),
+        FileSource::Parent => format!("parent {}", set),
+        FileSource::Bios => format!("BIOS {}", set),
+        FileSource::Device => format!("device {}", set),
+    }
+}
+
+/// The suggestions for `problems` with repeats left out, in order
+pub fn suggestions(problems: &[Problem], rom: &str) -> Vec<String> {
+    let mut suggestions = Vec::new();
+    for problem in problems {
+        let suggestion = problem.suggestion(rom);
+        if !suggestions.contains(&suggestion) {
+            suggestions.push(suggestion);
+        }
+    }
+    suggestions
+}
+
+/// `rom` followed by the sets it loads files from through `romof`
+fn romof_chain<'a>(rom: &str, metadata: &'a HashMap<String, GameMetadata>) -> Vec<&'a GameMetadata> {
+    let mut chain = Vec::new();
+    let mut next = metadata.get(rom);
+    while let Some(game) = next {
+        if chain.iter().any(|g: &&GameMetadata| g.name == game.name) {
+            break;
+        }
+        chain.push(game);
+        next = game.romof.as_deref().and_then(|name| metadata.get(name));
+    }
+    chain
+}
+
+fn parent_source(game: &GameMetadata) -> FileSource {
+    if game.is_bios { FileSource::Bios } else { FileSource::Parent }
+}
+
+/// The set that holds `file` for `rom`: the set itself, the parent or BIOS its `merge`
+/// name leads to, or one of its devices
+fn file_source(rom: &str, file: &str, metadata: &HashMap<String, GameMetadata>) -> (String, FileSource) {
+    let chain = romof_chain(rom, metadata);
+    let Some(game) = chain.first() else {
+        return (rom.to_string(), FileSource::Own);
+    };
+
+    if let Some(entry) = game.roms.iter().find(|r| r.name.eq_ignore_ascii_case(file)) {
+        let Some(mut merge) = entry.merge.clone() else {
+            return (rom.to_string(), FileSource::Own);
+        };
+        // Follow the merge name up the chain to the set that holds the file itself
+        for ancestor in &chain[1..] {
+            match ancestor.roms.iter().find(|r| r.name.eq_ignore_ascii_case(&merge)) {
+                Some(r) if r.merge.is_none() => return (ancestor.name.clone(), parent_source(ancestor)),
+                Some(r) => merge = r.merge.clone().unwrap_or_default(),
+                None => {}
+            }
+        }
+        if let Some(ancestor) = chain.get(1) {
+            return (ancestor.name.clone(), parent_source(ancestor));
+        }
+        return (rom.to_string(), FileSource::Own);
+    }
+
+    for set in &chain {
+        for device in &set.device_refs {
+            if metadata.get(device).is_some_and(|d| d.roms.iter().any(|r| r.name.eq_ignore_ascii_case(file))) {
+                return (device.clone(), FileSource::Device);
+            }
+        }
+    }
+
+    (rom.to_string(), FileSource::Own)
+}
+
+/// The set whose folder should hold the CHD `name` for `rom`, if `name` is one of its disks
+fn disk_set(rom: &str, name: &str, metadata: &HashMap<String, GameMetadata>) -> Option<(String, String)> {
+    let disk = name.strip_suffix(".chd").unwrap_or(name);
+    let chain = romof_chain(rom, metadata);
+    let entry = chain.first()?.disks.iter().find(|d| d.name.eq_ignore_ascii_case(disk))?;
+
+    // Disks shared with a parent are looked for in the parent's folder too
+    let mut set = rom.to_string();
+    if let Some(merge) = &entry.merge {
+        if let Some(ancestor) = chain[1..].iter().find(|g| g.disks.iter().any(|d| d.name.eq_ignore_ascii_case(merge) && d.merge.is_none())) {
+            set = ancestor.name.clone();
+        }
+    }
+    Some((entry.name.clone(), set))
+}
+
+fn option_layer(plan: &LaunchPlan, option: &str) -> Option<String> {
+    plan.option_sources.iter().find(|(name, _)| name == option).map(|(_, layer)| layer.clone())
+}
+
+/// The option name in MAME's "unknown option" and "illegal value" messages
+fn option_name(text: &str) -> String {
+    text.trim()
+    .trim_start_matches(['-', '"', '\''])
+    .split(|c: char| c.is_whitespace() || "\"';:=".contains(c))
+    .next()
+    .unwrap_or_default()
+    .to_lowercase()
+}
+
+fn is_video_error(lower: &str) -> bool {
+    let video = ["video", "renderer", "bgfx", "opengl", "direct3d", "d3d", "vulkan", "display"].iter().any(|w| lower.contains(w));
+    let error = ["fail", "unable", "error", "could not", "not supported", "unsupported"].iter().any(|w| lower.contains(w));
+    video && error
+}
+
+/// Read the problems MAME reported in the output of a launch of `plan`
+pub fn diagnose(output: &str, plan: &LaunchPlan, metadata: &HashMap<String, GameMetadata>) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let rom = plan.rom.as_str();
+
+    for line in output.lines() {
+        let line = line.trim();
+        // The launcher's own header lines hold the command, not MAME's output
+        if line.starts_with('#') {
+            continue;
+        }
+        let lower = line.to_lowercase();
+        let problem = if let Some(pos) = line.find(" NOT FOUND") {
+            let rest = &line[pos + " NOT FOUND".len()..];
+            // Optional files and files with no known dump don't stop the machine
+            if rest.starts_with(" BUT OPTIONAL") || rest.contains("NO GOOD DUMP KNOWN") {
+                continue;
+            }
+            let file = line[..pos].split_whitespace().next().unwrap_or_default().to_string();
+            let tried_in = rest.split_once("(tried in ")
+            .map(|(_, t)| t.trim_end_matches(')').split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+
+            match disk_set(rom, &file, metadata) {
+                Some((disk, set)) => Problem::MissingChd { disk, set },
+                None => {
+                    let (set, source) = file_source(rom, &file, metadata);
+                    Problem::MissingFile { file, set, source, tried_in }
+                }
+            }
+        } else if ["WRONG CHECKSUMS", "INCORRECT CHECKSUM", "WRONG LENGTH", "INCORRECT LENGTH"].iter().any(|m| line.contains(m)) {
+            let file = line.split_whitespace().next().unwrap_or_default().to_string();
+            let (set, source) = file_source(rom, &file, metadata);
+            Problem::BadDump { file, set, source }
+        } else if let Some(pos) = lower.find("unknown option") {
+            let text = line[pos + "unknown option".len()..].trim_start_matches([':', ' ']);
+            let option = option_name(text);
+            Problem::UnknownOption { layer: option_layer(plan, &option), option }
+        } else if lower.contains("illegal") && lower.contains(" value for ") {
+            let option = option_name(&line[lower.find(" value for ").unwrap_or(0) + " value for ".len()..]);
+            Problem::BadOptionValue { layer: option_layer(plan, &option), option }
+        } else if let Some(pos) = lower.find("unknown system") {
+            let name = line[pos + "unknown system".len()..].trim().trim_matches(['\'', '"', ':', ' ']).to_string();
+            Problem::UnknownSystem(name)
+        } else if is_video_error(&lower) {
+            // Only the first line of a failing backend is worth showing
+            if problems.iter().any(|p| matches!(p, Problem::VideoBackend { .. })) {
+                continue;
+            }
+            let backend = plan.args.iter().position(|a| a == "-video").and_then(|i| plan.args.get(i + 1)).cloned();
+            Problem::VideoBackend { backend, message: line.to_string(), layer: option_layer(plan, "video") }
+        } else {
+            continue;
+        };
+
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    }
+
+    // Arguments the launcher left out may be why MAME misread the rest
+    for (arg, layer) in &plan.ignored_args {
+        problems.push(Problem::IgnoredArgument { arg: arg.clone(), layer: layer.clone() });
+    }
+
+    problems
+}
+
+// ============= END LAUNCH FAILURE DIAGNOSIS =============
+
+// ============= PRE-LAUNCH CHECKS =============
+//
+// A quick look for the sets a game can't start without, done before MAME is spawned.
+// Only presence is checked, by name, so it costs a few file lookups; whether the files
+// inside are right is what the audit is for.
+
+/// Files MAME refuses to start without
+fn is_required(rom: &RomEntry) -> bool {
+    !rom.optional && rom.status != DumpStatus::NoDump
+}
+
+/// The entry of `dir` called `name`, ignoring case like the ROM scanners do
+fn find_entry(dir: &Path, name: &str) -> Option<PathBuf> {
+    let exact = dir.join(name);
+    if exact.exists() {
+        return Some(exact);
+    }
+    fs::read_dir(dir).ok()?
+    .flatten()
+    .map(|entry| entry.path())
+    .find(|path| path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.eq_ignore_ascii_case(name)))
+}
+
+/// Whether a set archive or folder named `set` is in one of `dirs`, ignoring case
+fn set_present(set: &str, dirs: &[PathBuf]) -> bool {
+    dirs.iter().any(|dir| {
+        ROM_ARCHIVE_EXTENSIONS.iter().any(|ext| dir.join(format!("{}.{}", set, ext)).is_file())
+        || dir.join(set).is_dir()
+        // Only when the exact names miss is the whole directory read
+        || fs::read_dir(dir).into_iter().flatten().flatten().any(|entry| {
+            let path = entry.path();
+            let name = if path.is_dir() { path.file_name().and_then(|n| n.to_str()) } else { archive_set_name(&path) };
+            name.is_some_and(|n| n.eq_ignore_ascii_case(set))
+        })
+    })
+}
+
+fn chd_present(disk: &str, sets: &[&GameMetadata], dirs: &[PathBuf]) -> bool {
+    let file = format!("{}.chd", disk);
+    dirs.iter().any(|dir| sets.iter().any(|set| {
+        find_entry(dir, &set.name)
+        .and_then(|folder| find_entry(&folder, &file))
+        .is_some_and(|path| path.is_file())
+    }))
+}
+
+/// Check that the parent, BIOS and device sets, CHDs and samples `rom` needs are in
+/// `rompath` and `sample_dirs`. Samples are only checked when a samples directory is set,
+/// since MAME otherwise looks in its own default.
+pub fn check_dependencies(
+    rom: &str,
+    metadata: &HashMap<String, GameMetadata>,
+    rompath: &[PathBuf],
+    sample_dirs: &[PathBuf],
+) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    let chain = romof_chain(rom, metadata);
+    let Some(game) = chain.first() else {
+        return problems;
+    };
+
+    let mut push = |problem: Problem| {
+        if !problems.contains(&problem) {
+            problems.push(problem);
+        }
+    };
+
+    for entry in game.roms.iter().filter(|r| is_required(r) && r.merge.is_some()) {
+        let (set, source) = file_source(rom, &entry.name, metadata);
+        if source != FileSource::Own && !set_present(&set, rompath) {
+            push(Problem::MissingSet { set, source });
+        }
+    }
+
+    for device in &game.device_refs {
+        let needs_roms = metadata.get(device).is_some_and(|d| d.roms.iter().any(is_required));
+        if needs_roms && !set_present(device, rompath) {
+            push(Problem::MissingSet { set: device.clone(), source: FileSource::Device });
+        }
+    }
+
+    for disk in game.disks.iter().filter(|d| !d.optional && d.status != DumpStatus::NoDump) {
+        if !chd_present(&disk.name, &chain, rompath) {
+            let set = disk_set(rom, &disk.name, metadata).map(|(_, set)| set).unwrap_or_else(|| rom.to_string());
+            push(Problem::MissingChd { disk: disk.name.clone(), set });
+        }
+    }
+
+    if !game.samples.is_empty() && !sample_dirs.is_empty() {
+        let set = game.sampleof.clone().unwrap_or_else(|| rom.to_string());
+        if !set_present(&set, sample_dirs) {
+            push(Problem::MissingSamples { set });
+        }
+    }
+
+    problems
+}
+
+// ============= END PRE-LAUNCH CHECKS =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DiskEntry;
+
+    fn rom(name: &str, merge: Option<&str>) -> RomEntry {
+        RomEntry {
+            name: name.to_string(),
+            size: 16,
+            crc: Some("00000001".to_string()),
+            merge: merge.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn machine(name: &str, romof: Option<&str>, roms: Vec<RomEntry>) -> GameMetadata {
+        GameMetadata {
+            name: name.to_string(),
+            romof: romof.map(str::to_string),
+            roms,
+            ..Default::default()
+        }
+    }
+
+    /// A BIOS, a parent with a device and a CHD, and a clone that merges from both
+    fn metadata() -> HashMap<String, GameMetadata> {
+        let bios = GameMetadata { is_bios: true, ..machine("neogeo", None, vec![rom("sp-s2.sp1", None)]) };
+        let device = machine("ng_memcard", None, vec![rom("memcard.bin", None)]);
+        let parent = GameMetadata {
+            device_refs: vec!["ng_memcard".to_string()],
+            disks: vec![DiskEntry { name: "mslug".to_string(), ..Default::default() }],
+            ..machine("mslug", Some("neogeo"), vec![rom("sp-s2.sp1", Some("sp-s2.sp1")), rom("201-p1.p1", None)])
+        };
+        let clone = GameMetadata {
+            parent: Some("mslug".to_string()),
+            is_clone: true,
+            device_refs: vec!["ng_memcard".to_string()],
+            disks: vec![DiskEntry { name: "mslug".to_string(), merge: Some("mslug".to_string()), ..Default::default() }],
+            samples: vec!["grunt".to_string()],
+            sampleof: Some("mslug".to_string()),
+            ..machine("mslugb", Some("mslug"), vec![
+                rom("sp-s2.sp1", Some("sp-s2.sp1")),
+                rom("201-p1.p1", Some("201-p1.p1")),
+                rom("b-p2.p2", None),
+            ])
+        };
+        [bios, device, parent, clone].into_iter().map(|m| (m.name.clone(), m)).collect()
+    }
+
+    fn plan(rom: &str) -> LaunchPlan {
+        LaunchPlan {
+            rom: rom.to_string(),
+            program: "mame".to_string(),
+            working_dir: None,
+            env: Vec::new(),
+            args: vec!["-video".to_string(), "bgfx".to_string(), "-foo".to_string(), rom.to_string()],
+            option_sources: vec![("video".to_string(), "Game".to_string()), ("foo".to_string(), "Executable".to_string())],
+            ignored_args: vec![("stray".to_string(), "Global".to_string())],
+        }
+    }
+
+    #[test]
+    fn missing_files_are_traced_to_the_set_that_holds_them() {
+        let output = "\
+# Command: mame mslugb
+sp-s2.sp1    NOT FOUND (tried in mslugb mslug neogeo)
+201-p1.p1    NOT FOUND (tried in mslugb mslug neogeo)
+b-p2.p2      NOT FOUND (tried in mslugb mslug neogeo)
+memcard.bin  NOT FOUND (tried in ng_memcard mslugb)
+extra.bin    NOT FOUND BUT OPTIONAL (tried in mslugb)
+mslug.chd    NOT FOUND
+b-p2.p2      NOT FOUND (tried in mslugb mslug neogeo)
+";
+        let problems = diagnose(output, &plan("mslugb"), &metadata());
+        let sets: Vec<(&str, FileSource)> = problems.iter().filter_map(|p| match p {
+            Problem::MissingFile { set, source, .. } => Some((set.as_str(), *source)),
+            _ => None,
+        }).collect();
+        assert_eq!(sets, [
+            ("neogeo", FileSource::Bios),
+            ("mslug", FileSource::Parent),
+            ("mslugb", FileSource::Own),
+            ("ng_memcard", FileSource::Device),
+        ]);
+
+        let Problem::MissingFile { tried_in, .. } = &problems[0] else { panic!() };
+        assert_eq!(tried_in, &["mslugb", "mslug", "neogeo"]);
+        assert!(problems.contains(&Problem::MissingChd { disk: "mslug".to_string(), set: "mslug".to_string() }));
+        assert!(!problems.iter().any(|p| matches!(p, Problem::MissingFile { file, .. } if file == "extra.bin")));
+    }
+
+    #[test]
+    fn rejected_options_name_their_layer() {
+        let output = "\
+Error: unknown option: -foo
+Illegal value for video = bgfx; reverting to auto
+201-p1.p1 WRONG CHECKSUMS:
+Unknown system 'mslugx'
+Unable to initialize bgfx renderer
+bgfx: failed to create video device
+";
+        let problems = diagnose(output, &plan("mslug"), &metadata());
+        assert_eq!(problems, [
+            Problem::UnknownOption { option: "foo".to_string(), layer: Some("Executable".to_string()) },
+            Problem::BadOptionValue { option: "video".to_string(), layer: Some("Game".to_string()) },
+            Problem::BadDump { file: "201-p1.p1".to_string(), set: "mslug".to_string(), source: FileSource::Own },
+            Problem::UnknownSystem("mslugx".to_string()),
+            Problem::VideoBackend {
+                backend: Some("bgfx".to_string()),
+                message: "Unable to initialize bgfx renderer".to_string(),
+                layer: Some("Game".to_string()),
+            },
+            Problem::IgnoredArgument { arg: "stray".to_string(), layer: "Global".to_string() },
+        ]);
+    }
+
+    /// A fresh rompath directory holding the given files and folders, removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let dir = std::env::temp_dir().join(format!("rmameui-diagnosis-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            for file in files {
+                let path = dir.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                if !file.ends_with('/') {
+                    fs::write(&path, b"").unwrap();
+                }
+            }
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn dependencies_are_found_ignoring_case() {
+        let roms = TempDir::new("complete", &["MSLUGB.zip", "mslug.7z", "NeoGeo.ZIP", "ng_memcard/memcard.bin", "MSLUG/Mslug.CHD"]);
+        let samples = TempDir::new("samples", &["Mslug.zip"]);
+        let problems = check_dependencies("mslugb", &metadata(), std::slice::from_ref(&roms.0), std::slice::from_ref(&samples.0));
+        assert_eq!(problems, []);
+    }
+
+    #[test]
+    fn missing_dependencies_are_reported() {
+        let roms = TempDir::new("partial", &["mslugb.zip"]);
+        let samples = TempDir::new("nosamples", &[]);
+        let problems = check_dependencies("mslugb", &metadata(), std::slice::from_ref(&roms.0), std::slice::from_ref(&samples.0));
+        assert_eq!(problems, [
+            Problem::MissingSet { set: "neogeo".to_string(), source: FileSource::Bios },
+            Problem::MissingSet { set: "mslug".to_string(), source: FileSource::Parent },
+            Problem::MissingSet { set: "ng_memcard".to_string(), source: FileSource::Device },
+            Problem::MissingChd { disk: "mslug".to_string(), set: "mslug".to_string() },
+            Problem::MissingSamples { set: "mslug".to_string() },
+        ]);
+
+        // Without a samples directory MAME looks in its own default, so samples aren't checked
+        let problems = check_dependencies("mslugb", &metadata(), std::slice::from_ref(&roms.0), &[]);
+        assert!(!problems.iter().any(|p| matches!(p, Problem::MissingSamples { .. })));
+    }
+}
@@ -0,0 +1,771 @@
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::time::Instant;
+use crate::models::{AppConfig, DataFolder, DirRole, MameExecutable, VideoOverrides, VideoSettings};
+use crate::config::{get_data_folder, get_managed_ini_dir, get_session_log_dir};
+use crate::diagnosis::Problem;
+
+// ============= LAUNCH PLAN =============
+//
+// Every launch goes through a LaunchPlan, built from these layers in increasing order of
+// precedence:
+//
+//   1. search paths from the directories manager (-rompath, -samplepath, -cheatpath),
+//      and -inipath when the launcher keeps MAME ini files
+//   2. the executable's shared or isolated data folders (-nvram_directory, -cfg_directory,
+//      -homepath, ...), then its default arguments
+//   3. the video settings, then their custom arguments
+//   4. the graphics preset (the game's own preset, or the global one)
+//   5. the game's extra arguments
+//
+// Video settings, the preset, environment, working directory and the executable itself
+// can be set per game by its launch profile; fields the profile leaves alone follow the
+// global settings.
+//
+// An option set by a later layer replaces the value from an earlier one in place, so
+// MAME never sees the same option twice with different values.
+
+/// MAME options that are switches: `-name` turns them on and `-noname` off
+pub const BOOLEAN_OPTIONS: &[&str] = &[
+    "window", "maximize", "waitvsync", "syncrefresh", "keepaspect", "filter", "unevenstretch",
+    "unevenstretchx", "unevenstretchy", "autostretchxy", "intoverscan", "triplebuffer",
+    "switchres", "cheat", "skip_gameinfo", "throttle", "sleep", "autosave", "rewind",
+    "mouse", "joystick", "lightgun", "multikeyboard", "multimouse", "steadykey", "offscreen_reload",
+    "sound", "samples", "readconfig", "writeconfig", "gl_glsl", "gl_pbo", "gl_vbo",
+    "glsl_filter", "hlsl_enable", "bgfx_debug", "ui_active", "confirm_quit", "nvram_save",
+    "artwork_crop", "use_backdrops", "use_overlays", "use_bezels", "use_cpanels", "use_marquees",
+    "autoframeskip", "refreshspeed", "lowlatency", "burnin", "snapbilinear", "debug", "verbose",
+    "oslog", "log", "drc", "drc_use_c", "coin_lockout", "natural", "joystick_contradictory",
+    "ui_mouse", "plugins", "console", "exit_after_playback",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OptionValue {
+    Flag(bool),
+    Value(String),
+}
+
+/// MAME options in the order they were first set, each with its latest value and the
+/// layer that set it
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    options: Vec<(String, OptionValue, String)>,
+    layer: String,
+    /// Arguments that couldn't be placed, each with the layer that gave it
+    ignored: Vec<(String, String)>,
+}
+
+impl LaunchOptions {
+    /// Name the layer that the options set from now on come from
+    pub fn set_layer(&mut self, layer: impl Into<String>) {
+        self.layer = layer.into();
+    }
+
+    pub fn set(&mut self, name: &str, value: OptionValue) {
+        match self.options.iter_mut().find(|(n, _, _)| n == name) {
+            Some((_, existing, layer)) => {
+                *existing = value;
+                layer.clone_from(&self.layer);
+            }
+            None => self.options.push((name.to_string(), value, self.layer.clone())),
+        }
+    }
+
+    pub fn set_value(&mut self, name: &str, value: impl Into<String>) {
+        self.set(name, OptionValue::Value(value.into()));
+    }
+
+    pub fn set_flag(&mut self, name: &str, on: bool) {
+        self.set(name, OptionValue::Flag(on));
+    }
+
+    fn unset(&mut self, name: &str) {
+        self.options.retain(|(n, _, _)| n != name);
+    }
+
+    fn is_flag(&self, name: &str) -> bool {
+        matches!(self.get(name), Some(OptionValue::Flag(_)))
+    }
+
+    /// Read options from command-line style arguments. A known switch may be written
+    /// `-name` or `-noname`, and so may any switch an earlier layer set; any other option
+    /// takes the following argument as its value unless that looks like another option.
+    pub fn apply_args(&mut self, args: &[String]) {
+        let mut i = 0;
+        while i < args.len() {
+            let Some(name) = args[i].strip_prefix('-').filter(|n| !n.is_empty()) else {
+                // A stray value with no option before it can't be placed; leave it out
+                self.ignored.push((args[i].clone(), self.layer.clone()));
+                i += 1;
+                continue;
+            };
+            let name = name.to_lowercase();
+
+            if BOOLEAN_OPTIONS.contains(&name.as_str()) {
+                // Switches also accept an explicit 0 or 1
+                let explicit = args.get(i + 1).and_then(|v| match v.as_str() {
+                    "0" => Some(false),
+                    "1" => Some(true),
+                    _ => None,
+                });
+                if explicit.is_some() {
+                    i += 1;
+                }
+                self.set_flag(&name, explicit.unwrap_or(true));
+            } else if let Some(off) = name.strip_prefix("no").filter(|n| BOOLEAN_OPTIONS.contains(n) || self.is_flag(n)) {
+                self.set_flag(off, false);
+            } else if let Some(value) = args.get(i + 1).filter(|v| !v.starts_with('-') || v.parse::<f64>().is_ok()) {
+                self.set_value(&name, value.clone());
+                i += 1;
+            } else {
+                // An earlier layer may have turned this switch off before it was known to be one
+                let negated = format!("no{}", name);
+                if self.get(&negated) == Some(&OptionValue::Flag(true)) {
+                    self.unset(&negated);
+                }
+                self.set_flag(&name, true);
+            }
+            i += 1;
+        }
+    }
+
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, value, _) in &self.options {
+            match value {
+                OptionValue::Flag(true) => args.push(format!("-{}", name)),
+                OptionValue::Flag(false) => args.push(format!("-no{}", name)),
+                OptionValue::Value(value) => {
+                    args.push(format!("-{}", name));
+                    args.push(value.clone());
+                }
+            }
+        }
+        args
+    }
+
+    /// The layered options for `rom`, or the global ones shared by every game when `rom`
+    /// is None. The executable's default arguments are left out without `mame`.
+    pub fn resolve(rom: Option<&str>, mame: Option<&MameExecutable>, config: &AppConfig, rompath: &[PathBuf]) -> Self {
+        let mut options = LaunchOptions::default();
+
+        options.set_layer("the directories manager");
+        if !rompath.is_empty() {
+            options.set_value("rompath", join_paths(rompath));
+        }
+        for (name, role) in [("samplepath", DirRole::Samples), ("cheatpath", DirRole::Cheats), ("historypath", DirRole::History)] {
+            let dirs = config.dirs_with_role(role);
+            if !dirs.is_empty() {
+                options.set_value(name, join_paths(&dirs));
+            }
+        }
+
+        if config.use_ini_files {
+            // The managed directory goes first, then wherever MAME looks by itself so the
+            // other ini files it reads are still found
+            options.set_layer("the MAME ini file settings");
+            options.set_value("inipath", managed_inipath(mame.and_then(|m| m.ini_path.as_deref())));
+        }
+
+        if let Some(mame) = mame {
+            options.set_layer(format!("the data folders of {}", mame.name));
+            // Only launches get these: -listxml, -verifyroms and -showconfig neither read nor
+            // write the folders, and the MAME configuration dialog lists them as the
+            // launcher's options next to what MAME would use
+            for folder in DataFolder::all() {
+                if let Some(path) = get_data_folder(mame, folder) {
+                    options.set_value(folder.option(), path.to_string_lossy().into_owned());
+                }
+            }
+
+            options.set_layer(format!("the default arguments of {}", mame.name));
+            options.apply_args(&mame.default_args);
+        }
+
+        let profile = rom.and_then(|rom| config.game_profiles.get(rom)).cloned().unwrap_or_default();
+        let video = profile.video.apply_to(&config.video_settings);
+        let game_settings = format!("the game settings for {}", rom.unwrap_or_default());
+
+        // Overridden fields are merged first: an override back to MAME's default has to
+        // remove the global option rather than add another
+        options.set_layer(if profile.video == VideoOverrides::default() {
+            "the video settings".to_string()
+        } else {
+            format!("the video settings with {}", game_settings)
+        });
+        apply_video_settings(&mut options, &video);
+        options.set_layer(if profile.video.custom_args.is_some() {
+            format!("the custom arguments in {}", game_settings)
+        } else {
+            "the video settings' custom arguments".to_string()
+        });
+        options.apply_args(&video.custom_args);
+
+        let graphics = &config.graphics_config;
+        let preset = profile.graphics_preset.as_deref()
+        .and_then(|name| graphics.get_preset(name))
+        .unwrap_or_else(|| graphics.get_game_preset(rom.unwrap_or_default()));
+        options.set_layer(format!("the graphics preset '{}'", preset.name));
+        options.apply_args(&preset.to_mame_args());
+
+        // Presets set the backend, filtering and aspect themselves, so the game's own
+        // choices for those go back on top
+        options.set_layer(format!("the video overrides in {}", game_settings));
+        apply_video_overrides(&mut options, &profile.video);
+
+        options.set_layer(format!("the extra arguments in {}", game_settings));
+        options.apply_args(&profile.extra_args);
+
+        options
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.options.iter().find(|(n, _, _)| n == name).map(|(_, value, _)| value)
+    }
+
+    /// Each option as written in a MAME ini file, switches as 1 or 0
+    pub fn ini_values(&self) -> Vec<(String, String)> {
+        self.options.iter()
+        .map(|(name, value, _)| {
+            let value = match value {
+                OptionValue::Flag(on) => if *on { "1" } else { "0" }.to_string(),
+                OptionValue::Value(value) => value.clone(),
+            };
+            (name.clone(), value)
+        })
+        .collect()
+    }
+
+    /// Each option with the layer that set its final value
+    pub fn sources(&self) -> Vec<(String, String)> {
+        self.options.iter().map(|(name, _, layer)| (name.clone(), layer.clone())).collect()
+    }
+
+    /// Arguments left out because no option came before them, with their layers
+    pub fn ignored_args(&self) -> &[(String, String)] {
+        &self.ignored
+    }
+}
+
+/// MAME's built-in -inipath, searched after the launcher's own ini directory until the
+/// executable's own value has been read from -showconfig
+#[cfg(target_os = "windows")]
+const MAME_INIPATH: &str = ".;ini;ini/presets";
+#[cfg(target_os = "macos")]
+const MAME_INIPATH: &str = "$HOME/Library/Application Support/mame;$HOME/.mame;.;ini";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const MAME_INIPATH: &str = "$HOME/.mame;.;ini";
+
+/// The -inipath with the launcher's ini directory in front of `own`, the executable's
+/// own -inipath
+pub fn managed_inipath(own: Option<&str>) -> String {
+    format!("{};{}", get_managed_ini_dir().to_string_lossy(), own.unwrap_or(MAME_INIPATH))
+}
+
+/// What MAME uses for the options the video settings and presets set when they're not
+/// given, so a game's ini file can undo a global setting it doesn't share
+pub const MAME_OPTION_DEFAULTS: &[(&str, &str)] = &[
+    ("video", "auto"), ("window", "0"), ("maximize", "1"), ("waitvsync", "0"),
+    ("syncrefresh", "0"), ("prescale", "1"), ("keepaspect", "1"), ("filter", "1"),
+    ("numscreens", "1"), ("bgfx_screen_chains", "default"), ("gl_glsl", "0"),
+    ("glsl_shader_mame0", "none"),
+];
+
+/// The global video settings as options. Settings left at MAME's own default add nothing.
+fn apply_video_settings(options: &mut LaunchOptions, video: &VideoSettings) {
+    if video.video_backend != "auto" {
+        options.set_value("video", video.video_backend.clone());
+    }
+    if video.window_mode {
+        options.set_flag("window", true);
+    }
+    if video.maximize {
+        options.set_flag("maximize", true);
+    }
+    if video.wait_vsync {
+        options.set_flag("waitvsync", true);
+    }
+    if video.sync_refresh {
+        options.set_flag("syncrefresh", true);
+    }
+    if video.prescale > 0 {
+        options.set_value("prescale", video.prescale.to_string());
+    }
+    if !video.keep_aspect {
+        options.set_flag("keepaspect", false);
+    }
+    if !video.filter {
+        options.set_flag("filter", false);
+    }
+    if video.num_screens > 1 {
+        options.set_value("numscreens", video.num_screens.to_string());
+    }
+}
+
+/// The overridden video settings a graphics preset also sets. An override back to MAME's
+/// default removes the preset's option.
+fn apply_video_overrides(options: &mut LaunchOptions, overrides: &VideoOverrides) {
+    match overrides.video_backend.as_deref() {
+        Some("auto") => options.unset("video"),
+        Some(backend) => options.set_value("video", backend),
+        None => {}
+    }
+    match overrides.prescale {
+        Some(0) => options.unset("prescale"),
+        Some(prescale) => options.set_value("prescale", prescale.to_string()),
+        None => {}
+    }
+    if let Some(on) = overrides.keep_aspect {
+        options.set_flag("keepaspect", on);
+    }
+    if let Some(on) = overrides.filter {
+        options.set_flag("filter", on);
+    }
+}
+
+/// Split an argument string the way a POSIX shell would: whitespace separates arguments,
+/// single quotes keep everything literal, double quotes keep spaces but allow `\"`, `\\`,
+/// `\$` and `` \` `` escapes, and a backslash outside quotes escapes the next character.
+pub fn parse_args(text: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    // Quotes can produce an empty argument, so track whether one has started
+    let mut in_arg = false;
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("Unbalanced single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c) if "\"\\$`".contains(c) => current.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("Unbalanced double quote".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("Unbalanced double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                // A backslash before a newline joins the lines
+                Some('\n') => {}
+                Some(c) => {
+                    in_arg = true;
+                    current.push(c);
+                }
+                None => return Err("Backslash at the end with nothing to escape".to_string()),
+            },
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            c => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+/// Join arguments into a string `parse_args` reads back unchanged
+pub fn join_args(args: &[String]) -> String {
+    args.iter().map(|a| posix_quote(a)).collect::<Vec<_>>().join(" ")
+}
+
+fn is_plain_arg(arg: &str) -> bool {
+    !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_+=/.,:@%".contains(c))
+}
+
+fn posix_quote(arg: &str) -> String {
+    if is_plain_arg(arg) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+fn join_paths(dirs: &[PathBuf]) -> String {
+    // MAME uses semicolon as separator on all platforms
+    dirs.iter().map(|p| p.to_string_lossy()).collect::<Vec<_>>().join(";")
+}
+
+/// Quote `arg` for a POSIX shell, or for cmd.exe on Windows, leaving plain words alone
+pub fn shell_quote(arg: &str) -> String {
+    if cfg!(target_os = "windows") {
+        cmd_quote(arg)
+    } else {
+        posix_quote(arg)
+    }
+}
+
+/// Quote `arg` for a cmd.exe batch line. `%` is doubled even inside quotes, because cmd
+/// expands variables before it looks at quoting.
+fn cmd_quote(arg: &str) -> String {
+    let escaped = arg.replace('%', "%%");
+    if is_plain_arg(arg) {
+        escaped
+    } else {
+        format!("\"{}\"", escaped.replace('"', "\\\""))
+    }
+}
+
+/// A cmd.exe `set` for one environment variable. The quotes go around the whole
+/// assignment so that nothing after the value, like a trailing space, ends up in it.
+fn cmd_set(key: &str, value: &str) -> String {
+    format!("set \"{}={}\" &&", key, value.replace('%', "%%"))
+}
+
+/// A fully resolved MAME command line
+#[derive(Clone, Debug)]
+pub struct LaunchPlan {
+    pub rom: String,
+    pub program: String,
+    pub working_dir: Option<PathBuf>,
+    pub env: Vec<(String, String)>,
+    pub args: Vec<String>,
+    /// Which layer set each option, for explaining options MAME rejects
+    pub option_sources: Vec<(String, String)>,
+    /// Arguments left out of `args` because no option came before them, with their layers
+    pub ignored_args: Vec<(String, String)>,
+}
+
+impl LaunchPlan {
+    /// Resolve the command for `rom` on `mame`. `rompath` is the expanded list of ROM
+    /// and CHD directories to search.
+    pub fn build(rom: &str, mame: &MameExecutable, config: &AppConfig, rompath: &[PathBuf]) -> Self {
+        let options = LaunchOptions::resolve(Some(rom), Some(mame), config, rompath);
+        let profile = config.game_profiles.get(rom).cloned().unwrap_or_default();
+
+        // The machine name goes last
+        let mut args = options.to_args();
+        args.push(rom.to_string());
+
+        Self {
+            rom: rom.to_string(),
+            program: mame.path.clone(),
+            working_dir: profile.working_dir,
+            env: profile.env,
+            args,
+            option_sources: options.sources(),
+            ignored_args: options.ignored_args().to_vec(),
+        }
+    }
+
+    /// The plan as a command that can be pasted into a shell
+    pub fn shell_command(&self) -> String {
+        let mut parts = Vec::new();
+
+        if let Some(dir) = &self.working_dir {
+            if cfg!(target_os = "windows") {
+                parts.push(format!("cd /d {} &&", shell_quote(&dir.to_string_lossy())));
+            } else {
+                parts.push(format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+            }
+        }
+        for (key, value) in &self.env {
+            if cfg!(target_os = "windows") {
+                parts.push(cmd_set(key, value));
+            } else {
+                parts.push(format!("{}={}", key, shell_quote(value)));
+            }
+        }
+
+        parts.push(shell_quote(&self.program));
+        parts.extend(self.args.iter().map(|a| shell_quote(a)));
+        parts.join(" ")
+    }
+
+    pub fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+        if let Some(dir) = &self.working_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+
+    /// Start MAME with its output going to a new session log
+    pub fn spawn(&self) -> std::io::Result<RunningGame> {
+        println!("Launching {}: {}", self.rom, self.shell_command());
+
+        let log_path = new_session_log_path(&self.rom);
+        let mut log = File::create(&log_path)?;
+        writeln!(log, "{} {}", LOG_STARTED, chrono::Local::now().to_rfc3339())?;
+        writeln!(log, "{} {}", LOG_COMMAND, self.shell_command())?;
+        writeln!(log)?;
+
+        let mut cmd = self.command();
+        cmd.stdout(log.try_clone()?).stderr(log);
+        let child = cmd.spawn()?;
+
+        prune_session_logs(&self.rom);
+        Ok(RunningGame { child, started: Instant::now(), log_path, plan: self.clone() })
+    }
+}
+
+// ============= END LAUNCH PLAN =============
+
+// ============= SESSION LOGS =============
+//
+// Each launch writes MAME's stdout and stderr to its own file under logs/<game>/, with
+// the command at the top and the exit status appended once the process is gone.
+
+const LOG_STARTED: &str = "# Started:";
+const LOG_COMMAND: &str = "# Command:";
+const LOG_EXIT: &str = "# Exit:";
+
+/// Session logs kept per game; older ones are deleted at launch
+const MAX_SESSION_LOGS: usize = 20;
+
+/// Runs shorter than this are treated as failed launches even when MAME exits cleanly
+pub const QUICK_EXIT_SECS: u64 = 5;
+
+/// A launched game that is being tracked until it exits
+pub struct RunningGame {
+    pub child: Child,
+    pub started: Instant,
+    pub log_path: PathBuf,
+    pub plan: LaunchPlan,
+}
+
+/// One past launch of a game, read back from its log
+#[derive(Clone, Debug)]
+pub struct SessionLog {
+    pub path: PathBuf,
+    pub started: String,
+    pub exit: Option<String>,
+}
+
+/// A game that exited with an error or straight after starting
+#[derive(Clone, Debug)]
+pub struct LaunchFailure {
+    pub rom: String,
+    pub log_path: PathBuf,
+    pub exit: String,
+    pub run_secs: u64,
+    pub tail: String,
+    pub problems: Vec<Problem>,
+}
+
+fn new_session_log_path(rom: &str) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    let dir = get_session_log_dir(rom);
+    let mut path = dir.join(format!("{}.log", stamp));
+    let mut n = 1;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.log", stamp, n));
+        n += 1;
+    }
+    path
+}
+
+fn sorted_session_logs(rom: &str) -> Vec<PathBuf> {
+    let mut logs: Vec<PathBuf> = fs::read_dir(get_session_log_dir(rom))
+    .into_iter()
+    .flatten()
+    .flatten()
+    .map(|e| e.path())
+    .filter(|p| p.extension().is_some_and(|e| e == "log"))
+    .collect();
+    // Timestamped names sort oldest first
+    logs.sort();
+    logs
+}
+
+fn prune_session_logs(rom: &str) {
+    let logs = sorted_session_logs(rom);
+    if logs.len() > MAX_SESSION_LOGS {
+        for old in &logs[..logs.len() - MAX_SESSION_LOGS] {
+            let _ = fs::remove_file(old);
+        }
+    }
+}
+
+/// Describe how a process ended
+pub fn describe_exit(status: &ExitStatus) -> String {
+    match status.code() {
+        Some(code) => format!("exit code {}", code),
+        None => "terminated by a signal".to_string(),
+    }
+}
+
+/// Append the exit status and run time to a session log
+pub fn finish_session_log(log_path: &Path, exit: &str, run_secs: u64) {
+    let result = OpenOptions::new().append(true).open(log_path)
+    .and_then(|mut log| writeln!(log, "\n{} {} after {}s", LOG_EXIT, exit, run_secs));
+    if let Err(e) = result {
+        eprintln!("Failed to finish session log {:?}: {}", log_path, e);
+    }
+}
+
+/// The last `lines` lines of a log
+pub fn log_tail(log_path: &Path, lines: usize) -> String {
+    let Ok(file) = File::open(log_path) else {
+        return String::new();
+    };
+    let all: Vec<String> = BufReader::new(file).lines().map_while(Result::ok).collect();
+    all[all.len().saturating_sub(lines)..].join("\n")
+}
+
+/// A game's recent sessions, newest first
+pub fn list_session_logs(rom: &str) -> Vec<SessionLog> {
+    let mut sessions: Vec<SessionLog> = sorted_session_logs(rom).into_iter()
+    .map(|path| {
+        let mut started = String::new();
+        let mut exit = None;
+        if let Ok(file) = File::open(&path) {
+            for line in BufReader::new(file).lines().map_while(Result::ok) {
+                if let Some(rest) = line.strip_prefix(LOG_STARTED) {
+                    started = rest.trim().to_string();
+                } else if let Some(rest) = line.strip_prefix(LOG_EXIT) {
+                    exit = Some(rest.trim().to_string());
+                }
+            }
+        }
+        SessionLog { path, started, exit }
+    })
+    .collect();
+    sessions.reverse();
+    sessions
+}
+
+// ============= END SESSION LOGS =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LaunchProfile;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_args_follows_shell_quoting() {
+        assert_eq!(parse_args("-video  bgfx\t-window").unwrap(), args(&["-video", "bgfx", "-window"]));
+        assert_eq!(parse_args(r#"-bgfx_screen_chains "hlsl,unfiltered" -x 'a b'"#).unwrap(),
+            args(&["-bgfx_screen_chains", "hlsl,unfiltered", "-x", "a b"]));
+        assert_eq!(parse_args(r#"'C:\MAME\roms' "say \"hi\"" a\ b"#).unwrap(),
+            args(&[r"C:\MAME\roms", r#"say "hi""#, "a b"]));
+        assert_eq!(parse_args("-a '' -b \"\"").unwrap(), args(&["-a", "", "-b", ""]));
+        assert_eq!(parse_args("  ").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn parse_args_rejects_unbalanced_quotes() {
+        assert!(parse_args("-x 'open").is_err());
+        assert!(parse_args("-x \"open").is_err());
+        assert!(parse_args("-x \"escaped\\\"").is_err());
+        assert!(parse_args("trailing\\").is_err());
+    }
+
+    #[test]
+    fn join_args_round_trips() {
+        let cases = [
+            args(&["-rompath", "/home/me/roms;/mnt/roms", "pacman"]),
+            args(&["", "-a", ""]),
+            args(&["with space", "it's", r#"quote"d"#, r"C:\MAME\roms", "$HOME", "`cmd`", "tab\there", "new\nline"]),
+        ];
+        for case in cases {
+            let joined = join_args(&case);
+            assert_eq!(parse_args(&joined).unwrap(), case, "{}", joined);
+        }
+        assert_eq!(join_args(&args(&["-video", "bgfx"])), "-video bgfx");
+    }
+
+    #[test]
+    fn later_layers_replace_switches_in_place() {
+        let mut options = LaunchOptions::default();
+        options.set_layer("first");
+        options.apply_args(&args(&["-window", "-video", "opengl", "-bezels", "0"]));
+        options.set_layer("second");
+        options.apply_args(&args(&["-nowindow", "-video", "bgfx"]));
+
+        assert_eq!(options.to_args(), args(&["-nowindow", "-video", "bgfx", "-bezels", "0"]));
+        assert_eq!(options.sources()[0], ("window".to_string(), "second".to_string()));
+    }
+
+    #[test]
+    fn unknown_switches_can_be_negated() {
+        let mut options = LaunchOptions::default();
+        options.apply_args(&args(&["-newswitch"]));
+        options.apply_args(&args(&["-nonewswitch"]));
+        assert_eq!(options.get("newswitch"), Some(&OptionValue::Flag(false)));
+
+        let mut options = LaunchOptions::default();
+        options.apply_args(&args(&["-nootherswitch"]));
+        options.apply_args(&args(&["-otherswitch"]));
+        assert_eq!(options.to_args(), args(&["-otherswitch"]));
+    }
+
+    #[test]
+    fn cmd_lines_quote_the_whole_assignment_and_double_percent() {
+        assert_eq!(cmd_set("MAME_DEBUG", "1"), "set \"MAME_DEBUG=1\" &&");
+        assert_eq!(cmd_set("SDL_HINT", "50% off"), "set \"SDL_HINT=50%% off\" &&");
+        assert_eq!(cmd_quote("-rompath"), "-rompath");
+        assert_eq!(cmd_quote("%HOME%"), "%%HOME%%");
+        assert_eq!(cmd_quote("C:\\MAME\\roms"), "\"C:\\MAME\\roms\"");
+    }
+
+    #[test]
+    fn game_video_overrides_win_over_the_graphics_preset() {
+        let mut config = AppConfig::default();
+        let video = VideoOverrides {
+            video_backend: Some("opengl".to_string()),
+            keep_aspect: Some(false),
+            filter: Some(false),
+            ..VideoOverrides::default()
+        };
+        let profile = LaunchProfile { graphics_preset: Some("CRT Classic".to_string()), video, ..LaunchProfile::default() };
+        config.game_profiles.insert("pacman".to_string(), profile);
+
+        let options = LaunchOptions::resolve(Some("pacman"), None, &config, &[]);
+        assert_eq!(options.get("video"), Some(&OptionValue::Value("opengl".to_string())));
+        assert_eq!(options.get("keepaspect"), Some(&OptionValue::Flag(false)));
+        assert_eq!(options.get("filter"), Some(&OptionValue::Flag(false)));
+        assert!(options.sources().contains(&("filter".to_string(), "the video overrides in the game settings for pacman".to_string())));
+
+        // Without overrides the preset's choices stand, and an override back to auto drops its backend
+        let options = LaunchOptions::resolve(Some("galaga"), None, &config, &[]);
+        assert_eq!(options.get("keepaspect"), Some(&OptionValue::Flag(true)));
+        config.game_profiles.get_mut("pacman").unwrap().video.video_backend = Some("auto".to_string());
+        assert_eq!(LaunchOptions::resolve(Some("pacman"), None, &config, &[]).get("video"), None);
+    }
+
+    #[test]
+    fn stray_values_are_reported_with_their_layer() {
+        let mut options = LaunchOptions::default();
+        options.set_layer("the extra arguments");
+        options.apply_args(&args(&["stray", "-throttle", "1"]));
+
+        assert_eq!(options.get("throttle"), Some(&OptionValue::Flag(true)));
+        assert_eq!(options.ignored_args(), &[("stray".to_string(), "the extra arguments".to_string())]);
+    }
+}
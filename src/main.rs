@@ -6,6 +6,9 @@ mod models;
 mod ui;
 mod rom_utils;
 mod mame_utils;
+mod launch;
+mod diagnosis;
+mod mame_ini;
 mod app;
 mod graphics_presets;  // ← ADD THIS
 
@@ -35,6 +38,7 @@ fn main() -> Result<(), eframe::Error> {
     eframe::run_native(
         "RMAMEUI",
         options,
-        Box::new(|_cc| Ok(Box::new(MyApp::default()))),
+        // new() loads the saved config and starts loading metadata; default() is a blank app
+        Box::new(|_cc| Ok(Box::new(MyApp::new()))),
     )
 }
@@ -0,0 +1,430 @@
+use std::path::{Path, PathBuf};
+use std::fs;
+use crate::launch::{LaunchOptions, BOOLEAN_OPTIONS, MAME_OPTION_DEFAULTS};
+use crate::models::AppConfig;
+
+// ============= MAME INI FILES =============
+//
+// The launcher's settings written out as MAME ini files, so they also apply when a game
+// is started from MAME's own menus. mame.ini holds what every game shares: the search
+// paths, video settings and global preset, on top of any options edited in the MAME
+// configuration dialog, on top of everything the executable's own ini files set. MAME
+// only reads the first mame.ini on its -inipath, so the managed one has to carry the
+// user's own settings along. A game with its own settings gets <game>.ini
+// with only what differs, which MAME reads after mame.ini. Launches still pass every
+// option on the command line, so what the launcher starts doesn't depend on the files
+// being up to date.
+
+/// First line of every generated file; files without it are never touched
+const GENERATED_HEADER: &str = "# Generated by the Rust MAME Launcher from its settings; changes here are overwritten.";
+
+/// Options that only make sense on the command line
+const NOT_IN_INI: &[&str] = &["inipath"];
+
+/// One file as it is on disk and as it would be written. `new` is None for a generated
+/// file that is no longer needed.
+#[derive(Clone, Debug)]
+pub struct IniFile {
+    pub path: PathBuf,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+impl IniFile {
+    pub fn is_changed(&self) -> bool {
+        self.old != self.new
+    }
+
+    pub fn status(&self) -> &'static str {
+        match (&self.old, &self.new) {
+            (None, Some(_)) => "new",
+            (Some(_), None) => "removed",
+            _ if self.is_changed() => "changed",
+            _ => "unchanged",
+        }
+    }
+
+    pub fn diff(&self) -> Vec<DiffLine> {
+        line_diff(self.old.as_deref().unwrap_or_default(), self.new.as_deref().unwrap_or_default())
+    }
+}
+
+/// The lines of `old` and `new` lined up on their longest common subsequence
+pub fn line_diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // common[i][j] is the length of the longest common run of old[i..] and new[j..]
+    let mut common = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            common[i][j] = if old[i] == new[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut diff = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            diff.push(DiffLine::Same(old[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || common[i + 1][j] >= common[i][j + 1]) {
+            diff.push(DiffLine::Removed(old[i].to_string()));
+            i += 1;
+        } else {
+            diff.push(DiffLine::Added(new[j].to_string()));
+            j += 1;
+        }
+    }
+    diff
+}
+
+/// Quote a value the way MAME's ini reader expects when it has spaces in it
+fn ini_value(value: &str) -> String {
+    if value.is_empty() || value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn ini_text(title: &str, values: &[(String, String)]) -> String {
+    let width = values.iter().map(|(name, _)| name.len()).max().unwrap_or(0).max(24) + 1;
+    let mut text = format!("{}\n#\n# {}\n#\n", GENERATED_HEADER, title);
+    for (name, value) in values.iter().filter(|(name, _)| !NOT_IN_INI.contains(&name.as_str())) {
+        text.push_str(&format!("{:width$}{}\n", name, ini_value(value), width = width));
+    }
+    text
+}
+
+fn is_generated(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|text| text.starts_with(GENERATED_HEADER))
+}
+
+/// The ini options for `rom` that differ from the global ones. Options the global
+/// settings set but the game's don't are put back to MAME's default where it's known.
+fn game_values(global: &LaunchOptions, game: &LaunchOptions) -> Vec<(String, String)> {
+    let mut values: Vec<(String, String)> = game.ini_values().into_iter()
+    .filter(|(name, _)| game.get(name) != global.get(name))
+    .collect();
+
+    for (name, _) in global.ini_values() {
+        if game.get(&name).is_none() {
+            if let Some((_, default)) = MAME_OPTION_DEFAULTS.iter().find(|(n, _)| *n == name) {
+                values.push((name, default.to_string()));
+            }
+        }
+    }
+    values
+}
+
+/// Work out the files the settings call for in `ini_dir`, alongside what is there now.
+/// `own` is what the executable's own ini files set, as -showconfig reports it without
+/// the managed directory on the -inipath.
+pub fn plan_ini_files(config: &AppConfig, rompath: &[PathBuf], ini_dir: &Path, own: &[ConfigOption]) -> Vec<IniFile> {
+    let global = LaunchOptions::resolve(None, None, config, rompath);
+
+    // MAME's own values first, then the edited options; the launcher passes its own on
+    // the command line anyway, so where both set one the launcher's value is written to
+    // keep the two in step
+    let mut shared: Vec<(String, String)> = own.iter().map(|o| (o.name.clone(), o.value.clone())).collect();
+    let mut edits: Vec<(String, String)> = config.mame_ini_options.iter()
+    .map(|(name, value)| (name.clone(), value.clone()))
+    .collect();
+    edits.sort();
+    for (name, value) in edits.into_iter().chain(global.ini_values()) {
+        match shared.iter_mut().find(|(n, _)| *n == name) {
+            Some(existing) => existing.1 = value,
+            None => shared.push((name, value)),
+        }
+    }
+    let mut wanted = vec![(
+        ini_dir.join("mame.ini"),
+        Some(ini_text("Settings shared by every game", &shared)),
+    )];
+
+    let mut roms: Vec<&String> = config.game_profiles.keys().collect();
+    roms.sort();
+    for rom in roms {
+        let game = LaunchOptions::resolve(Some(rom), None, config, rompath);
+        let values = game_values(&global, &game);
+        let text = (!values.is_empty()).then(|| ini_text(&format!("Settings for {}", rom), &values));
+        wanted.push((ini_dir.join(format!("{}.ini", rom)), text));
+    }
+
+    // Generated files for games that no longer have settings of their own
+    let mut stale: Vec<PathBuf> = fs::read_dir(ini_dir).into_iter().flatten().flatten()
+    .map(|e| e.path())
+    .filter(|p| p.extension().is_some_and(|e| e == "ini") && !wanted.iter().any(|(w, _)| w == p) && is_generated(p))
+    .collect();
+    stale.sort();
+    wanted.extend(stale.into_iter().map(|path| (path, None)));
+
+    wanted.into_iter()
+    .filter_map(|(path, new)| {
+        let old = fs::read_to_string(&path).ok();
+        // Leave alone anything the launcher didn't write
+        if old.as_ref().is_some_and(|text| !text.starts_with(GENERATED_HEADER)) {
+            eprintln!("Not replacing {:?}: it wasn't generated by the launcher", path);
+            return None;
+        }
+        if old.is_none() && new.is_none() {
+            return None;
+        }
+        Some(IniFile { path, old, new })
+    })
+    .collect()
+}
+
+/// Write or remove the files that changed, returning how many were
+pub fn write_ini_files(files: &[IniFile]) -> std::io::Result<usize> {
+    let mut written = 0;
+    for file in files.iter().filter(|f| f.is_changed()) {
+        match &file.new {
+            Some(text) => fs::write(&file.path, text)?,
+            None if is_generated(&file.path) => fs::remove_file(&file.path)?,
+            None => continue,
+        }
+        written += 1;
+    }
+    Ok(written)
+}
+
+// ============= END MAME INI FILES =============
+
+// ============= EFFECTIVE CONFIGURATION =============
+//
+// What `mame -showconfig` reports: every option MAME knows with the value it ends up with
+// after its own ini files are read, grouped under the section comments it prints.
+
+/// How an option's value is edited. MAME doesn't say, so it's inferred from the name and
+/// the current value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OptionKind {
+    Switch,
+    Integer,
+    Float,
+    PathList,
+    Text,
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigOption {
+    pub name: String,
+    pub section: String,
+    pub value: String,
+    pub kind: OptionKind,
+}
+
+fn option_kind(name: &str, value: &str) -> OptionKind {
+    if BOOLEAN_OPTIONS.contains(&name) {
+        OptionKind::Switch
+    } else if name.ends_with("path") || name.ends_with("_directory") || value.contains(';') {
+        OptionKind::PathList
+    } else if value.parse::<i64>().is_ok() {
+        OptionKind::Integer
+    } else if value.contains('.') && value.parse::<f64>().is_ok() {
+        OptionKind::Float
+    } else {
+        OptionKind::Text
+    }
+}
+
+/// Read the options out of `-showconfig` output
+pub fn parse_showconfig(text: &str) -> Vec<ConfigOption> {
+    let mut options = Vec::new();
+    let mut section = String::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix('#') {
+            // Section titles sit between bare "#" lines
+            let comment = comment.trim();
+            if !comment.is_empty() {
+                section = comment.to_string();
+            }
+            continue;
+        }
+        let Some((name, value)) = line.split_once(char::is_whitespace) else {
+            if !line.is_empty() {
+                options.push(ConfigOption { name: line.to_string(), section: section.clone(), value: String::new(), kind: option_kind(line, "") });
+            }
+            continue;
+        };
+        let value = value.trim();
+        let value = value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value);
+        options.push(ConfigOption {
+            name: name.to_string(),
+            section: section.clone(),
+            value: value.to_string(),
+            kind: option_kind(name, value),
+        });
+    }
+    options
+}
+
+/// Expand a search path entry the way MAME does before using it: a leading `~` is the
+/// home directory, and `$NAME` or `${NAME}` (also `%NAME%` on Windows) is the variable's
+/// value, or nothing when it isn't set
+pub fn expand_path_entry(entry: &str) -> String {
+    let mut expanded = String::new();
+    let mut rest = entry;
+    if let Some(after) = rest.strip_prefix('~').filter(|a| a.is_empty() || a.starts_with(['/', '\\'])) {
+        if let Some(home) = dirs::home_dir() {
+            expanded.push_str(&home.to_string_lossy());
+            rest = after;
+        }
+    }
+
+    let var = |name: &str| std::env::var(name).unwrap_or_default();
+    let is_name = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    while let Some(pos) = rest.find(|c| c == '$' || (cfg!(target_os = "windows") && c == '%')) {
+        expanded.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        let (value, used) = if rest[pos..].starts_with('%') {
+            match after.find('%') {
+                Some(end) if end > 0 => (var(&after[..end]), end + 1),
+                _ => ("%".to_string(), 0),
+            }
+        } else if let Some(braced) = after.strip_prefix('{') {
+            match braced.find('}') {
+                Some(end) => (var(&braced[..end]), end + 2),
+                None => ("$".to_string(), 0),
+            }
+        } else {
+            let end = after.find(|c| !is_name(c)).unwrap_or(after.len());
+            if end == 0 { ("$".to_string(), 0) } else { (var(&after[..end]), end) }
+        };
+        expanded.push_str(&value);
+        rest = &after[used..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+// ============= END EFFECTIVE CONFIGURATION =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::LaunchProfile;
+
+    const SHOWCONFIG: &str = "\
+#
+# CORE SEARCH PATH OPTIONS
+#
+homepath                  .
+rompath                   \"roms;/srv/mame roms\"
+
+#
+# CORE SCREEN OPTIONS
+#
+brightness                1.0
+numscreens                1
+window                    0
+video                     auto
+bgfx_screen_chains
+";
+
+    #[test]
+    fn showconfig_options_keep_their_sections() {
+        let options = parse_showconfig(SHOWCONFIG);
+        let found: Vec<(&str, &str, &str, OptionKind)> = options.iter()
+        .map(|o| (o.name.as_str(), o.section.as_str(), o.value.as_str(), o.kind))
+        .collect();
+        assert_eq!(found, [
+            ("homepath", "CORE SEARCH PATH OPTIONS", ".", OptionKind::PathList),
+            ("rompath", "CORE SEARCH PATH OPTIONS", "roms;/srv/mame roms", OptionKind::PathList),
+            ("brightness", "CORE SCREEN OPTIONS", "1.0", OptionKind::Float),
+            ("numscreens", "CORE SCREEN OPTIONS", "1", OptionKind::Integer),
+            ("window", "CORE SCREEN OPTIONS", "0", OptionKind::Switch),
+            ("video", "CORE SCREEN OPTIONS", "auto", OptionKind::Text),
+            ("bgfx_screen_chains", "CORE SCREEN OPTIONS", "", OptionKind::Text),
+        ]);
+    }
+
+    #[test]
+    fn line_diff_lines_up_common_lines() {
+        assert_eq!(line_diff("a\nb\nc\n", "a\nc\nd\n"), [
+            DiffLine::Same("a".to_string()),
+            DiffLine::Removed("b".to_string()),
+            DiffLine::Same("c".to_string()),
+            DiffLine::Added("d".to_string()),
+        ]);
+        assert_eq!(line_diff("", "x"), [DiffLine::Added("x".to_string())]);
+        assert_eq!(line_diff("x", ""), [DiffLine::Removed("x".to_string())]);
+        assert!(line_diff("same\n", "same").iter().all(|l| matches!(l, DiffLine::Same(_))));
+    }
+
+    /// The value `name` is given in generated ini text
+    fn ini_setting<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+        text.lines()
+        .filter_map(|line| line.split_once(char::is_whitespace))
+        .find(|(n, _)| *n == name)
+        .map(|(_, value)| value.trim())
+    }
+
+    #[test]
+    fn ini_files_layer_mame_settings_edits_and_launcher_options() {
+        let ini_dir = std::env::temp_dir().join(format!("rmameui-ini-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&ini_dir);
+        fs::create_dir_all(&ini_dir).unwrap();
+        fs::write(ini_dir.join("galaga.ini"), "# written by hand\nbezels 0\n").unwrap();
+        fs::write(ini_dir.join("oldgame.ini"), format!("{}\nthrottle 0\n", GENERATED_HEADER)).unwrap();
+
+        let mut config = AppConfig::default();
+        config.mame_ini_options.insert("numscreens".to_string(), "2".to_string());
+        for rom in ["pacman", "galaga"] {
+            let profile = LaunchProfile { extra_args: vec!["-nothrottle".to_string()], ..LaunchProfile::default() };
+            config.game_profiles.insert(rom.to_string(), profile);
+        }
+        let own = parse_showconfig(SHOWCONFIG);
+        let files = plan_ini_files(&config, &[PathBuf::from("/launcher/roms")], &ini_dir, &own);
+
+        let paths: Vec<String> = files.iter().map(|f| f.path.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        assert_eq!(paths, ["mame.ini", "pacman.ini", "oldgame.ini"]);
+
+        let mame_ini = files[0].new.as_deref().unwrap();
+        assert!(mame_ini.starts_with(GENERATED_HEADER));
+        assert_eq!(ini_setting(mame_ini, "brightness"), Some("1.0"));
+        assert_eq!(ini_setting(mame_ini, "numscreens"), Some("2"));
+        assert_eq!(ini_setting(mame_ini, "rompath"), Some("/launcher/roms"));
+        assert_eq!(ini_setting(mame_ini, "bgfx_screen_chains"), Some("\"\""));
+        assert_eq!(files[0].status(), "new");
+
+        assert_eq!(ini_setting(files[1].new.as_deref().unwrap(), "throttle"), Some("0"));
+        assert_eq!(files[2].status(), "removed");
+
+        assert_eq!(write_ini_files(&files).unwrap(), 3);
+        assert!(!ini_dir.join("oldgame.ini").exists());
+        assert!(fs::read_to_string(ini_dir.join("galaga.ini")).unwrap().starts_with("# written by hand"));
+        assert!(plan_ini_files(&config, &[PathBuf::from("/launcher/roms")], &ini_dir, &own).iter().all(|f| !f.is_changed()));
+
+        let _ = fs::remove_dir_all(&ini_dir);
+    }
+
+    #[test]
+    fn path_entries_expand_like_mame() {
+        std::env::set_var("RMAMEUI_TEST_ROMS", "/srv/roms");
+        let home = dirs::home_dir().unwrap().to_string_lossy().into_owned();
+
+        assert_eq!(expand_path_entry("$RMAMEUI_TEST_ROMS/arcade"), "/srv/roms/arcade");
+        assert_eq!(expand_path_entry("${RMAMEUI_TEST_ROMS}_extra"), "/srv/roms_extra");
+        assert_eq!(expand_path_entry("~/mame/roms"), format!("{}/mame/roms", home));
+        assert_eq!(expand_path_entry("$RMAMEUI_TEST_UNSET/roms"), "/roms");
+        assert_eq!(expand_path_entry("roms~1/$/${open"), "roms~1/$/${open");
+        assert_eq!(expand_path_entry("roms"), "roms");
+    }
+}
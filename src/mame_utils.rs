@@ -1,10 +1,20 @@
-use std::process::Command;
+use std::process::{Child, ChildStdout, Command, Stdio};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use rayon::prelude::*;
-use crate::models::{GameMetadata, VideoSettings};
-use crate::config::get_mame_data_dir;
-use crate::graphics_presets::GraphicsConfig;
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::Sender;
+use std::time::UNIX_EPOCH;
+use serde::{Deserialize, Serialize};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::Reader;
+use crate::models::{
+    GameMetadata, DriverInfo, DumpStatus, BiosSet, RomEntry, DiskEntry,
+    ChipEntry, DisplayEntry, InputInfo, ControlEntry, DipSwitch, DipValue, FeatureEntry,
+};
 
 pub fn get_mame_version(exec_path: &str) -> String {
     if let Ok(output) = Command::new(exec_path)
@@ -17,112 +27,118 @@ pub fn get_mame_version(exec_path: &str) -> String {
         }
 }
 
-pub fn load_mame_metadata_parallel_with_exec(exec_path: &str) -> HashMap<String, GameMetadata> {
-    println!("Running MAME -listxml...");
+/// Run `-showconfig`, which prints every option with the value MAME ends up with after
+/// reading its ini files. `extra_args` should be the ones that change where it looks.
+pub fn get_mame_showconfig(exec_path: &str, extra_args: &[String]) -> Result<String, String> {
     let output = Command::new(exec_path)
-    .arg("-listxml")
+    .args(extra_args)
+    .arg("-showconfig")
     .output()
-    .expect("Failed to run mame -listxml");
-
-    let xml_str = String::from_utf8_lossy(&output.stdout);
-    println!("XML output size: {} bytes", xml_str.len());
-
-    let entries: Vec<_> = xml_str.split("<machine ").skip(1).collect();
-    println!("Found {} machine entries", entries.len());
-
-    let metadata: HashMap<String, GameMetadata> = entries
-    .into_par_iter()
-    .filter_map(|entry| {
-        // The machine tag attributes are on the first line
-        let first_line = entry.lines().next()?;
-
-        // Extract name attribute
-        let name = first_line.split("name=\"")
-        .nth(1)?
-        .split('"')
-        .next()?;
-
-        // Check for parent/clone status - look for both cloneof and romof
-        let parent = if first_line.contains("cloneof=\"") {
-            first_line.split("cloneof=\"")
-            .nth(1)
-            .and_then(|s| s.split('"').next())
-            .map(|s| s.to_string())
-        } else if first_line.contains("romof=\"") {
-            // Some MAME versions use romof instead of/in addition to cloneof
-            first_line.split("romof=\"")
-            .nth(1)
-            .and_then(|s| s.split('"').next())
-            .map(|s| s.to_string())
-        } else {
-            None
-        };
-        let is_clone = parent.is_some();
-
-        // Debug clone detection for specific games
-        if name.starts_with("1944") || name.starts_with("simpsons") || name.starts_with("1943") || name == "kov2" {
-            println!("METADATA DEBUG - ROM {}: parent={:?}, is_clone={}", name, parent, is_clone);
-            if first_line.len() > 100 {
-                println!("  First line: {}...", &first_line[..100]);
-            } else {
-                println!("  First line: {}", first_line);
-            }
+    .map_err(|e| format!("Failed to run {}: {}", exec_path, e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("MAME -showconfig failed: {}", stderr.lines().next().unwrap_or("no output")));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Progress messages sent by a background metadata load
+pub enum MetadataProgress {
+    LoadingCache,
+    RunningMame,
+    Parsing { parsed: usize, bytes_received: usize },
+    Indexing,
+    Done { version: String, metadata: HashMap<String, GameMetadata> },
+    Failed(String),
+    Cancelled,
+}
+
+/// MAME's `-listxml` output as it arrives, counting bytes for progress. Reading fails
+/// once the load is cancelled, and MAME is killed when this is dropped.
+struct ListxmlOutput<'a> {
+    child: Child,
+    stdout: ChildStdout,
+    bytes_received: Arc<AtomicUsize>,
+    cancel: &'a AtomicBool,
+}
+
+impl Read for ListxmlOutput<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            return Err(std::io::Error::other("cancelled"));
         }
+        let n = self.stdout.read(buf)?;
+        self.bytes_received.fetch_add(n, Ordering::Relaxed);
+        Ok(n)
+    }
+}
 
-        // Check if it's a device, BIOS, or mechanical machine
-        let is_device = entry.contains("isdevice=\"yes\"");
-        let is_bios = entry.contains("isbios=\"yes\"");
-        let is_mechanical = entry.contains("ismechanical=\"yes\"");
-        let runnable = entry.contains("runnable=\"no\"");
-
-        let description = entry.lines()
-        .find(|l| l.contains("<description>"))
-        .and_then(|l| l.split_once('>'))
-        .and_then(|(_, r)| r.split_once('<'))
-        .map(|(d, _)| d.trim().to_string())
-        .unwrap_or_default();
-
-        let year = entry.lines()
-        .find(|l| l.contains("<year>"))
-        .and_then(|l| l.split_once('>'))
-        .and_then(|(_, r)| r.split_once('<'))
-        .map(|(d, _)| d.trim().to_string())
-        .unwrap_or_default();
-
-        let manufacturer = entry.lines()
-        .find(|l| l.contains("<manufacturer>"))
-        .and_then(|l| l.split_once('>'))
-        .and_then(|(_, r)| r.split_once('<'))
-        .map(|(d, _)| d.trim().to_string())
-        .unwrap_or_default();
-
-        let controls = entry.lines()
-        .find(|l| l.contains("<control "))
-        .and_then(|l| l.split("type=\"").nth(1))
-        .and_then(|s| s.split('"').next())
-        .unwrap_or_default()
-        .to_string();
-
-        Some((
-            name.to_string(),
-              GameMetadata {
-                  name: name.to_string(),            // ← FIXED
-              description,                       // ← FIXED
-              year,
-              manufacturer,
-              controls,                          // ← FIXED
-              is_device,
-              is_bios,
-              is_mechanical,
-              runnable,
-              parent,
-              is_clone,
-              driver_status: None,
-              emulation_status: None,
-              }
-        ))
-    })
-    .collect();
+impl Drop for ListxmlOutput<'_> {
+    fn drop(&mut self) {
+        // Does nothing if MAME already exited
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Start `-listxml` with its output piped back
+fn spawn_listxml<'a>(exec_path: &str, cancel: &'a AtomicBool) -> Result<ListxmlOutput<'a>, String> {
+    println!("Running MAME -listxml...");
+    let mut child = Command::new(exec_path)
+    .arg("-listxml")
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .map_err(|e| format!("Failed to start MAME ({}): {}", exec_path, e))?;
+
+    let stdout = child.stdout.take().ok_or("MAME stdout was not captured")?;
+    Ok(ListxmlOutput { child, stdout, bytes_received: Arc::new(AtomicUsize::new(0)), cancel })
+}
+
+/// Load metadata for one executable, preferring the on-disk cache.
+///
+/// Meant to run on a background thread; every stage is reported through `progress`
+/// and `cancel` is polled between chunks of work.
+pub fn load_mame_metadata_with_progress(
+    exec_path: &str,
+    cache_path: &Path,
+    force_rebuild: bool,
+    progress: &Sender<MetadataProgress>,
+    cancel: &AtomicBool,
+) -> Result<(String, HashMap<String, GameMetadata>), String> {
+    let version = get_mame_version(exec_path);
+
+    if !force_rebuild {
+        let _ = progress.send(MetadataProgress::LoadingCache);
+        if let Some(metadata) = load_cached_metadata(cache_path, exec_path) {
+            return Ok((version, metadata));
+        }
+    }
+
+    let _ = progress.send(MetadataProgress::RunningMame);
+    let output = spawn_listxml(exec_path, cancel)?;
+    let bytes_received = output.bytes_received.clone();
+
+    // The XML is parsed as MAME writes it, so the whole document is never held in memory
+    let result = parse_listxml(BufReader::with_capacity(1 << 20, output), |parsed| {
+        if parsed % 500 == 0 {
+            let bytes_received = bytes_received.load(Ordering::Relaxed);
+            let _ = progress.send(MetadataProgress::Parsing { parsed, bytes_received });
+        }
+        !cancel.load(Ordering::Relaxed)
+    });
+    if cancel.load(Ordering::Relaxed) {
+        return Err("cancelled".to_string());
+    }
+    let metadata = result.map_err(|e| format!("Failed to parse MAME -listxml output: {}", e))?;
+    println!("XML output size: {} bytes", bytes_received.load(Ordering::Relaxed));
+
+    if metadata.is_empty() {
+        return Err("MAME -listxml output contained no machines".to_string());
+    }
+
+    let _ = progress.send(MetadataProgress::Indexing);
 
     // Debug summary
     let clone_count = metadata.values().filter(|m| m.is_clone).count();
@@ -133,231 +149,476 @@ pub fn load_mame_metadata_parallel_with_exec(exec_path: &str) -> HashMap<String,
     println!("  Parent games: {}", parent_count);
     println!("  Clone games: {}", clone_count);
 
-    // Show a few examples of parsed clones
-    println!("\nExample parsed clones:");
-    for (name, meta) in metadata.iter().filter(|(_, m)| m.is_clone).take(5) {
-        println!("  {} -> parent: {:?}", name, meta.parent);
+    if let Err(e) = save_cached_metadata(cache_path, exec_path, &version, &metadata) {
+        eprintln!("Failed to write metadata cache {:?}: {}", cache_path, e);
     }
 
-    metadata
+    Ok((version, metadata))
 }
 
-// UPDATED FUNCTION WITH VIDEO SETTINGS
-pub fn launch_rom_with_mame_tracked(
-    rom_name: &str,
-    rom_dirs: &[PathBuf],
-    extra_rom_dirs: &[PathBuf],
-    mame_executable: &str,
-    graphics_config: &GraphicsConfig,
-    video_settings: &VideoSettings,  // ADD THIS PARAMETER
-) -> Result<std::process::Child, Box<dyn std::error::Error>> {
-    let mut cmd = Command::new(mame_executable);
-
-    // Build rompath argument
-    let all_dirs = rom_dirs.iter().chain(extra_rom_dirs.iter());
-    let separator = ";"; // MAME uses semicolon on all platforms
-    let rom_paths = all_dirs
-    .map(|p| p.to_string_lossy())
-    .collect::<Vec<_>>()
-    .join(separator);
-
-    cmd.arg("-rompath").arg(&rom_paths);
-
-    // Apply graphics settings
-    let preset = graphics_config.get_game_preset(rom_name);
-    for arg in preset.to_mame_args() {
-        cmd.arg(arg);
-    }
-
-    // APPLY VIDEO SETTINGS
-    if video_settings.video_backend != "auto" {
-        cmd.arg("-video").arg(&video_settings.video_backend);
-    }
-
-    if video_settings.window_mode {
-        cmd.arg("-window");
-    }
+// ============= METADATA CACHE =============
 
-    if video_settings.maximize {
-        cmd.arg("-maximize");
-    }
+/// Bump whenever `GameMetadata` changes shape so stale caches are rebuilt
+const METADATA_CACHE_VERSION: u32 = 1;
 
-    if video_settings.wait_vsync {
-        cmd.arg("-waitvsync");
-    }
+/// On-disk form of a parsed `-listxml`, tied to the binary it came from
+#[derive(Serialize, Deserialize)]
+struct MetadataCacheFile {
+    format_version: u32,
+    executable_size: u64,
+    executable_mtime: u64,
+    mame_version: String,
+    machines: HashMap<String, GameMetadata>,
+}
 
-    if video_settings.sync_refresh {
-        cmd.arg("-syncrefresh");
+/// Resolve a bare executable name like `mame` through `PATH`
+pub fn resolve_executable_path(exec_path: &str) -> Option<PathBuf> {
+    let path = Path::new(exec_path);
+    if path.components().count() > 1 || path.is_file() {
+        return path.is_file().then(|| path.to_path_buf());
     }
 
-    if video_settings.prescale > 0 {
-        cmd.arg("-prescale").arg(video_settings.prescale.to_string());
-    }
+    std::env::var_os("PATH").and_then(|paths| {
+        std::env::split_paths(&paths)
+        .map(|dir| dir.join(exec_path))
+        .find(|candidate| candidate.is_file())
+    })
+}
 
-    if !video_settings.keep_aspect {
-        cmd.arg("-nokeepaspect");
-    }
+/// Size and modification time (seconds since epoch) of a MAME binary
+pub fn executable_fingerprint(exec_path: &str) -> Option<(u64, u64)> {
+    let meta = fs::metadata(resolve_executable_path(exec_path)?).ok()?;
+    let mtime = meta.modified().ok()?
+    .duration_since(UNIX_EPOCH).ok()?
+    .as_secs();
+    Some((meta.len(), mtime))
+}
 
-    if !video_settings.filter {
-        cmd.arg("-nofilter");
+/// Load cached metadata if the cache still matches the executable on disk
+pub fn load_cached_metadata(cache_path: &Path, exec_path: &str) -> Option<HashMap<String, GameMetadata>> {
+    let (size, mtime) = executable_fingerprint(exec_path)?;
+    let bytes = fs::read(cache_path).ok()?;
+
+    match serde_json::from_slice::<MetadataCacheFile>(&bytes) {
+        Ok(cache) if cache.format_version == METADATA_CACHE_VERSION
+            && cache.executable_size == size
+            && cache.executable_mtime == mtime => {
+            println!("Loaded {} machines from metadata cache {:?}", cache.machines.len(), cache_path);
+            Some(cache.machines)
+        }
+        Ok(_) => {
+            println!("Metadata cache {:?} is stale, MAME binary changed", cache_path);
+            None
+        }
+        Err(e) => {
+            eprintln!("Ignoring unreadable metadata cache {:?}: {}", cache_path, e);
+            None
+        }
     }
+}
 
-    if video_settings.num_screens > 1 {
-        cmd.arg("-numscreens").arg(video_settings.num_screens.to_string());
+pub fn save_cached_metadata(
+    cache_path: &Path,
+    exec_path: &str,
+    mame_version: &str,
+    metadata: &HashMap<String, GameMetadata>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (size, mtime) = executable_fingerprint(exec_path)
+    .ok_or_else(|| format!("Cannot stat MAME executable {}", exec_path))?;
+
+    if let Some(parent) = cache_path.parent() {
+        fs::create_dir_all(parent)?;
     }
 
-    // Add custom arguments if any
-    if !video_settings.custom_args.is_empty() {
-        for arg in video_settings.custom_args.split_whitespace() {
-            cmd.arg(arg);
-        }
-    }
+    let cache = MetadataCacheFile {
+        format_version: METADATA_CACHE_VERSION,
+        executable_size: size,
+        executable_mtime: mtime,
+        mame_version: mame_version.to_string(),
+        machines: metadata.clone(),
+    };
+
+    // Write to a temporary file first so a crash never leaves a truncated cache behind
+    let tmp_path = cache_path.with_extension("json.tmp");
+    let writer = BufWriter::new(fs::File::create(&tmp_path)?);
+    serde_json::to_writer(writer, &cache)?;
+    fs::rename(&tmp_path, cache_path)?;
+    Ok(())
+}
 
-    // ROM name must be last
-    cmd.arg(rom_name);
+// ============= END METADATA CACHE =============
 
-    // Spawn and return the child process
-    let child = cmd.spawn()?;
+// ============= LISTXML PARSER =============
 
-    Ok(child)
+/// Which text-only child element of a machine we are currently collecting
+enum TextField {
+    Description,
+    Year,
+    Manufacturer,
 }
 
-// UPDATED FUNCTION WITH VIDEO SETTINGS
-pub fn launch_rom_with_mame(
-    rom: &str,
-    rom_dirs: &[PathBuf],
-    _extra_dirs: &[PathBuf],
-    mame_executable: &str,
-    graphics_config: &GraphicsConfig,
-    video_settings: &VideoSettings,  // ADD THIS PARAMETER
-) {
-    eprintln!("=== LAUNCH DEBUG ===");
-    eprintln!("Launching ROM: {} with MAME: {}", rom, mame_executable);
-    eprintln!("ROM directories count: {}", rom_dirs.len());
-
-    // Print each ROM directory
-    for (i, dir) in rom_dirs.iter().enumerate() {
-        eprintln!("  ROM dir {}: {:?}", i, dir);
+/// Parse MAME `-listxml` output (or any Logiqx-style `<game>` list) into a machine model.
+///
+/// The input is consumed as a stream of events, so memory use stays proportional
+/// to the machine being parsed rather than to the whole document. `on_machine` is
+/// called with the running count after each machine; returning `false` stops early.
+pub fn parse_listxml<R: BufRead>(
+    source: R,
+    mut on_machine: impl FnMut(usize) -> bool,
+) -> Result<HashMap<String, GameMetadata>, Box<dyn std::error::Error>> {
+    let mut reader = Reader::from_reader(source);
+
+    let mut buf = Vec::new();
+    let mut machines = HashMap::new();
+    let mut current: Option<GameMetadata> = None;
+    let mut text_field: Option<TextField> = None;
+    let mut text = String::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let attrs = read_attrs(&e)?;
+                match e.name().as_ref() {
+                    b"machine" | b"game" => current = Some(machine_from_attrs(&attrs)),
+                    b"description" | b"year" | b"manufacturer" if current.is_some() => {
+                        text.clear();
+                        text_field = match e.name().as_ref() {
+                            b"description" => Some(TextField::Description),
+                            b"year" => Some(TextField::Year),
+                            _ => Some(TextField::Manufacturer),
+                        };
+                    }
+                    name => {
+                        if let Some(machine) = current.as_mut() {
+                            apply_machine_child(machine, name, &attrs);
+                        }
+                    }
+                }
+            }
+            Event::Empty(e) => {
+                if let Some(machine) = current.as_mut() {
+                    let attrs = read_attrs(&e)?;
+                    apply_machine_child(machine, e.name().as_ref(), &attrs);
+                }
+            }
+            Event::Text(e) if text_field.is_some() => text.push_str(&e.decode()?),
+            Event::CData(e) if text_field.is_some() => text.push_str(&e.decode()?),
+            Event::GeneralRef(e) if text_field.is_some() => {
+                if let Some(ch) = e.resolve_char_ref()? {
+                    text.push(ch);
+                } else if let Some(resolved) = resolve_predefined_entity(&e.decode()?) {
+                    text.push_str(resolved);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"machine" | b"game" => {
+                    if let Some(mut machine) = current.take() {
+                        machine.controls = machine.input.as_ref()
+                        .and_then(|input| input.controls.first())
+                        .map(|control| control.control_type.clone())
+                        .unwrap_or_default();
+                        machines.insert(machine.name.clone(), machine);
+                        if !on_machine(machines.len()) {
+                            break;
+                        }
+                    }
+                }
+                b"description" | b"year" | b"manufacturer" => {
+                    if let (Some(field), Some(machine)) = (text_field.take(), current.as_mut()) {
+                        let value = text.trim().to_string();
+                        match field {
+                            TextField::Description => machine.description = value,
+                            TextField::Year => machine.year = value,
+                            TextField::Manufacturer => machine.manufacturer = value,
+                        }
+                    }
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
     }
 
-    // Only use ROM directories for the rompath
-    // MAME uses semicolon as separator on all platforms
-    let separator = ";";
-    let rom_paths = rom_dirs
-    .iter()
-    .map(|p| p.to_string_lossy())
-    .collect::<Vec<_>>()
-    .join(separator);
-
-    eprintln!("Combined ROM paths: {}", rom_paths);
-
-    // Get MAME data directory
-    let mame_data_dir = get_mame_data_dir();
-
-    // Create directories if they don't exist
-    let nvram_dir = mame_data_dir.join("nvram");
-    let cfg_dir = mame_data_dir.join("cfg");
-    let state_dir = mame_data_dir.join("sta");
-    let snap_dir = mame_data_dir.join("snap");
-
-    let _ = std::fs::create_dir_all(&nvram_dir);
-    let _ = std::fs::create_dir_all(&cfg_dir);
-    let _ = std::fs::create_dir_all(&state_dir);
-    let _ = std::fs::create_dir_all(&snap_dir);
-
-    // Build the command
-    let mut cmd = Command::new(mame_executable);
-
-    // MOST RELIABLE: Set the working directory to our MAME data directory
-    // This ensures MAME creates its folders in the right place
-    cmd.current_dir(&mame_data_dir);
-
-    // Add rompath and ROM name
-    // When running from the data directory, MAME will use relative paths
-    cmd.arg("-rompath").arg(&rom_paths)
-    .arg("-nvram_directory").arg("nvram")
-    .arg("-cfg_directory").arg("cfg")
-    .arg("-state_directory").arg("sta")
-    .arg("-snapshot_directory").arg("snap");
-
-    // ADD GRAPHICS PRESET ARGUMENTS HERE
-    let preset = graphics_config.get_game_preset(rom);
-    eprintln!("Using graphics preset: {}", preset.name);
-    for arg in preset.to_mame_args() {
-        eprintln!("  Graphics arg: {}", arg);
-        cmd.arg(arg);
-    }
+    Ok(machines)
+}
 
-    // APPLY VIDEO SETTINGS
-    eprintln!("Applying video settings:");
-    if video_settings.video_backend != "auto" {
-        eprintln!("  Video backend: {}", video_settings.video_backend);
-        cmd.arg("-video").arg(&video_settings.video_backend);
+fn read_attrs(e: &BytesStart) -> Result<Vec<(String, String)>, Box<dyn std::error::Error>> {
+    let mut attrs = Vec::new();
+    for attr in e.attributes() {
+        let attr = attr?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        let value = attr.unescape_value()?.into_owned();
+        attrs.push((key, value));
     }
+    Ok(attrs)
+}
 
-    if video_settings.window_mode {
-        eprintln!("  Window mode enabled");
-        cmd.arg("-window");
-    }
+fn attr<'a>(attrs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+}
 
-    if video_settings.maximize {
-        eprintln!("  Maximize enabled");
-        cmd.arg("-maximize");
-    }
+fn attr_string(attrs: &[(String, String)], key: &str) -> Option<String> {
+    attr(attrs, key).map(|v| v.to_string())
+}
 
-    if video_settings.wait_vsync {
-        eprintln!("  V-Sync enabled");
-        cmd.arg("-waitvsync");
-    }
+fn attr_yes(attrs: &[(String, String)], key: &str) -> bool {
+    attr(attrs, key) == Some("yes")
+}
 
-    if video_settings.sync_refresh {
-        eprintln!("  Sync refresh enabled");
-        cmd.arg("-syncrefresh");
-    }
+fn attr_num<T: std::str::FromStr>(attrs: &[(String, String)], key: &str) -> Option<T> {
+    attr(attrs, key).and_then(|v| v.parse().ok())
+}
 
-    if video_settings.prescale > 0 {
-        eprintln!("  Prescale: {}x", video_settings.prescale);
-        cmd.arg("-prescale").arg(video_settings.prescale.to_string());
+fn machine_from_attrs(attrs: &[(String, String)]) -> GameMetadata {
+    let parent = attr_string(attrs, "cloneof");
+    GameMetadata {
+        name: attr_string(attrs, "name").unwrap_or_default(),
+        sourcefile: attr_string(attrs, "sourcefile").unwrap_or_default(),
+        is_bios: attr_yes(attrs, "isbios"),
+        is_device: attr_yes(attrs, "isdevice"),
+        is_mechanical: attr_yes(attrs, "ismechanical"),
+        runnable: attr(attrs, "runnable") != Some("no"),
+        is_clone: parent.is_some(),
+        parent,
+        romof: attr_string(attrs, "romof"),
+        sampleof: attr_string(attrs, "sampleof"),
+        ..Default::default()
     }
+}
 
-    if !video_settings.keep_aspect {
-        eprintln!("  Keep aspect disabled");
-        cmd.arg("-nokeepaspect");
+/// Apply a child element of `<machine>` to the machine being built
+fn apply_machine_child(machine: &mut GameMetadata, name: &[u8], attrs: &[(String, String)]) {
+    match name {
+        b"biosset" => machine.biossets.push(BiosSet {
+            name: attr_string(attrs, "name").unwrap_or_default(),
+            description: attr_string(attrs, "description").unwrap_or_default(),
+            default: attr_yes(attrs, "default"),
+        }),
+        b"rom" => machine.roms.push(RomEntry {
+            name: attr_string(attrs, "name").unwrap_or_default(),
+            bios: attr_string(attrs, "bios"),
+            size: attr_num(attrs, "size").unwrap_or(0),
+            crc: attr(attrs, "crc").map(|v| v.to_lowercase()),
+            sha1: attr(attrs, "sha1").map(|v| v.to_lowercase()),
+            merge: attr_string(attrs, "merge"),
+            region: attr_string(attrs, "region"),
+            offset: attr_string(attrs, "offset"),
+            status: attr(attrs, "status").map(DumpStatus::from_attr).unwrap_or_default(),
+            optional: attr_yes(attrs, "optional"),
+        }),
+        b"disk" => machine.disks.push(DiskEntry {
+            name: attr_string(attrs, "name").unwrap_or_default(),
+            sha1: attr(attrs, "sha1").map(|v| v.to_lowercase()),
+            merge: attr_string(attrs, "merge"),
+            region: attr_string(attrs, "region"),
+            index: attr_num(attrs, "index"),
+            writable: attr_yes(attrs, "writable"),
+            status: attr(attrs, "status").map(DumpStatus::from_attr).unwrap_or_default(),
+            optional: attr_yes(attrs, "optional"),
+        }),
+        b"device_ref" => {
+            if let Some(device) = attr_string(attrs, "name") {
+                machine.device_refs.push(device);
+            }
+        }
+        b"sample" => {
+            if let Some(sample) = attr_string(attrs, "name") {
+                machine.samples.push(sample);
+            }
+        }
+        b"chip" => machine.chips.push(ChipEntry {
+            chip_type: attr_string(attrs, "type").unwrap_or_default(),
+            tag: attr_string(attrs, "tag"),
+            name: attr_string(attrs, "name").unwrap_or_default(),
+            clock: attr_num(attrs, "clock"),
+        }),
+        b"display" => machine.displays.push(DisplayEntry {
+            tag: attr_string(attrs, "tag"),
+            display_type: attr_string(attrs, "type").unwrap_or_default(),
+            rotate: attr_num(attrs, "rotate").unwrap_or(0),
+            flipx: attr_yes(attrs, "flipx"),
+            width: attr_num(attrs, "width"),
+            height: attr_num(attrs, "height"),
+            refresh: attr_num(attrs, "refresh"),
+        }),
+        b"sound" => machine.sound_channels = attr_num(attrs, "channels"),
+        b"input" => machine.input = Some(InputInfo {
+            players: attr_num(attrs, "players").unwrap_or(0),
+            coins: attr_num(attrs, "coins").unwrap_or(0),
+            service: attr_yes(attrs, "service"),
+            tilt: attr_yes(attrs, "tilt"),
+            controls: Vec::new(),
+        }),
+        b"control" => {
+            if let Some(input) = machine.input.as_mut() {
+                input.controls.push(ControlEntry {
+                    control_type: attr_string(attrs, "type").unwrap_or_default(),
+                    player: attr_num(attrs, "player"),
+                    buttons: attr_num(attrs, "buttons"),
+                    ways: attr_string(attrs, "ways"),
+                });
+            }
+        }
+        b"dipswitch" => machine.dipswitches.push(DipSwitch {
+            name: attr_string(attrs, "name").unwrap_or_default(),
+            tag: attr_string(attrs, "tag"),
+            mask: attr_string(attrs, "mask"),
+            values: Vec::new(),
+        }),
+        b"dipvalue" => {
+            if let Some(dipswitch) = machine.dipswitches.last_mut() {
+                dipswitch.values.push(DipValue {
+                    name: attr_string(attrs, "name").unwrap_or_default(),
+                    value: attr_string(attrs, "value"),
+                    default: attr_yes(attrs, "default"),
+                });
+            }
+        }
+        b"driver" => machine.driver = Some(DriverInfo {
+            status: attr_string(attrs, "status").unwrap_or_default(),
+            emulation: attr_string(attrs, "emulation").unwrap_or_default(),
+            cocktail: attr_string(attrs, "cocktail"),
+            savestate: attr_string(attrs, "savestate"),
+            requires_artwork: attr_yes(attrs, "requiresartwork"),
+            unofficial: attr_yes(attrs, "unofficial"),
+            no_sound_hardware: attr_yes(attrs, "nosoundhardware"),
+            incomplete: attr_yes(attrs, "incomplete"),
+        }),
+        b"feature" => machine.features.push(FeatureEntry {
+            feature_type: attr_string(attrs, "type").unwrap_or_default(),
+            status: attr_string(attrs, "status"),
+            overall: attr_string(attrs, "overall"),
+        }),
+        _ => {}
     }
+}
 
-    if !video_settings.filter {
-        eprintln!("  Filter disabled");
-        cmd.arg("-nofilter");
+// ============= END LISTXML PARSER =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DumpStatus;
+
+    const LISTXML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE mame [<!ELEMENT mame (machine+)>]>
+<mame build="0.261 (mame0261)">
+    <machine name="neogeo" sourcefile="neogeo/neogeo.cpp" isbios="yes">
+        <description>Neo-Geo MV-6F</description>
+        <year>1990</year>
+        <manufacturer>SNK</manufacturer>
+        <biosset name="euro" description="Europe MVS (Ver. 2)" default="yes"/>
+        <rom name="sp-s2.sp1" bios="euro" size="131072" crc="9036d879" sha1="4f5ed7105b7128794654ce82b51723e16e389543" region="mainbios" offset="0"/>
+        <rom name="sm1.sm1" size="131072" crc="94416d67" region="audiobios" offset="0"/>
+    </machine>
+    <machine name="mslug" sourcefile="neogeo/neogeo.cpp" romof="neogeo">
+        <description>Metal Slug - Super Vehicle-001</description>
+        <year>1996</year>
+        <manufacturer>Nazca</manufacturer>
+        <rom name="sp-s2.sp1" merge="sp-s2.sp1" bios="euro" size="131072" crc="9036d879" region="mainbios" offset="0"/>
+        <rom name="201-p1.p1" size="2097152" crc="08d8daa5" region="cslot1:maincpu" offset="100000"/>
+        <rom name="201-c1.c1" size="4194304" status="baddump" crc="72813676" region="cslot1:sprites" offset="0"/>
+        <device_ref name="ng_memcard"/>
+        <driver status="good" emulation="good" savestate="supported"/>
+    </machine>
+    <machine name="mslugb" sourcefile="neogeo/neogeo.cpp" cloneof="mslug" romof="mslug">
+        <description>Metal Slug &amp; &quot;Bootleg&quot; &#233;dition</description>
+        <year>19??</year>
+        <manufacturer>bootleg</manufacturer>
+        <rom name="201-p1.p1" merge="201-p1.p1" size="2097152" crc="08d8daa5" region="cslot1:maincpu" offset="100000"/>
+        <rom name="pal.bin" size="260" status="nodump" region="plds" offset="0"/>
+        <rom name="extra.bin" size="16" crc="00000000" region="user1" offset="0" optional="yes"/>
+    </machine>
+    <machine name="gauntlet" sourcefile="atari/gauntlet.cpp" sampleof="gauntlet">
+        <description>Gauntlet</description>
+        <sample name="grunt"/>
+        <sample name="hurt"/>
+        <disk name="gauntlet" sha1="0123456789abcdef0123456789abcdef01234567" region="ata:0:hdd" index="0" writable="yes"/>
+        <disk name="gauntlet_patch" merge="gauntlet_patch" status="nodump" region="ata:1:hdd" index="1"/>
+    </machine>
+    <machine name="ng_memcard" sourcefile="neogeo/memcard.cpp" isdevice="yes" runnable="no">
+        <description>NEOGEO Memory Card</description>
+    </machine>
+</mame>"#;
+
+    fn parse(text: &str) -> HashMap<String, GameMetadata> {
+        parse_listxml(text.as_bytes(), |_| true).unwrap()
     }
 
-    if video_settings.num_screens > 1 {
-        eprintln!("  Number of screens: {}", video_settings.num_screens);
-        cmd.arg("-numscreens").arg(video_settings.num_screens.to_string());
+    #[test]
+    fn listxml_machines_and_relations() {
+        let machines = parse(LISTXML);
+        assert_eq!(machines.len(), 5);
+
+        let bios = &machines["neogeo"];
+        assert!(bios.is_bios && !bios.is_clone && bios.runnable);
+        assert_eq!(bios.biossets.len(), 1);
+        assert!(bios.biossets[0].default);
+        assert_eq!(bios.roms[0].bios.as_deref(), Some("euro"));
+
+        let parent = &machines["mslug"];
+        assert_eq!(parent.romof.as_deref(), Some("neogeo"));
+        assert_eq!(parent.parent, None);
+        assert_eq!(parent.device_refs, ["ng_memcard"]);
+        assert_eq!(parent.year, "1996");
+        assert_eq!(parent.driver.as_ref().map(|d| d.status.as_str()), Some("good"));
+
+        let clone = &machines["mslugb"];
+        assert!(clone.is_clone);
+        assert_eq!(clone.parent.as_deref(), Some("mslug"));
+        assert_eq!(clone.romof.as_deref(), Some("mslug"));
+
+        let device = &machines["ng_memcard"];
+        assert!(device.is_device && !device.runnable);
     }
 
-    // Add custom arguments if any
-    if !video_settings.custom_args.is_empty() {
-        eprintln!("  Custom args: {}", video_settings.custom_args);
-        for arg in video_settings.custom_args.split_whitespace() {
-            cmd.arg(arg);
-        }
+    #[test]
+    fn listxml_rom_and_disk_details() {
+        let machines = parse(LISTXML);
+
+        let parent = &machines["mslug"];
+        assert_eq!(parent.roms[0].merge.as_deref(), Some("sp-s2.sp1"));
+        assert_eq!(parent.roms[1].size, 2097152);
+        assert_eq!(parent.roms[1].crc.as_deref(), Some("08d8daa5"));
+        assert_eq!(parent.roms[1].status, DumpStatus::Good);
+        assert_eq!(parent.roms[2].status, DumpStatus::BadDump);
+
+        let clone = &machines["mslugb"];
+        assert_eq!(clone.roms[1].status, DumpStatus::NoDump);
+        assert_eq!(clone.roms[1].crc, None);
+        assert!(clone.roms[2].optional);
+
+        let disks = &machines["gauntlet"].disks;
+        assert_eq!(disks.len(), 2);
+        assert_eq!(disks[0].name, "gauntlet");
+        assert_eq!(disks[0].index, Some(0));
+        assert!(disks[0].writable);
+        assert_eq!(disks[1].merge.as_deref(), Some("gauntlet_patch"));
+        assert_eq!(disks[1].status, DumpStatus::NoDump);
     }
 
-    // ROM name must be last
-    cmd.arg(rom);
+    #[test]
+    fn listxml_samples_and_escaped_text() {
+        let machines = parse(LISTXML);
 
-    eprintln!("Working directory: {:?}", mame_data_dir);
-    eprintln!("Full command: cd {:?} && {:?} -rompath {} -nvram_directory nvram -cfg_directory cfg ... {}",
-              mame_data_dir, mame_executable, rom_paths, rom);
-    eprintln!("===================");
+        let gauntlet = &machines["gauntlet"];
+        assert_eq!(gauntlet.sampleof.as_deref(), Some("gauntlet"));
+        assert_eq!(gauntlet.samples, ["grunt", "hurt"]);
 
-    // Spawn and check for errors
-    match cmd.spawn() {
-        Ok(child) => {
-            eprintln!("MAME launched successfully with PID: {:?}", child.id());
-        },
-        Err(e) => {
-            eprintln!("Failed to launch MAME: {}", e);
-        }
+        assert_eq!(machines["mslugb"].description, "Metal Slug & \"Bootleg\" édition");
+        assert_eq!(machines["mslugb"].year, "19??");
+    }
+
+    #[test]
+    fn listxml_stops_when_asked() {
+        let mut seen = 0;
+        let machines = parse_listxml(LISTXML.as_bytes(), |count| {
+            seen = count;
+            count < 2
+        }).unwrap();
+        assert_eq!(seen, 2);
+        assert_eq!(machines.len(), 2);
     }
 }
@@ -39,6 +39,9 @@ pub struct MameExecutable {
     pub version: String,
     pub total_games: usize,
     pub working_games: usize,
+    pub default_args: Vec<String>,  // Passed on every launch with this executable
+    pub data_layout: DataLayout,
+    pub ini_path: Option<String>,  // MAME's own -inipath from -showconfig, once it has been read
 }
 
 impl Default for MameExecutable {
@@ -49,11 +52,88 @@ impl Default for MameExecutable {
             version: String::new(),
             total_games: 0,
             working_games: 0,
+            default_args: Vec::new(),
+            data_layout: DataLayout::default(),
+            ini_path: None,
         }
     }
 }
 
-#[derive(Clone, Debug, Default)]
+/// A folder MAME writes to while a game runs
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DataFolder {
+    Nvram,
+    Cfg,
+    States,
+    Snapshots,
+    Inputs,
+    Diffs,
+    Hiscore,
+    Plugins,
+}
+
+impl DataFolder {
+    pub fn all() -> [DataFolder; 8] {
+        [
+            DataFolder::Nvram, DataFolder::Cfg, DataFolder::States, DataFolder::Snapshots,
+            DataFolder::Inputs, DataFolder::Diffs, DataFolder::Hiscore, DataFolder::Plugins,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataFolder::Nvram => "NVRAM",
+            DataFolder::Cfg => "Configuration",
+            DataFolder::States => "Save states",
+            DataFolder::Snapshots => "Snapshots",
+            DataFolder::Inputs => "Input recordings",
+            DataFolder::Diffs => "Hard disk diffs",
+            DataFolder::Hiscore => "Hiscores",
+            DataFolder::Plugins => "Plugin data",
+        }
+    }
+
+    /// The MAME option that points at the folder. Hiscores go through -homepath, under
+    /// which the hiscore plugin keeps its hiscore/ folder.
+    pub fn option(&self) -> &'static str {
+        match self {
+            DataFolder::Nvram => "nvram_directory",
+            DataFolder::Cfg => "cfg_directory",
+            DataFolder::States => "state_directory",
+            DataFolder::Snapshots => "snapshot_directory",
+            DataFolder::Inputs => "input_directory",
+            DataFolder::Diffs => "diff_directory",
+            DataFolder::Hiscore => "homepath",
+            DataFolder::Plugins => "plugindatapath",
+        }
+    }
+
+    /// Folder name under the shared or per-executable data directory
+    pub fn dir_name(&self) -> &'static str {
+        match self {
+            DataFolder::Nvram => "nvram",
+            DataFolder::Cfg => "cfg",
+            DataFolder::States => "sta",
+            DataFolder::Snapshots => "snap",
+            DataFolder::Inputs => "inp",
+            DataFolder::Diffs => "diff",
+            DataFolder::Hiscore => "home",
+            DataFolder::Plugins => "plugins",
+        }
+    }
+}
+
+/// Where an executable's data folders live. Folders listed in `isolated` are kept in the
+/// executable's own directory and those in `shared` in the data directory shared by all
+/// executables; the rest are left where MAME's own settings put them.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DataLayout {
+    pub root: Option<PathBuf>,  // The executable's own directory, fixed when it is added
+    pub shared: Vec<DataFolder>,
+    pub isolated: Vec<DataFolder>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GameMetadata {
     pub name: String,
     pub description: String,
@@ -64,10 +144,146 @@ pub struct GameMetadata {
     pub is_bios: bool,
     pub is_mechanical: bool,
     pub runnable: bool,
-    pub parent: Option<String>,
+    pub parent: Option<String>,  // cloneof
     pub is_clone: bool,
-    pub driver_status: Option<String>,  // NEW
-    pub emulation_status: Option<String>, // NEW
+    pub romof: Option<String>,
+    pub sampleof: Option<String>,
+    pub sourcefile: String,
+    pub driver: Option<DriverInfo>,
+    pub biossets: Vec<BiosSet>,
+    pub roms: Vec<RomEntry>,
+    pub disks: Vec<DiskEntry>,
+    pub device_refs: Vec<String>,
+    pub samples: Vec<String>,
+    pub chips: Vec<ChipEntry>,
+    pub displays: Vec<DisplayEntry>,
+    pub sound_channels: Option<u32>,
+    pub input: Option<InputInfo>,
+    pub dipswitches: Vec<DipSwitch>,
+    pub features: Vec<FeatureEntry>,
+}
+
+/// Attributes of the listxml `<driver>` element
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DriverInfo {
+    pub status: String,
+    pub emulation: String,
+    pub cocktail: Option<String>,
+    pub savestate: Option<String>,
+    pub requires_artwork: bool,
+    pub unofficial: bool,
+    pub no_sound_hardware: bool,
+    pub incomplete: bool,
+}
+
+/// Dump status of a ROM or disk as reported by listxml
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DumpStatus {
+    #[default]
+    Good,
+    BadDump,
+    NoDump,
+}
+
+impl DumpStatus {
+    pub fn from_attr(value: &str) -> Self {
+        match value {
+            "baddump" => DumpStatus::BadDump,
+            "nodump" => DumpStatus::NoDump,
+            _ => DumpStatus::Good,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BiosSet {
+    pub name: String,
+    pub description: String,
+    pub default: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RomEntry {
+    pub name: String,
+    pub bios: Option<String>,
+    pub size: u64,
+    pub crc: Option<String>,
+    pub sha1: Option<String>,
+    pub merge: Option<String>,
+    pub region: Option<String>,
+    pub offset: Option<String>,
+    pub status: DumpStatus,
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DiskEntry {
+    pub name: String,
+    pub sha1: Option<String>,
+    pub merge: Option<String>,
+    pub region: Option<String>,
+    pub index: Option<u32>,
+    pub writable: bool,
+    pub status: DumpStatus,
+    pub optional: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ChipEntry {
+    pub chip_type: String,  // cpu or audio
+    pub tag: Option<String>,
+    pub name: String,
+    pub clock: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DisplayEntry {
+    pub tag: Option<String>,
+    pub display_type: String,  // raster, vector, lcd, svg
+    pub rotate: u16,
+    pub flipx: bool,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub refresh: Option<f64>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputInfo {
+    pub players: u32,
+    pub coins: u32,
+    pub service: bool,
+    pub tilt: bool,
+    pub controls: Vec<ControlEntry>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ControlEntry {
+    pub control_type: String,
+    pub player: Option<u32>,
+    pub buttons: Option<u32>,
+    pub ways: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DipSwitch {
+    pub name: String,
+    pub tag: Option<String>,
+    pub mask: Option<String>,
+    pub values: Vec<DipValue>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct DipValue {
+    pub name: String,
+    pub value: Option<String>,
+    pub default: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FeatureEntry {
+    pub feature_type: String,
+    pub status: Option<String>,
+    pub overall: Option<String>,
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -100,25 +316,31 @@ impl RomStatus {
 
 impl GameMetadata {
     pub fn get_status(&self) -> RomStatus {
-        // Check driver status first
-        if let Some(driver) = &self.driver_status {
-            match driver.as_str() {
-                "good" => return RomStatus::Good,
-                "imperfect" => return RomStatus::Imperfect,
-                "preliminary" => return RomStatus::Preliminary,
-                _ => {}
-            }
+        if !self.runnable {
+            return RomStatus::NotWorking;
         }
 
-        // Fall back to other checks
-        if self.runnable {
-            RomStatus::NotWorking
-        } else if self.is_mechanical || self.emulation_status.as_deref() == Some("imperfect") {
-            RomStatus::Imperfect
-        } else {
-            RomStatus::Good
+        match &self.driver {
+            Some(driver) => {
+                // emulation="preliminary" is how listxml marks MACHINE_NOT_WORKING
+                if driver.emulation == "preliminary" {
+                    return RomStatus::NotWorking;
+                }
+                match driver.status.as_str() {
+                    "preliminary" => RomStatus::Preliminary,
+                    "imperfect" => RomStatus::Imperfect,
+                    _ => RomStatus::Good,
+                }
+            }
+            // Devices and some BIOS entries carry no driver element
+            None => RomStatus::Good,
         }
     }
+
+    /// Playable in MAME's sense: runnable and not flagged as not working
+    pub fn is_working(&self) -> bool {
+        matches!(self.get_status(), RomStatus::Good | RomStatus::Imperfect)
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
@@ -172,6 +394,7 @@ pub struct FilterSettings {
     pub hide_casino: bool,
     pub show_favorites_only: bool,  // NEW
     pub status_filter: StatusFilter,  // NEW
+    pub audit_filter: AuditFilter,
 }
 
 // NEW: Status filter enum
@@ -203,6 +426,7 @@ impl Default for FilterSettings {
             hide_casino: false,
             show_favorites_only: false,
             status_filter: StatusFilter::All,
+            audit_filter: AuditFilter::All,
         }
     }
 }
@@ -215,6 +439,141 @@ pub enum RomSetType {
     Unknown,
 }
 
+/// Outcome of auditing one set against its listxml ROM and disk entries
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AuditStatus {
+    Good,
+    BestAvailable,
+    Incorrect,
+    MissingFiles,
+    NotFound,
+    NoRomsNeeded,
+}
+
+impl AuditStatus {
+    /// Whether MAME will be able to start the set
+    pub fn is_playable(&self) -> bool {
+        matches!(self, AuditStatus::Good | AuditStatus::BestAvailable | AuditStatus::NoRomsNeeded)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditStatus::Good => "Good",
+            AuditStatus::BestAvailable => "Best available",
+            AuditStatus::Incorrect => "Incorrect",
+            AuditStatus::MissingFiles => "Missing files",
+            AuditStatus::NotFound => "Not found",
+            AuditStatus::NoRomsNeeded => "No ROMs needed",
+        }
+    }
+
+    pub fn to_color(self) -> egui::Color32 {
+        match self {
+            AuditStatus::Good | AuditStatus::NoRomsNeeded => egui::Color32::from_rgb(0, 255, 0),
+            AuditStatus::BestAvailable => egui::Color32::from_rgb(255, 200, 0),
+            AuditStatus::Incorrect | AuditStatus::MissingFiles => egui::Color32::from_rgb(255, 150, 0),
+            AuditStatus::NotFound => egui::Color32::from_rgb(255, 0, 0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuditProblemKind {
+    Missing,
+    BadCrc,
+    WrongSize,
+    Unneeded,
+}
+
+impl AuditProblemKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditProblemKind::Missing => "Missing",
+            AuditProblemKind::BadCrc => "Bad CRC",
+            AuditProblemKind::WrongSize => "Wrong size",
+            AuditProblemKind::Unneeded => "Unneeded",
+        }
+    }
+}
+
+/// A single file that kept a set from auditing clean
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AuditProblem {
+    pub file: String,
+    pub kind: AuditProblemKind,
+    pub expected: Option<String>,
+    pub found: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SetAuditResult {
+    pub name: String,
+    pub status: AuditStatus,
+    pub problems: Vec<AuditProblem>,
+}
+
+/// Audit results for every set, persisted per MAME executable
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub mame_version: String,
+    pub audited_at: String,  // ISO timestamp
+    pub results: HashMap<String, SetAuditResult>,
+}
+
+/// Filter the ROM list by the last audit's outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum AuditFilter {
+    #[default]
+    All,
+    PlayableOnly,
+    GoodOnly,
+    BestAvailableOnly,
+    IncorrectOnly,
+    MissingFilesOnly,
+    NotFoundOnly,
+    NotAudited,
+}
+
+impl AuditFilter {
+    pub const ALL: [AuditFilter; 8] = [
+        AuditFilter::All,
+        AuditFilter::PlayableOnly,
+        AuditFilter::GoodOnly,
+        AuditFilter::BestAvailableOnly,
+        AuditFilter::IncorrectOnly,
+        AuditFilter::MissingFilesOnly,
+        AuditFilter::NotFoundOnly,
+        AuditFilter::NotAudited,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            AuditFilter::All => "All",
+            AuditFilter::PlayableOnly => "Playable",
+            AuditFilter::GoodOnly => "Good",
+            AuditFilter::BestAvailableOnly => "Best available",
+            AuditFilter::IncorrectOnly => "Incorrect",
+            AuditFilter::MissingFilesOnly => "Missing files",
+            AuditFilter::NotFoundOnly => "Not found",
+            AuditFilter::NotAudited => "Not audited",
+        }
+    }
+
+    pub fn matches(&self, result: Option<&SetAuditResult>) -> bool {
+        let status = result.map(|r| r.status);
+        match self {
+            AuditFilter::All => true,
+            AuditFilter::PlayableOnly => status.map(|s| s.is_playable()).unwrap_or(false),
+            AuditFilter::GoodOnly => status == Some(AuditStatus::Good),
+            AuditFilter::BestAvailableOnly => status == Some(AuditStatus::BestAvailable),
+            AuditFilter::IncorrectOnly => status == Some(AuditStatus::Incorrect),
+            AuditFilter::MissingFilesOnly => status == Some(AuditStatus::MissingFiles),
+            AuditFilter::NotFoundOnly => status == Some(AuditStatus::NotFound),
+            AuditFilter::NotAudited => status.is_none(),
+        }
+    }
+}
+
 // NEW: Game statistics
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct GameStats {
@@ -316,7 +675,7 @@ pub struct VideoSettings {
     pub keep_aspect: bool,          // Keep aspect ratio
     pub filter: bool,               // Bilinear filtering
     pub num_screens: u8,            // Number of screens (1-4)
-    pub custom_args: String,        // Additional custom arguments
+    pub custom_args: Vec<String>,   // Additional custom arguments
 }
 
 impl Default for VideoSettings {
@@ -331,11 +690,62 @@ impl Default for VideoSettings {
             keep_aspect: true,
             filter: true,
             num_screens: 1,
-            custom_args: String::new(),
+            custom_args: Vec::new(),
         }
     }
 }
 
+/// Video settings a game sets for itself; fields left at None follow the global settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct VideoOverrides {
+    pub video_backend: Option<String>,
+    pub window_mode: Option<bool>,
+    pub maximize: Option<bool>,
+    pub wait_vsync: Option<bool>,
+    pub sync_refresh: Option<bool>,
+    pub prescale: Option<u8>,
+    pub keep_aspect: Option<bool>,
+    pub filter: Option<bool>,
+    pub num_screens: Option<u8>,
+    pub custom_args: Option<Vec<String>>,
+}
+
+impl VideoOverrides {
+    /// `global` with these overrides applied
+    pub fn apply_to(&self, global: &VideoSettings) -> VideoSettings {
+        VideoSettings {
+            video_backend: self.video_backend.clone().unwrap_or_else(|| global.video_backend.clone()),
+            window_mode: self.window_mode.unwrap_or(global.window_mode),
+            maximize: self.maximize.unwrap_or(global.maximize),
+            wait_vsync: self.wait_vsync.unwrap_or(global.wait_vsync),
+            sync_refresh: self.sync_refresh.unwrap_or(global.sync_refresh),
+            prescale: self.prescale.unwrap_or(global.prescale),
+            keep_aspect: self.keep_aspect.unwrap_or(global.keep_aspect),
+            filter: self.filter.unwrap_or(global.filter),
+            num_screens: self.num_screens.unwrap_or(global.num_screens),
+            custom_args: self.custom_args.clone().unwrap_or_else(|| global.custom_args.clone()),
+        }
+    }
+}
+
+/// How one game is launched when it differs from the global settings
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LaunchProfile {
+    pub mame_index: Option<usize>,         // Index into mame_executables
+    pub graphics_preset: Option<String>,
+    pub video: VideoOverrides,
+    pub extra_args: Vec<String>,            // Applied after everything else
+    pub env: Vec<(String, String)>,
+    pub working_dir: Option<PathBuf>,
+}
+
+impl LaunchProfile {
+    /// Whether the profile leaves everything at the global settings
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AppConfig {
     pub mame_executables: Vec<MameExecutable>,
@@ -366,6 +776,12 @@ pub struct AppConfig {
 
     pub graphics_config: GraphicsConfig,  // NEW
     pub video_settings: VideoSettings,  // ADD THIS LINE
+    pub custom_collections: Vec<CustomCollection>,
+    pub dir_scan_settings: HashMap<PathBuf, DirScanSettings>,  // Keyed by ROM or extra ROM directory
+    pub directories: Vec<ManagedDir>,  // Source of the directory lists above
+    pub game_profiles: HashMap<String, LaunchProfile>,
+    pub use_ini_files: bool,  // Pass -inipath for the generated MAME ini files
+    pub mame_ini_options: HashMap<String, String>,  // Edited in the MAME configuration dialog
 }
 
 impl Default for AppConfig {
@@ -399,10 +815,104 @@ impl Default for AppConfig {
 
             graphics_config: GraphicsConfig::default(),
             video_settings: VideoSettings::default(),  // ADD THIS LINE
+            custom_collections: vec![],
+            dir_scan_settings: HashMap::new(),
+            directories: vec![],
+            game_profiles: HashMap::new(),
+            use_ini_files: false,
+            mame_ini_options: HashMap::new(),
+        }
+    }
+}
+
+impl AppConfig {
+    /// Enabled directories with `role`, in the order they're listed
+    pub fn dirs_with_role(&self, role: DirRole) -> Vec<PathBuf> {
+        self.directories.iter()
+        .filter(|d| d.enabled && d.role == role)
+        .map(|d| d.path.clone())
+        .collect()
+    }
+
+    /// Rebuild the per-purpose directory lists the scanners and launcher read
+    /// from the managed directories
+    pub fn sync_directory_lists(&mut self) {
+        self.rom_dirs = self.dirs_with_role(DirRole::Roms);
+        self.extra_rom_dirs = self.dirs_with_role(DirRole::Chds);
+        self.extra_asset_dirs = self.dirs_with_role(DirRole::Artwork);
+        self.icons_path = self.dirs_with_role(DirRole::Icons).into_iter().next();
+    }
+}
+
+/// What a configured directory holds
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum DirRole {
+    Roms,
+    Chds,
+    Samples,
+    Artwork,
+    Icons,
+    Cheats,
+    History,
+}
+
+impl DirRole {
+    pub fn all() -> [DirRole; 7] {
+        [DirRole::Roms, DirRole::Chds, DirRole::Samples, DirRole::Artwork, DirRole::Icons, DirRole::Cheats, DirRole::History]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DirRole::Roms => "ROMs",
+            DirRole::Chds => "CHDs",
+            DirRole::Samples => "Samples",
+            DirRole::Artwork => "Artwork",
+            DirRole::Icons => "Icons",
+            DirRole::Cheats => "Cheats",
+            DirRole::History => "History/DAT files",
         }
     }
 }
 
+/// A directory from the directories manager. ROM and CHD directories are searched
+/// in list order, which is the order of the -rompath.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ManagedDir {
+    pub path: PathBuf,
+    pub role: DirRole,
+    pub enabled: bool,
+}
+
+/// A set list from a third-party DAT, verified against its own ROM directory
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct CustomCollection {
+    pub name: String,
+    pub dat_path: PathBuf,
+    pub rom_dir: PathBuf,
+}
+
+impl CustomCollection {
+    /// File-name-safe form of the collection name, used for its saved audit report and
+    /// archive cache. A hash of the DAT path keeps names that only differ in punctuation
+    /// or non-ASCII letters apart.
+    pub fn slug(&self) -> String {
+        let name: String = self.name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+        .collect();
+        format!("{}_{:08x}", name, crc32fast::hash(self.dat_path.to_string_lossy().as_bytes()))
+    }
+}
+
+/// How one ROM directory is scanned. Subfolders down to `max_depth` levels are added to
+/// the rompath; the glob patterns choose which, by their path relative to the directory
+/// (e.g. `Capcom/*` or `*/Prototypes`).
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct DirScanSettings {
+    pub max_depth: usize,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+}
+
 // NEW: ROM collection statistics
 #[derive(Debug, Default)]
 pub struct RomStatistics {
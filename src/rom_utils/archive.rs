@@ -0,0 +1,1086 @@
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use liblzma::stream::{Action, Filters, Status, Stream};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+use crate::models::{DumpStatus, GameMetadata};
+
+/// Archive formats MAME can load sets from
+pub const ROM_ARCHIVE_EXTENSIONS: [&str; 2] = ["zip", "7z"];
+
+/// A file inside a ROM archive, as recorded in the archive's directory
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchiveMember {
+    pub name: String,
+    pub size: u64,
+    pub crc: u32,
+}
+
+/// Whether `path` is a zip or 7z archive
+pub fn is_rom_archive(path: &Path) -> bool {
+    path.extension()
+    .and_then(|s| s.to_str())
+    .map(|ext| ROM_ARCHIVE_EXTENSIONS.iter().any(|e| ext.eq_ignore_ascii_case(e)))
+    .unwrap_or(false)
+}
+
+/// The set name of a ROM archive (its file name without the extension)
+pub fn archive_set_name(path: &Path) -> Option<&str> {
+    if is_rom_archive(path) {
+        path.file_stem().and_then(|s| s.to_str())
+    } else {
+        None
+    }
+}
+
+/// List the members of a zip or 7z archive without decompressing any file data
+pub fn list_archive_members(path: &Path) -> Result<Vec<ArchiveMember>, Box<dyn std::error::Error>> {
+    let is_7z = path.extension()
+    .and_then(|s| s.to_str())
+    .map(|ext| ext.eq_ignore_ascii_case("7z"))
+    .unwrap_or(false);
+
+    if is_7z {
+        list_7z_members(path)
+    } else {
+        list_zip_members(path)
+    }
+}
+
+/// List the members of a zip using only its central directory (nothing is decompressed)
+pub fn list_zip_members(path: &Path) -> Result<Vec<ArchiveMember>, Box<dyn std::error::Error>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut members = Vec::with_capacity(archive.len());
+
+    for i in 0..archive.len() {
+        let file = archive.by_index_raw(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        members.push(ArchiveMember {
+            name: base_name(file.name()),
+            size: file.size(),
+            crc: file.crc32(),
+        });
+    }
+
+    Ok(members)
+}
+
+// ============= DIRECTORY SETS =============
+//
+// MAME also loads a set from a plain folder named after it (`roms/pacman/pacman.6e`).
+// The same folder holds the set's CHDs, which are disks rather than ROM files.
+
+fn is_chd(path: &Path) -> bool {
+    path.extension().and_then(|s| s.to_str()).map(|e| e.eq_ignore_ascii_case("chd")).unwrap_or(false)
+}
+
+/// The set name of a folder on the rompath, if it is named after a known machine
+pub fn directory_set_name<'a>(path: &'a Path, metadata: &HashMap<String, GameMetadata>) -> Option<&'a str> {
+    let name = path.file_name().and_then(|s| s.to_str())?;
+    (metadata.contains_key(name) && path.is_dir()).then_some(name)
+}
+
+/// Regular files directly inside a set folder, CHDs excluded
+pub fn directory_rom_files(path: &Path) -> Vec<PathBuf> {
+    fs::read_dir(path)
+    .map(|entries| {
+        entries.flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && !is_chd(p))
+        .collect()
+    })
+    .unwrap_or_default()
+}
+
+/// Whether a folder holds ROM files itself, as opposed to only CHDs
+pub fn is_directory_set(path: &Path) -> bool {
+    !directory_rom_files(path).is_empty()
+}
+
+/// Whether a set's own files are all disks, its ROMs (if any) coming from its parent or BIOS
+fn needs_only_disks(meta: &GameMetadata) -> bool {
+    !meta.disks.is_empty()
+        && meta.roms.iter().all(|rom| rom.merge.is_some() || rom.optional || rom.status == DumpStatus::NoDump)
+}
+
+fn holds_chds(path: &Path) -> bool {
+    fs::read_dir(path).into_iter().flatten().flatten().any(|e| is_chd(&e.path()))
+}
+
+/// Whether a folder named after a set stands for the whole set: it holds the ROM files,
+/// or the set needs nothing of its own but the CHDs inside it
+fn is_set_folder(path: &Path, name: &str, metadata: &HashMap<String, GameMetadata>) -> bool {
+    is_directory_set(path) || (metadata.get(name).is_some_and(needs_only_disks) && holds_chds(path))
+}
+
+/// List the ROM files in a set folder. Unlike archives there is no stored CRC, so every file is read.
+pub fn list_directory_members(path: &Path) -> Result<Vec<ArchiveMember>, Box<dyn std::error::Error>> {
+    let mut members = Vec::new();
+
+    for file_path in directory_rom_files(path) {
+        let mut file = File::open(&file_path)?;
+        let mut hasher = crc32fast::Hasher::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut size = 0u64;
+
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buffer[..read]);
+            size += read as u64;
+        }
+
+        members.push(ArchiveMember {
+            name: file_path.file_name().and_then(|s| s.to_str()).unwrap_or_default().to_string(),
+            size,
+            crc: hasher.finalize(),
+        });
+    }
+
+    Ok(members)
+}
+
+/// The set name of an archive or a set folder on the rompath
+pub fn rom_set_name<'a>(path: &'a Path, metadata: &HashMap<String, GameMetadata>) -> Option<&'a str> {
+    archive_set_name(path).or_else(|| directory_set_name(path, metadata).filter(|name| is_set_folder(path, name, metadata)))
+}
+
+/// File names in a set archive or folder, without reading any file contents
+pub fn list_set_file_names(path: &Path) -> Option<Vec<String>> {
+    if path.is_dir() {
+        Some(directory_rom_files(path).iter()
+        .filter_map(|p| p.file_name().and_then(|s| s.to_str()).map(String::from))
+        .collect())
+    } else {
+        list_archive_members(path).ok().map(|members| members.into_iter().map(|m| m.name).collect())
+    }
+}
+
+/// Names of the sets on the rompath stored as uncompressed folders
+pub fn find_directory_sets(rom_dirs: &[PathBuf], metadata: &HashMap<String, GameMetadata>) -> HashSet<String> {
+    let mut sets = HashSet::new();
+
+    for dir in rom_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(name) = directory_set_name(&path, metadata).filter(|_| is_directory_set(&path)) {
+                sets.insert(name.to_string());
+            }
+        }
+    }
+
+    sets
+}
+
+// ============= END DIRECTORY SETS =============
+
+fn base_name(name: &str) -> String {
+    name.rsplit(['/', '\\']).next().unwrap_or_default().to_string()
+}
+
+// ============= 7Z HEADER PARSER =============
+//
+// Only the archive header is read: it records every file's name, size and CRC, so
+// listing a set never touches the compressed file data. Compressed ("encoded")
+// headers are unpacked with liblzma.
+
+const SEVEN_ZIP_SIGNATURE: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+const MAX_HEADER_SIZE: u64 = 64 * 1024 * 1024;
+/// 7-Zip's own limit on the streams of one folder's coders
+const MAX_FOLDER_STREAMS: u64 = 64;
+/// Largest folder `read_7z_files` will unpack into memory
+const MAX_FOLDER_SIZE: u64 = 1 << 30;
+
+const K_END: u8 = 0x00;
+const K_HEADER: u8 = 0x01;
+const K_ARCHIVE_PROPERTIES: u8 = 0x02;
+const K_ADDITIONAL_STREAMS_INFO: u8 = 0x03;
+const K_MAIN_STREAMS_INFO: u8 = 0x04;
+const K_FILES_INFO: u8 = 0x05;
+const K_PACK_INFO: u8 = 0x06;
+const K_UNPACK_INFO: u8 = 0x07;
+const K_SUBSTREAMS_INFO: u8 = 0x08;
+const K_SIZE: u8 = 0x09;
+const K_CRC: u8 = 0x0A;
+const K_FOLDER: u8 = 0x0B;
+const K_CODERS_UNPACK_SIZE: u8 = 0x0C;
+const K_NUM_UNPACK_STREAM: u8 = 0x0D;
+const K_EMPTY_STREAM: u8 = 0x0E;
+const K_EMPTY_FILE: u8 = 0x0F;
+const K_NAME: u8 = 0x11;
+const K_ENCODED_HEADER: u8 = 0x17;
+
+type ParseResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+struct HeaderReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> HeaderReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn byte(&mut self) -> ParseResult<u8> {
+        let b = *self.data.get(self.pos).ok_or("truncated 7z header")?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn bytes(&mut self, len: usize) -> ParseResult<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.data.len()).ok_or("truncated 7z header")?;
+        let slice = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32_le(&mut self) -> ParseResult<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into()?))
+    }
+
+    /// 7z's variable-length integer: the leading one bits of the first byte say how many bytes follow
+    fn number(&mut self) -> ParseResult<u64> {
+        let first = self.byte()?;
+        let mut mask = 0x80u8;
+        let mut value = 0u64;
+
+        for i in 0..8 {
+            if first & mask == 0 {
+                let high = (first & mask.wrapping_sub(1)) as u64;
+                return Ok(value | (high << (8 * i)));
+            }
+            value |= (self.byte()? as u64) << (8 * i);
+            mask >>= 1;
+        }
+
+        Ok(value)
+    }
+
+    fn count(&mut self) -> ParseResult<usize> {
+        let n = self.number()?;
+        // Every counted item takes at least one bit, so anything larger is corrupt
+        if n > (self.data.len() as u64) * 8 {
+            return Err("invalid item count in 7z header".into());
+        }
+        Ok(n as usize)
+    }
+
+    fn bits(&mut self, count: usize) -> ParseResult<Vec<bool>> {
+        let mut bits = Vec::with_capacity(count);
+        let mut current = 0u8;
+        for i in 0..count {
+            if i % 8 == 0 {
+                current = self.byte()?;
+            }
+            bits.push(current & (0x80 >> (i % 8)) != 0);
+        }
+        Ok(bits)
+    }
+
+    fn digests(&mut self, count: usize) -> ParseResult<Vec<Option<u32>>> {
+        let all_defined = self.byte()? != 0;
+        let defined = if all_defined { vec![true; count] } else { self.bits(count)? };
+
+        defined.into_iter()
+        .map(|d| if d { self.u32_le().map(Some) } else { Ok(None) })
+        .collect()
+    }
+}
+
+struct Coder {
+    id: Vec<u8>,
+    properties: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Folder {
+    coders: Vec<Coder>,
+    bound_outputs: Vec<u64>,
+    unpack_sizes: Vec<u64>,
+    crc: Option<u32>,
+}
+
+impl Folder {
+    /// Size of the folder's final output, the one stream not fed into another coder
+    fn unpack_size(&self) -> u64 {
+        (0..self.unpack_sizes.len())
+        .rev()
+        .find(|i| !self.bound_outputs.contains(&(*i as u64)))
+        .map(|i| self.unpack_sizes[i])
+        .unwrap_or(0)
+    }
+}
+
+#[derive(Default)]
+struct StreamsInfo {
+    pack_pos: u64,
+    pack_sizes: Vec<u64>,
+    folders: Vec<Folder>,
+    /// How many of the streams below each folder unpacks to
+    folder_streams: Vec<usize>,
+    stream_sizes: Vec<u64>,
+    stream_crcs: Vec<Option<u32>>,
+}
+
+fn read_pack_info(r: &mut HeaderReader, info: &mut StreamsInfo) -> ParseResult<()> {
+    info.pack_pos = r.number()?;
+    let count = r.count()?;
+
+    loop {
+        match r.byte()? {
+            K_END => return Ok(()),
+            K_SIZE => {
+                info.pack_sizes = (0..count).map(|_| r.number()).collect::<ParseResult<_>>()?;
+            }
+            K_CRC => {
+                r.digests(count)?;
+            }
+            other => return Err(format!("unexpected 7z pack info property {:#x}", other).into()),
+        }
+    }
+}
+
+fn read_folder(r: &mut HeaderReader) -> ParseResult<Folder> {
+    let mut folder = Folder::default();
+    let mut total_in = 0u64;
+    let mut total_out = 0u64;
+
+    for _ in 0..r.count()? {
+        let flags = r.byte()?;
+        if flags & 0x80 != 0 {
+            return Err("7z alternative coder methods are not supported".into());
+        }
+
+        let id = r.bytes((flags & 0x0F) as usize)?.to_vec();
+        let (num_in, num_out) = if flags & 0x10 != 0 {
+            (r.number()?, r.number()?)
+        } else {
+            (1, 1)
+        };
+        let properties = if flags & 0x20 != 0 {
+            let len = r.count()?;
+            r.bytes(len)?.to_vec()
+        } else {
+            Vec::new()
+        };
+
+        total_in = total_in.checked_add(num_in).filter(|n| *n <= MAX_FOLDER_STREAMS).ok_or("too many coder streams in 7z folder")?;
+        total_out = total_out.checked_add(num_out).filter(|n| *n <= MAX_FOLDER_STREAMS).ok_or("too many coder streams in 7z folder")?;
+        folder.coders.push(Coder { id, properties });
+    }
+
+    let bind_pairs = total_out.saturating_sub(1);
+    for _ in 0..bind_pairs {
+        r.number()?; // input index
+        folder.bound_outputs.push(r.number()?);
+    }
+
+    let packed_streams = total_in.saturating_sub(bind_pairs);
+    if packed_streams > 1 {
+        for _ in 0..packed_streams {
+            r.number()?;
+        }
+    }
+
+    folder.unpack_sizes = vec![0; total_out as usize];
+    Ok(folder)
+}
+
+fn read_unpack_info(r: &mut HeaderReader, info: &mut StreamsInfo) -> ParseResult<()> {
+    if r.byte()? != K_FOLDER {
+        return Err("expected folder list in 7z header".into());
+    }
+
+    let count = r.count()?;
+    if r.byte()? != 0 {
+        return Err("external 7z folder lists are not supported".into());
+    }
+    info.folders = (0..count).map(|_| read_folder(r)).collect::<ParseResult<_>>()?;
+
+    if r.byte()? != K_CODERS_UNPACK_SIZE {
+        return Err("expected coder unpack sizes in 7z header".into());
+    }
+    for folder in &mut info.folders {
+        for size in &mut folder.unpack_sizes {
+            *size = r.number()?;
+        }
+    }
+
+    loop {
+        match r.byte()? {
+            K_END => return Ok(()),
+            K_CRC => {
+                let crcs = r.digests(count)?;
+                for (folder, crc) in info.folders.iter_mut().zip(crcs) {
+                    folder.crc = crc;
+                }
+            }
+            other => return Err(format!("unexpected 7z unpack info property {:#x}", other).into()),
+        }
+    }
+}
+
+fn read_substreams_info(r: &mut HeaderReader, info: &mut StreamsInfo) -> ParseResult<()> {
+    let mut counts = vec![1usize; info.folders.len()];
+    let mut id = r.byte()?;
+
+    if id == K_NUM_UNPACK_STREAM {
+        for count in &mut counts {
+            *count = r.count()?;
+        }
+        id = r.byte()?;
+    }
+
+    // Sizes are stored for all but the last stream of each folder; the last takes the remainder
+    let mut sizes = Vec::new();
+    for (folder, &count) in info.folders.iter().zip(&counts) {
+        if count == 0 {
+            continue;
+        }
+        let mut sum = 0u64;
+        if id == K_SIZE {
+            for _ in 1..count {
+                let size = r.number()?;
+                sizes.push(size);
+                sum = sum.checked_add(size).ok_or("7z substream sizes overflow")?;
+            }
+        }
+        sizes.push(folder.unpack_size().saturating_sub(sum));
+    }
+    if id == K_SIZE {
+        id = r.byte()?;
+    }
+
+    // Folders holding a single stream with a known CRC don't repeat it here
+    let needs_digest = |folder: &Folder, count: usize| !(count == 1 && folder.crc.is_some());
+    let digest_count: usize = info.folders.iter().zip(&counts)
+    .filter(|(folder, count)| needs_digest(folder, **count))
+    .map(|(_, count)| *count)
+    .sum();
+
+    let mut digests = Vec::new();
+    loop {
+        match id {
+            K_END => break,
+            K_CRC => digests = r.digests(digest_count)?,
+            other => return Err(format!("unexpected 7z substream property {:#x}", other).into()),
+        }
+        id = r.byte()?;
+    }
+
+    let mut digests = digests.into_iter();
+    let mut crcs = Vec::with_capacity(sizes.len());
+    for (folder, &count) in info.folders.iter().zip(&counts) {
+        if needs_digest(folder, count) {
+            for _ in 0..count {
+                crcs.push(digests.next().flatten());
+            }
+        } else {
+            crcs.push(folder.crc);
+        }
+    }
+
+    info.folder_streams = counts;
+    info.stream_sizes = sizes;
+    info.stream_crcs = crcs;
+    Ok(())
+}
+
+fn read_streams_info(r: &mut HeaderReader) -> ParseResult<StreamsInfo> {
+    let mut info = StreamsInfo::default();
+    let mut has_substreams = false;
+
+    loop {
+        match r.byte()? {
+            K_END => break,
+            K_PACK_INFO => read_pack_info(r, &mut info)?,
+            K_UNPACK_INFO => read_unpack_info(r, &mut info)?,
+            K_SUBSTREAMS_INFO => {
+                read_substreams_info(r, &mut info)?;
+                has_substreams = true;
+            }
+            other => return Err(format!("unexpected 7z streams property {:#x}", other).into()),
+        }
+    }
+
+    // Without substream info every folder holds exactly one file
+    if !has_substreams {
+        info.folder_streams = vec![1; info.folders.len()];
+        info.stream_sizes = info.folders.iter().map(|f| f.unpack_size()).collect();
+        info.stream_crcs = info.folders.iter().map(|f| f.crc).collect();
+    }
+
+    Ok(info)
+}
+
+/// Every file in a 7z header, with the stream holding its data (None for empty files)
+struct SevenZipListing {
+    streams: StreamsInfo,
+    members: Vec<(ArchiveMember, Option<usize>)>,
+}
+
+fn read_files_info(r: &mut HeaderReader, streams: &StreamsInfo) -> ParseResult<Vec<(ArchiveMember, Option<usize>)>> {
+    let file_count = r.count()?;
+    let mut empty_stream = vec![false; file_count];
+    let mut empty_file = Vec::new();
+    let mut names = Vec::new();
+
+    loop {
+        let property = r.number()?;
+        if property == K_END as u64 {
+            break;
+        }
+        let len = r.count()?;
+        let mut data = HeaderReader::new(r.bytes(len)?);
+
+        let property = u8::try_from(property).map_err(|_| format!("invalid 7z file property {:#x}", property))?;
+        match property {
+            K_EMPTY_STREAM => empty_stream = data.bits(file_count)?,
+            K_EMPTY_FILE => {
+                let empty_count = empty_stream.iter().filter(|e| **e).count();
+                empty_file = data.bits(empty_count)?;
+            }
+            K_NAME => {
+                if data.byte()? != 0 {
+                    return Err("external 7z file names are not supported".into());
+                }
+                let utf16: Vec<u16> = data.data[data.pos..]
+                .chunks_exact(2)
+                .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+                .collect();
+                names = utf16.split(|c| *c == 0)
+                .take(file_count)
+                .map(String::from_utf16_lossy)
+                .collect();
+            }
+            // Timestamps, attributes and padding don't matter for ROM matching
+            _ => {}
+        }
+    }
+
+    let mut members = Vec::with_capacity(file_count);
+    let mut stream_index = 0;
+    let mut empty_index = 0;
+
+    for (i, is_empty_stream) in empty_stream.into_iter().enumerate() {
+        let name = base_name(names.get(i).map(String::as_str).unwrap_or_default());
+
+        if is_empty_stream {
+            // An empty stream is either a zero-length file or a directory
+            let is_file = empty_file.get(empty_index).copied().unwrap_or(false);
+            empty_index += 1;
+            if is_file {
+                members.push((ArchiveMember { name, size: 0, crc: 0 }, None));
+            }
+            continue;
+        }
+
+        let size = *streams.stream_sizes.get(stream_index).ok_or("7z file without a stream")?;
+        let crc = streams.stream_crcs.get(stream_index).copied().flatten().unwrap_or(0);
+        members.push((ArchiveMember { name, size, crc }, Some(stream_index)));
+        stream_index += 1;
+    }
+
+    Ok(members)
+}
+
+fn read_header(r: &mut HeaderReader) -> ParseResult<SevenZipListing> {
+    let mut streams = StreamsInfo::default();
+
+    loop {
+        match r.byte()? {
+            K_END => return Ok(SevenZipListing { streams, members: Vec::new() }),
+            K_ARCHIVE_PROPERTIES => loop {
+                if r.byte()? == K_END {
+                    break;
+                }
+                let len = r.count()?;
+                r.bytes(len)?;
+            },
+            K_ADDITIONAL_STREAMS_INFO => {
+                read_streams_info(r)?;
+            }
+            K_MAIN_STREAMS_INFO => streams = read_streams_info(r)?,
+            K_FILES_INFO => {
+                let members = read_files_info(r, &streams)?;
+                return Ok(SevenZipListing { streams, members });
+            }
+            other => return Err(format!("unexpected 7z header property {:#x}", other).into()),
+        }
+    }
+}
+
+/// Unpack a stream packed by a single LZMA, LZMA2 or stored coder. Headers always are,
+/// and so are the archives ROM managers write.
+fn decode_stream(coder: &Coder, packed: Vec<u8>, unpack_size: usize) -> ParseResult<Vec<u8>> {
+    let (mut stream, input) = match coder.id.as_slice() {
+        [0x00] => return Ok(packed),
+        [0x03, 0x01, 0x01] => {
+            // Prefixing the .lzma "alone" header gives the decoder the exact output size,
+            // since 7z's raw LZMA streams carry no end marker
+            let mut alone = Vec::with_capacity(13 + packed.len());
+            alone.extend_from_slice(&coder.properties);
+            alone.extend_from_slice(&(unpack_size as u64).to_le_bytes());
+            alone.extend_from_slice(&packed);
+            (Stream::new_lzma_decoder(u64::MAX)?, alone)
+        }
+        [0x21] => {
+            let mut filters = Filters::new();
+            filters.lzma2_properties(&coder.properties)?;
+            (Stream::new_raw_decoder(&filters)?, packed)
+        }
+        [0x06, 0xF1, 0x07, 0x01] => return Err("encrypted 7z archives are not supported".into()),
+        other => return Err(format!("unsupported 7z coder {:02x?}", other).into()),
+    };
+
+    let mut output = Vec::with_capacity(unpack_size);
+    while output.len() < unpack_size {
+        let consumed = (stream.total_in() as usize).min(input.len());
+        let produced = output.len();
+        let status = stream.process_vec(&input[consumed..], &mut output, Action::Finish)?;
+
+        if status == Status::StreamEnd {
+            break;
+        }
+        if stream.total_in() as usize == consumed && output.len() == produced {
+            break;
+        }
+    }
+
+    if output.len() < unpack_size {
+        return Err("7z stream ended early".into());
+    }
+    output.truncate(unpack_size);
+    Ok(output)
+}
+
+/// List the members of a 7z archive from its header
+pub fn list_7z_members(path: &Path) -> Result<Vec<ArchiveMember>, Box<dyn std::error::Error>> {
+    let listing = read_7z_listing(&mut File::open(path)?)?;
+    Ok(listing.members.into_iter().map(|(member, _)| member).collect())
+}
+
+/// Read `size` bytes stored `offset` bytes after the start header
+fn read_packed(file: &mut File, offset: u64, size: u64) -> ParseResult<Vec<u8>> {
+    let file_len = file.metadata()?.len();
+    let end = 32u64.checked_add(offset).and_then(|start| start.checked_add(size));
+    if end.is_none_or(|end| end > file_len) {
+        return Err("7z header lies outside the archive".into());
+    }
+
+    let mut packed = vec![0u8; size as usize];
+    file.seek(SeekFrom::Start(32 + offset))?;
+    file.read_exact(&mut packed)?;
+    Ok(packed)
+}
+
+/// The members of an archive, each with its unpacked contents
+pub type UnpackedMembers = Vec<(ArchiveMember, Vec<u8>)>;
+
+/// Unpack every member of a 7z archive, in the order `list_7z_members` lists them.
+/// Each folder is unpacked whole, so this is for sets rather than arbitrary archives.
+pub fn read_7z_files(path: &Path) -> Result<UnpackedMembers, Box<dyn std::error::Error>> {
+    let mut file = File::open(path)?;
+    let SevenZipListing { streams, members } = read_7z_listing(&mut file)?;
+
+    let mut unpacked: Vec<Vec<u8>> = Vec::with_capacity(streams.stream_sizes.len());
+    let mut pack_offset = streams.pack_pos;
+    for (i, folder) in streams.folders.iter().enumerate() {
+        // A folder with a single coder reads a single packed stream, in folder order
+        let coder = match folder.coders.as_slice() {
+            [coder] => coder,
+            _ => return Err("7z archives with chained coders can't be unpacked".into()),
+        };
+        let packed_size = *streams.pack_sizes.get(i).ok_or("7z folder without a packed stream")?;
+        let unpack_size = folder.unpack_size();
+        if packed_size > MAX_FOLDER_SIZE || unpack_size > MAX_FOLDER_SIZE {
+            return Err("7z folder is too large to unpack".into());
+        }
+
+        let packed = read_packed(&mut file, pack_offset, packed_size)?;
+        pack_offset = pack_offset.checked_add(packed_size).ok_or("7z header lies outside the archive")?;
+        let data = decode_stream(coder, packed, unpack_size as usize)?;
+
+        let mut rest = data.as_slice();
+        for _ in 0..streams.folder_streams.get(i).copied().unwrap_or(0) {
+            let size = *streams.stream_sizes.get(unpacked.len()).ok_or("7z stream without a size")?;
+            if size > rest.len() as u64 {
+                return Err("7z streams are larger than their folder".into());
+            }
+            let (stream, tail) = rest.split_at(size as usize);
+            unpacked.push(stream.to_vec());
+            rest = tail;
+        }
+    }
+
+    members.into_iter()
+    .map(|(member, stream)| {
+        let Some(index) = stream else {
+            return Ok((member, Vec::new()));
+        };
+        let data = std::mem::take(unpacked.get_mut(index).ok_or("7z file without a stream")?);
+        if streams.stream_crcs.get(index).copied().flatten().is_some_and(|crc| crc != crc32fast::hash(&data)) {
+            return Err(format!("{} fails its CRC check", member.name).into());
+        }
+        Ok((member, data))
+    })
+    .collect()
+}
+
+fn read_7z_listing(file: &mut File) -> ParseResult<SevenZipListing> {
+    let file_len = file.metadata()?.len();
+
+    let mut start = [0u8; 32];
+    file.read_exact(&mut start)?;
+    if start[0..6] != SEVEN_ZIP_SIGNATURE {
+        return Err("not a 7z archive".into());
+    }
+
+    let next_offset = u64::from_le_bytes(start[12..20].try_into()?);
+    let next_size = u64::from_le_bytes(start[20..28].try_into()?);
+    if next_size == 0 {
+        return Ok(SevenZipListing { streams: StreamsInfo::default(), members: Vec::new() });
+    }
+    let header_end = 32u64.checked_add(next_offset).and_then(|start| start.checked_add(next_size));
+    if next_size > MAX_HEADER_SIZE || header_end.is_none_or(|end| end > file_len) {
+        return Err("7z header lies outside the archive".into());
+    }
+
+    let mut header = vec![0u8; next_size as usize];
+    file.seek(SeekFrom::Start(32 + next_offset))?;
+    file.read_exact(&mut header)?;
+
+    // Encoded headers point at a packed stream holding the real header (possibly encoded again)
+    for _ in 0..4 {
+        let mut r = HeaderReader::new(&header);
+        match r.byte()? {
+            K_HEADER => return read_header(&mut r),
+            K_ENCODED_HEADER => {
+                let streams = read_streams_info(&mut r)?;
+                let folder = streams.folders.first().ok_or("encoded 7z header without a folder")?;
+                let coder = match folder.coders.as_slice() {
+                    [coder] => coder,
+                    _ => return Err("7z header uses chained coders".into()),
+                };
+                let packed_size = *streams.pack_sizes.first().ok_or("encoded 7z header without a packed stream")?;
+                let unpack_size = folder.unpack_size();
+                if packed_size > MAX_HEADER_SIZE || unpack_size > MAX_HEADER_SIZE {
+                    return Err("encoded 7z header is too large".into());
+                }
+
+                let packed = read_packed(file, streams.pack_pos, packed_size)?;
+                header = decode_stream(coder, packed, unpack_size as usize)?;
+            }
+            other => return Err(format!("unexpected 7z header type {:#x}", other).into()),
+        }
+    }
+
+    Err("7z header is nested too deeply".into())
+}
+
+// ============= END 7Z HEADER PARSER =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use liblzma::stream::LzmaOptions;
+
+    /// A file in a fresh temporary directory, removed again when dropped
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let dir = std::env::temp_dir().join(format!("rmameui-archive-{}-{}", std::process::id(), name));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join(name);
+            fs::write(&path, contents).unwrap();
+            TempFile(path)
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(self.0.parent().unwrap());
+        }
+    }
+
+    #[derive(Clone, Copy)]
+    enum Method {
+        Store,
+        Lzma,
+        Lzma2,
+    }
+
+    fn push_number(out: &mut Vec<u8>, n: u64) {
+        if n < 0x80 {
+            out.push(n as u8);
+        } else if n < 0x4000 {
+            out.extend_from_slice(&[0x80 | (n >> 8) as u8, n as u8]);
+        } else {
+            out.push(0xFF);
+            out.extend_from_slice(&n.to_le_bytes());
+        }
+    }
+
+    fn lzma_options() -> LzmaOptions {
+        let mut options = LzmaOptions::new_preset(1).unwrap();
+        options.dict_size(1 << 20).literal_context_bits(3).literal_position_bits(0).position_bits(2);
+        options
+    }
+
+    /// Compress `data` the way 7z stores it, returning the coder id and properties
+    fn compress(method: Method, data: &[u8]) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let mut filters = Filters::new();
+        let (id, properties) = match method {
+            Method::Store => return (vec![0x00], Vec::new(), data.to_vec()),
+            Method::Lzma => {
+                filters.lzma1(&lzma_options());
+                let mut properties = vec![(2 * 5) * 9 + 3];
+                properties.extend_from_slice(&(1u32 << 20).to_le_bytes());
+                (vec![0x03, 0x01, 0x01], properties)
+            }
+            Method::Lzma2 => {
+                filters.lzma2(&lzma_options());
+                // Dictionary size 2 << (16 / 2 + 11) = 1 MiB
+                (vec![0x21], vec![16])
+            }
+        };
+        let mut stream = Stream::new_raw_encoder(&filters).unwrap();
+        let mut packed = Vec::with_capacity(data.len() + 1024);
+        loop {
+            let consumed = stream.total_in() as usize;
+            if stream.process_vec(&data[consumed..], &mut packed, Action::Finish).unwrap() == Status::StreamEnd {
+                break;
+            }
+            packed.reserve(1024);
+        }
+        (id, properties, packed)
+    }
+
+    fn push_folder(out: &mut Vec<u8>, id: &[u8], properties: &[u8]) {
+        push_number(out, 1);
+        let flags = id.len() as u8 | if properties.is_empty() { 0 } else { 0x20 };
+        out.push(flags);
+        out.extend_from_slice(id);
+        if !properties.is_empty() {
+            push_number(out, properties.len() as u64);
+            out.extend_from_slice(properties);
+        }
+    }
+
+    /// Pack info, one folder and its unpack size, for a single packed stream at `pack_pos`
+    fn push_streams(out: &mut Vec<u8>, pack_pos: u64, packed_len: usize, id: &[u8], properties: &[u8], unpack_size: usize) {
+        out.push(K_PACK_INFO);
+        push_number(out, pack_pos);
+        push_number(out, 1);
+        out.push(K_SIZE);
+        push_number(out, packed_len as u64);
+        out.push(K_END);
+
+        out.push(K_UNPACK_INFO);
+        out.push(K_FOLDER);
+        push_number(out, 1);
+        out.push(0);
+        push_folder(out, id, properties);
+        out.push(K_CODERS_UNPACK_SIZE);
+        push_number(out, unpack_size as u64);
+        out.push(K_END);
+    }
+
+    /// A whole archive holding `files` in one solid folder, and a directory entry. The
+    /// header is written plainly, or itself packed with `header_method`.
+    fn build_7z(method: Method, header_method: Option<Method>, files: &[(&str, &[u8])]) -> Vec<u8> {
+        let data: Vec<u8> = files.iter().flat_map(|(_, d)| d.iter().copied()).collect();
+        let (id, properties, packed) = compress(method, &data);
+
+        let mut header = vec![K_HEADER, K_MAIN_STREAMS_INFO];
+        push_streams(&mut header, 0, packed.len(), &id, &properties, data.len());
+        header.push(K_SUBSTREAMS_INFO);
+        header.push(K_NUM_UNPACK_STREAM);
+        push_number(&mut header, files.len() as u64);
+        header.push(K_SIZE);
+        for (_, d) in &files[..files.len() - 1] {
+            push_number(&mut header, d.len() as u64);
+        }
+        header.push(K_CRC);
+        header.push(1);
+        for (_, d) in files {
+            header.extend_from_slice(&crc32fast::hash(d).to_le_bytes());
+        }
+        header.extend_from_slice(&[K_END, K_END]);
+
+        // The directory comes last and has no stream
+        let file_count = files.len() + 1;
+        header.push(K_FILES_INFO);
+        push_number(&mut header, file_count as u64);
+        let mut empty = vec![0u8; file_count.div_ceil(8)];
+        empty[files.len() / 8] |= 0x80 >> (files.len() % 8);
+        header.push(K_EMPTY_STREAM);
+        push_number(&mut header, empty.len() as u64);
+        header.extend_from_slice(&empty);
+        let mut names = vec![0u8];
+        for name in files.iter().map(|(n, _)| *n).chain(["sub"]) {
+            names.extend(name.encode_utf16().chain([0]).flat_map(u16::to_le_bytes));
+        }
+        header.push(K_NAME);
+        push_number(&mut header, names.len() as u64);
+        header.extend_from_slice(&names);
+        header.extend_from_slice(&[K_END, K_END]);
+
+        let mut body = packed;
+        if let Some(header_method) = header_method {
+            let (id, properties, packed_header) = compress(header_method, &header);
+            let pack_pos = body.len() as u64;
+            body.extend_from_slice(&packed_header);
+            let mut encoded = vec![K_ENCODED_HEADER];
+            push_streams(&mut encoded, pack_pos, packed_header.len(), &id, &properties, header.len());
+            encoded.push(K_END);
+            header = encoded;
+        }
+
+        start_header(body.len() as u64, header.len() as u64, body, &header)
+    }
+
+    fn start_header(next_offset: u64, next_size: u64, body: Vec<u8>, header: &[u8]) -> Vec<u8> {
+        let mut archive = SEVEN_ZIP_SIGNATURE.to_vec();
+        archive.extend_from_slice(&[0, 4]);
+        archive.extend_from_slice(&[0; 4]); // start header CRC, not checked
+        archive.extend_from_slice(&next_offset.to_le_bytes());
+        archive.extend_from_slice(&next_size.to_le_bytes());
+        archive.extend_from_slice(&crc32fast::hash(header).to_le_bytes());
+        archive.extend(body);
+        archive.extend_from_slice(header);
+        archive
+    }
+
+    const FILES: &[(&str, &[u8])] = &[
+        ("pacman.6e", b"first rom file contents"),
+        ("roms/pacman.6f", b"second"),
+        ("empty.bin", b""),
+        ("pacman.6h", b"the third and last rom in the set"),
+    ];
+
+    fn list(name: &str, archive: &[u8]) -> Result<Vec<ArchiveMember>, Box<dyn std::error::Error>> {
+        let file = TempFile::new(name, archive);
+        list_7z_members(&file.0)
+    }
+
+    fn assert_lists_files(members: &[ArchiveMember]) {
+        let expected: Vec<(String, u64, u32)> = FILES.iter()
+        .map(|(name, data)| (base_name(name), data.len() as u64, crc32fast::hash(data)))
+        .collect();
+        let listed: Vec<(String, u64, u32)> = members.iter().map(|m| (m.name.clone(), m.size, m.crc)).collect();
+        assert_eq!(listed, expected);
+    }
+
+    #[test]
+    fn lists_store_lzma_and_lzma2_archives() {
+        for (name, method) in [("store.7z", Method::Store), ("lzma.7z", Method::Lzma), ("lzma2.7z", Method::Lzma2)] {
+            assert_lists_files(&list(name, &build_7z(method, None, FILES)).unwrap());
+        }
+    }
+
+    #[test]
+    fn lists_archives_with_encoded_headers() {
+        for (name, header_method) in [("hstore.7z", Method::Store), ("hlzma.7z", Method::Lzma), ("hlzma2.7z", Method::Lzma2)] {
+            assert_lists_files(&list(name, &build_7z(Method::Lzma2, Some(header_method), FILES)).unwrap());
+        }
+    }
+
+    #[test]
+    fn unpacks_store_lzma_and_lzma2_archives() {
+        for (name, method) in [("ustore.7z", Method::Store), ("ulzma.7z", Method::Lzma), ("ulzma2.7z", Method::Lzma2)] {
+            let file = TempFile::new(name, &build_7z(method, Some(Method::Lzma), FILES));
+            let unpacked: Vec<(String, Vec<u8>)> = read_7z_files(&file.0).unwrap().into_iter()
+            .map(|(member, data)| (member.name, data))
+            .collect();
+            let expected: Vec<(String, Vec<u8>)> = FILES.iter().map(|(name, data)| (base_name(name), data.to_vec())).collect();
+            assert_eq!(unpacked, expected);
+        }
+    }
+
+    #[test]
+    fn unpacking_checks_crcs() {
+        let mut archive = build_7z(Method::Store, None, FILES);
+        // The first file's data starts right after the start header
+        archive[32] ^= 0xFF;
+        let file = TempFile::new("badcrc.7z", &archive);
+        let error = read_7z_files(&file.0).unwrap_err();
+        assert!(error.to_string().contains("CRC"), "{}", error);
+    }
+
+    #[test]
+    fn empty_archive_has_no_members() {
+        assert!(list("empty.7z", &start_header(0, 0, Vec::new(), &[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let archive = build_7z(Method::Store, None, FILES);
+        // Cutting the file short leaves the header pointing past the end
+        assert!(list("cut.7z", &archive[..archive.len() - 5]).is_err());
+        assert!(list("short.7z", &archive[..20]).is_err());
+
+        // A header that ends early, with the start header agreeing on its size
+        let body_len = archive.len() - 32 - header_len(&archive);
+        let header = &archive[32 + body_len..archive.len() - 10];
+        let truncated = start_header(body_len as u64, header.len() as u64, archive[32..32 + body_len].to_vec(), header);
+        let error = list("trunc.7z", &truncated).unwrap_err();
+        assert!(error.to_string().contains("truncated"), "{}", error);
+    }
+
+    fn header_len(archive: &[u8]) -> usize {
+        u64::from_le_bytes(archive[20..28].try_into().unwrap()) as usize
+    }
+
+    #[test]
+    fn rejects_header_offsets_that_overflow() {
+        let archive = build_7z(Method::Store, None, FILES);
+        let mut header_offset = archive.clone();
+        header_offset[12..20].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+        let error = list("offset.7z", &header_offset).unwrap_err();
+        assert!(error.to_string().contains("outside"), "{}", error);
+
+        let mut packed_offset = vec![K_ENCODED_HEADER];
+        push_streams(&mut packed_offset, u64::MAX - 16, 64, &[0x00], &[], 64);
+        packed_offset.push(K_END);
+        let error = list("packpos.7z", &start_header(0, packed_offset.len() as u64, Vec::new(), &packed_offset)).unwrap_err();
+        assert!(error.to_string().contains("outside"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_coder_stream_counts_that_overflow() {
+        // One folder of two coders
+        let mut header = vec![K_HEADER, K_MAIN_STREAMS_INFO, K_UNPACK_INFO, K_FOLDER, 1, 0, 2];
+        for _ in 0..2 {
+            // A complex coder claiming u64::MAX output streams
+            header.extend_from_slice(&[0x11, 0x00]);
+            push_number(&mut header, 1);
+            push_number(&mut header, u64::MAX);
+        }
+        let error = list("streams.7z", &start_header(0, header.len() as u64, Vec::new(), &header)).unwrap_err();
+        assert!(error.to_string().contains("coder streams"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_file_properties_above_a_byte() {
+        let mut header = vec![K_HEADER, K_FILES_INFO];
+        push_number(&mut header, 1);
+        // 0x111 would read as K_NAME if cut down to a byte
+        push_number(&mut header, 0x111);
+        push_number(&mut header, 1);
+        header.extend_from_slice(&[0, K_END, K_END]);
+        let error = list("property.7z", &start_header(0, header.len() as u64, Vec::new(), &header)).unwrap_err();
+        assert!(error.to_string().contains("invalid 7z file property"), "{}", error);
+    }
+}
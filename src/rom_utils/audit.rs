@@ -0,0 +1,773 @@
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+use rayon::prelude::*;
+use quick_xml::escape::escape;
+use serde::{Deserialize, Serialize};
+use crate::models::{
+    GameMetadata, RomEntry, DiskEntry, DumpStatus, AuditStatus, AuditProblem, AuditProblemKind,
+    SetAuditResult, AuditReport,
+};
+use super::archive::{
+    ArchiveMember, archive_set_name, directory_rom_files, directory_set_name, list_archive_members,
+    list_directory_members,
+};
+
+// ============= ARCHIVE FINGERPRINT CACHE =============
+
+/// Bump when the cached member format changes so stale caches are discarded
+const ARCHIVE_CACHE_VERSION: u32 = 1;
+
+/// What an archive or set folder looked like the last time its directory was read
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ArchiveFingerprint {
+    pub size: u64,
+    pub mtime: u64,
+    pub members: Vec<ArchiveMember>,
+}
+
+/// Member listings of the archives and set folders seen by past scans, keyed by path
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ArchiveCache {
+    pub format_version: u32,
+    pub archives: HashMap<PathBuf, ArchiveFingerprint>,
+}
+
+fn file_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some((meta.len(), mtime))
+}
+
+/// A folder changes when any file in it does, so combine the folder's own entry with its files
+fn set_fingerprint(path: &Path) -> Option<(u64, u64)> {
+    if !path.is_dir() {
+        return file_fingerprint(path);
+    }
+
+    let (_, mut mtime) = file_fingerprint(path)?;
+    let mut size = 0;
+    for file in directory_rom_files(path) {
+        let (file_size, file_mtime) = file_fingerprint(&file)?;
+        size += file_size;
+        mtime = mtime.max(file_mtime);
+    }
+    Some((size, mtime))
+}
+
+pub fn load_archive_cache(path: &Path) -> ArchiveCache {
+    let cache = File::open(path).ok()
+    .and_then(|file| serde_json::from_reader::<_, ArchiveCache>(std::io::BufReader::new(file)).ok());
+
+    match cache {
+        Some(cache) if cache.format_version == ARCHIVE_CACHE_VERSION => cache,
+        _ => ArchiveCache {
+            format_version: ARCHIVE_CACHE_VERSION,
+            archives: HashMap::new(),
+        },
+    }
+}
+
+pub fn save_archive_cache(cache: &ArchiveCache, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let writer = std::io::BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(writer, cache)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+// ============= END ARCHIVE FINGERPRINT CACHE =============
+
+/// Where a set's files live on the rompath
+#[derive(Clone, Debug, Default)]
+pub struct SetLocation {
+    pub archive: Option<PathBuf>,
+    pub members: Vec<ArchiveMember>,
+    /// Folder named after the set: holds its CHDs, and its ROMs for uncompressed sets
+    pub directory: Option<PathBuf>,
+    pub directory_is_set: bool,
+}
+
+/// Read the SHA1 MAME records for a CHD from its header (versions 3 to 5)
+pub fn read_chd_sha1(path: &Path) -> Option<String> {
+    let mut header = [0u8; 124];
+    let mut file = File::open(path).ok()?;
+    let read = file.read(&mut header).ok()?;
+
+    if read < 16 || &header[0..8] != b"MComprHD" {
+        return None;
+    }
+
+    let version = u32::from_be_bytes(header[12..16].try_into().ok()?);
+    let offset = match version {
+        3 => 80,
+        4 => 48,
+        5 => 84,
+        _ => return None,
+    };
+    if read < offset + 20 {
+        return None;
+    }
+
+    Some(header[offset..offset + 20].iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Index every set on the rompath. Like MAME, the first directory that has a set wins.
+///
+/// Member listings are reused from `cache` for archives and set folders that haven't
+/// changed, and the cache is updated with what this scan read.
+pub fn index_rom_dirs(
+    rom_dirs: &[PathBuf],
+    metadata: &HashMap<String, GameMetadata>,
+    cache: &mut ArchiveCache,
+    progress: &(dyn Fn(String) + Sync),
+) -> HashMap<String, SetLocation> {
+    let mut index: HashMap<String, SetLocation> = HashMap::new();
+
+    for dir in rom_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                if let Some(name) = path.file_name().and_then(|s| s.to_str()) {
+                    let location = index.entry(name.to_string()).or_default();
+                    if location.directory.is_none() {
+                        location.directory_is_set = directory_set_name(&path, metadata).is_some()
+                            && !directory_rom_files(&path).is_empty();
+                        location.directory = Some(path.clone());
+                    }
+                }
+            } else if let Some(stem) = archive_set_name(&path) {
+                // MAME tries .zip before .7z when both exist in the same directory
+                let location = index.entry(stem.to_string()).or_default();
+                let replaces_7z = location.archive.as_ref()
+                .map(|existing| existing.parent() == path.parent() && existing.extension().is_some_and(|e| e.eq_ignore_ascii_case("7z")))
+                .unwrap_or(false);
+                if location.archive.is_none() || (replaces_7z && !path.extension().is_some_and(|e| e.eq_ignore_ascii_case("7z"))) {
+                    location.archive = Some(path.clone());
+                }
+            }
+        }
+    }
+
+    let total: usize = index.values()
+    .map(|l| l.archive.is_some() as usize + l.directory_is_set as usize)
+    .sum();
+    let scanned = AtomicUsize::new(0);
+    let rescanned = AtomicUsize::new(0);
+    progress(format!("Scanning archive contents: 0 / {}", total));
+
+    // Only archives and folders whose size or mtime moved since the last audit are read again
+    let previous = &cache.archives;
+    let fingerprints: Vec<(PathBuf, ArchiveFingerprint)> = index.par_iter_mut()
+    .flat_map_iter(|(_, location)| {
+        let folder = location.directory.clone().filter(|_| location.directory_is_set);
+        let mut listed = Vec::new();
+
+        for source in location.archive.clone().into_iter().chain(folder) {
+            let Some((size, mtime)) = set_fingerprint(&source) else {
+                continue;
+            };
+
+            let done = scanned.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(500) {
+                progress(format!("Scanning archive contents: {} / {}", done, total));
+            }
+
+            if let Some(cached) = previous.get(&source).filter(|c| c.size == size && c.mtime == mtime) {
+                location.members.extend(cached.members.iter().cloned());
+                listed.push((source, cached.clone()));
+                continue;
+            }
+
+            rescanned.fetch_add(1, Ordering::Relaxed);
+            let members = if source.is_dir() {
+                list_directory_members(&source)
+            } else {
+                list_archive_members(&source)
+            };
+            match members {
+                Ok(members) => {
+                    location.members.extend(members.iter().cloned());
+                    listed.push((source, ArchiveFingerprint { size, mtime, members }));
+                }
+                Err(e) => eprintln!("Failed to read {:?}: {}", source, e),
+            }
+        }
+
+        listed
+    })
+    .collect();
+
+    let rescanned = rescanned.into_inner();
+    let listed = fingerprints.len();
+    cache.archives.extend(fingerprints);
+
+    // Other scans share the cache, so only entries for this scan's directories are
+    // dropped, and only once their archive or folder is gone
+    let before = cache.archives.len();
+    cache.archives.retain(|path, _| {
+        let scanned = path.parent().is_some_and(|parent| rom_dirs.iter().any(|dir| dir == parent));
+        !scanned || path.exists()
+    });
+    let removed = before - cache.archives.len();
+    progress(format!(
+        "Scanned {} archives and folders: {} unchanged, {} new or changed, {} removed",
+        total,
+        listed - rescanned,
+        rescanned,
+        removed
+    ));
+
+    index
+}
+
+/// Result of checking one ROM or disk entry
+enum FileCheck {
+    Good,
+    BestAvailable,
+    Skipped,
+    Missing(AuditProblem),
+    Incorrect(AuditProblem),
+}
+
+fn check_rom(rom: &RomEntry, pool: &[&ArchiveMember]) -> FileCheck {
+    let by_name = pool.iter().find(|m| m.name.eq_ignore_ascii_case(&rom.name));
+
+    // Nothing to verify against; MAME reports these as best available whether present or not
+    let Some(crc) = rom.crc.as_deref().and_then(|c| u32::from_str_radix(c, 16).ok()) else {
+        return if by_name.is_some() || rom.status == DumpStatus::NoDump {
+            FileCheck::BestAvailable
+        } else {
+            FileCheck::Skipped
+        };
+    };
+    if rom.status == DumpStatus::NoDump {
+        return FileCheck::BestAvailable;
+    }
+
+    // Names differ between set layouts, so the checksum is what identifies a file
+    if pool.iter().any(|m| m.crc == crc && m.size == rom.size) {
+        return if rom.status == DumpStatus::BadDump {
+            FileCheck::BestAvailable
+        } else {
+            FileCheck::Good
+        };
+    }
+
+    match by_name {
+        Some(member) if member.size != rom.size => FileCheck::Incorrect(AuditProblem {
+            file: rom.name.clone(),
+            kind: AuditProblemKind::WrongSize,
+            expected: Some(rom.size.to_string()),
+            found: Some(member.size.to_string()),
+        }),
+        Some(_) if rom.status == DumpStatus::BadDump => FileCheck::BestAvailable,
+        Some(member) => FileCheck::Incorrect(AuditProblem {
+            file: rom.name.clone(),
+            kind: AuditProblemKind::BadCrc,
+            expected: Some(format!("{:08x}", crc)),
+            found: Some(format!("{:08x}", member.crc)),
+        }),
+        None if rom.optional => FileCheck::Skipped,
+        None => FileCheck::Missing(AuditProblem {
+            file: rom.name.clone(),
+            kind: AuditProblemKind::Missing,
+            expected: Some(format!("{:08x}", crc)),
+            found: None,
+        }),
+    }
+}
+
+fn check_disk(disk: &DiskEntry, chd_dirs: &[&PathBuf]) -> FileCheck {
+    let file_name = format!("{}.chd", disk.name);
+    let found = chd_dirs.iter().map(|dir| dir.join(&file_name)).find(|p| p.is_file());
+
+    let Some(path) = found else {
+        return match disk.status {
+            DumpStatus::NoDump => FileCheck::BestAvailable,
+            _ if disk.optional => FileCheck::Skipped,
+            _ => FileCheck::Missing(AuditProblem {
+                file: file_name,
+                kind: AuditProblemKind::Missing,
+                expected: disk.sha1.clone(),
+                found: None,
+            }),
+        };
+    };
+
+    match (&disk.sha1, read_chd_sha1(&path)) {
+        (Some(expected), Some(actual)) if !expected.eq_ignore_ascii_case(&actual) => {
+            if disk.status == DumpStatus::BadDump {
+                FileCheck::BestAvailable
+            } else {
+                FileCheck::Incorrect(AuditProblem {
+                    file: file_name,
+                    kind: AuditProblemKind::BadCrc,
+                    expected: Some(expected.clone()),
+                    found: Some(actual),
+                })
+            }
+        }
+        _ if disk.status != DumpStatus::Good => FileCheck::BestAvailable,
+        _ => FileCheck::Good,
+    }
+}
+
+/// The set itself followed by its `romof` ancestors (parent, then BIOS), in MAME's search order
+fn romof_chain(name: &str, metadata: &HashMap<String, GameMetadata>) -> Vec<String> {
+    let mut chain = vec![name.to_string()];
+    let mut current = metadata.get(name).and_then(|m| m.romof.clone());
+
+    while let Some(next) = current {
+        if chain.contains(&next) || chain.len() > 8 {
+            break;
+        }
+        current = metadata.get(&next).and_then(|m| m.romof.clone());
+        chain.push(next);
+    }
+
+    chain
+}
+
+/// Members of a set's archive or folder that neither the set, its clones (merged sets)
+/// nor its devices need
+pub fn unneeded_members<'a>(
+    name: &str,
+    metadata: &HashMap<String, GameMetadata>,
+    members: &'a [ArchiveMember],
+    clones: &HashMap<String, Vec<String>>,
+) -> Vec<&'a ArchiveMember> {
+    let Some(meta) = metadata.get(name) else {
+        return Vec::new();
+    };
+
+    let mut needed_crcs = HashSet::new();
+    let mut needed_names = HashSet::new();
+    let related = std::iter::once(name)
+    .chain(clones.get(name).into_iter().flatten().map(|s| s.as_str()))
+    .chain(meta.device_refs.iter().map(|s| s.as_str()));
+
+    for set in related {
+        for rom in metadata.get(set).map(|m| m.roms.as_slice()).unwrap_or_default() {
+            needed_names.insert(rom.name.to_lowercase());
+            if let Some(crc) = rom.crc.as_deref().and_then(|c| u32::from_str_radix(c, 16).ok()) {
+                needed_crcs.insert(crc);
+            }
+        }
+    }
+
+    members.iter()
+    .filter(|m| !needed_crcs.contains(&m.crc) && !needed_names.contains(&m.name.to_lowercase()))
+    .collect()
+}
+
+/// Map each parent set to its clones
+pub fn clone_map(metadata: &HashMap<String, GameMetadata>) -> HashMap<String, Vec<String>> {
+    let mut clones: HashMap<String, Vec<String>> = HashMap::new();
+    for (name, meta) in metadata {
+        if let Some(parent) = &meta.parent {
+            clones.entry(parent.clone()).or_default().push(name.clone());
+        }
+    }
+    clones
+}
+
+/// Audit one set against the rompath index
+pub fn audit_set(
+    name: &str,
+    metadata: &HashMap<String, GameMetadata>,
+    index: &HashMap<String, SetLocation>,
+    clones: &HashMap<String, Vec<String>>,
+) -> SetAuditResult {
+    let mut result = SetAuditResult {
+        name: name.to_string(),
+        status: AuditStatus::NoRomsNeeded,
+        problems: Vec::new(),
+    };
+    let Some(meta) = metadata.get(name) else {
+        result.status = AuditStatus::NotFound;
+        return result;
+    };
+
+    let chain = romof_chain(name, metadata);
+    let pool: Vec<&ArchiveMember> = chain.iter()
+    .filter_map(|set| index.get(set))
+    .flat_map(|location| location.members.iter())
+    .collect();
+    let chd_dirs: Vec<&PathBuf> = chain.iter()
+    .filter_map(|set| index.get(set).and_then(|l| l.directory.as_ref()))
+    .collect();
+
+    // (check, belongs to this set rather than a parent, BIOS or device)
+    let mut checks: Vec<(FileCheck, bool)> = Vec::new();
+    for rom in &meta.roms {
+        checks.push((check_rom(rom, &pool), rom.merge.is_none()));
+    }
+    for disk in &meta.disks {
+        checks.push((check_disk(disk, &chd_dirs), disk.merge.is_none()));
+    }
+
+    // Device ROMs are looked up in the set's own chain and in the device's archive
+    let mut seen_devices = HashSet::new();
+    for device in &meta.device_refs {
+        if !seen_devices.insert(device.as_str()) {
+            continue;
+        }
+        let Some(device_meta) = metadata.get(device).filter(|d| !d.roms.is_empty()) else {
+            continue;
+        };
+        let mut device_pool = pool.clone();
+        if let Some(location) = index.get(device) {
+            device_pool.extend(location.members.iter());
+        }
+        for rom in &device_meta.roms {
+            checks.push((check_rom(rom, &device_pool), false));
+        }
+    }
+
+    if checks.is_empty() {
+        return result;
+    }
+
+    let mut own_required = 0;
+    let mut own_found = 0;
+    let mut required = 0;
+    let mut found = 0;
+    let mut incorrect = false;
+    let mut missing = false;
+    let mut best_available = false;
+
+    for (check, own) in checks {
+        let present = match check {
+            FileCheck::Skipped => continue,
+            FileCheck::Good => true,
+            FileCheck::BestAvailable => {
+                best_available = true;
+                true
+            }
+            FileCheck::Incorrect(problem) => {
+                incorrect = true;
+                result.problems.push(problem);
+                true
+            }
+            FileCheck::Missing(problem) => {
+                missing = true;
+                result.problems.push(problem);
+                false
+            }
+        };
+
+        required += 1;
+        found += present as usize;
+        if own {
+            own_required += 1;
+            own_found += present as usize;
+        }
+    }
+
+    let nothing_found = if own_required > 0 { own_found == 0 } else { required > 0 && found == 0 };
+    result.status = if nothing_found {
+        AuditStatus::NotFound
+    } else if incorrect {
+        AuditStatus::Incorrect
+    } else if missing {
+        AuditStatus::MissingFiles
+    } else if best_available {
+        AuditStatus::BestAvailable
+    } else {
+        AuditStatus::Good
+    };
+
+    // Anything in the set's own archive that neither it, its clones (merged sets) nor its devices need
+    if let Some(location) = index.get(name) {
+        for member in unneeded_members(name, metadata, &location.members, clones) {
+            result.problems.push(AuditProblem {
+                file: member.name.clone(),
+                kind: AuditProblemKind::Unneeded,
+                expected: None,
+                found: Some(format!("{:08x}", member.crc)),
+            });
+        }
+    }
+
+    result
+}
+
+/// Audit every machine in `metadata` against the sets found in `rom_dirs`
+pub fn audit_rom_sets(
+    rom_dirs: &[PathBuf],
+    metadata: &HashMap<String, GameMetadata>,
+    cache: &mut ArchiveCache,
+    progress: &(dyn Fn(String) + Sync),
+) -> HashMap<String, SetAuditResult> {
+    progress("Indexing ROM directories...".to_string());
+    let index = index_rom_dirs(rom_dirs, metadata, cache, progress);
+    let clones = clone_map(metadata);
+
+    progress(format!("Verifying {} sets against listxml checksums...", metadata.len()));
+    metadata.par_iter()
+    .map(|(name, _)| (name.clone(), audit_set(name, metadata, &index, &clones)))
+    .collect()
+}
+
+/// Write the playable sets in the `[AVAILABLE]` ini format read by `load_roms_from_audit`
+pub fn write_available_ini(results: &HashMap<String, SetAuditResult>, path: &Path) -> std::io::Result<()> {
+    let mut available: Vec<&str> = results.values()
+    .filter(|r| r.status.is_playable() && r.status != AuditStatus::NoRomsNeeded)
+    .map(|r| r.name.as_str())
+    .collect();
+    available.sort_unstable();
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+    writeln!(file, "[AVAILABLE]")?;
+    for name in available {
+        writeln!(file, "{} = 1", name)?;
+    }
+    file.flush()
+}
+
+/// Write a Logiqx XML DAT listing only the missing and bad ROMs and disks of each audited set,
+/// so a ROM manager can complete the collection. Returns the number of sets written.
+pub fn write_fix_dat(
+    results: &HashMap<String, SetAuditResult>,
+    metadata: &HashMap<String, GameMetadata>,
+    mame_version: &str,
+    path: &Path,
+) -> std::io::Result<usize> {
+    let mut names: Vec<&String> = results.keys().collect();
+    names.sort_unstable();
+
+    let mut file = std::io::BufWriter::new(File::create(path)?);
+    writeln!(file, "<?xml version=\"1.0\"?>")?;
+    writeln!(file, "<!DOCTYPE datafile PUBLIC \"-//Logiqx//DTD ROM Management Datafile//EN\" \"http://www.logiqx.com/Dats/datafile.dtd\">")?;
+    writeln!(file, "<datafile>")?;
+    writeln!(file, "\t<header>")?;
+    writeln!(file, "\t\t<name>fix_{}</name>", escape(mame_version))?;
+    writeln!(file, "\t\t<description>Missing and bad files for {}</description>", escape(mame_version))?;
+    writeln!(file, "\t\t<version>{}</version>", chrono::Local::now().format("%Y%m%d"))?;
+    writeln!(file, "\t\t<author>RMAMEUI</author>")?;
+    writeln!(file, "\t</header>")?;
+
+    let mut written = 0;
+    for name in names {
+        let (Some(result), Some(machine)) = (results.get(name), metadata.get(name)) else {
+            continue;
+        };
+        let wanted: Vec<&AuditProblem> = result.problems.iter()
+        .filter(|p| p.kind != AuditProblemKind::Unneeded)
+        .collect();
+        if wanted.is_empty() {
+            continue;
+        }
+
+        // Device ROMs are audited as part of the set, so look them up there too
+        let machines = std::iter::once(machine)
+        .chain(machine.device_refs.iter().filter_map(|d| metadata.get(d)));
+        let roms: Vec<&RomEntry> = machines.clone().flat_map(|m| m.roms.iter()).collect();
+        let disks: Vec<&DiskEntry> = machines.flat_map(|m| m.disks.iter()).collect();
+
+        write!(file, "\t<game name=\"{}\"", escape(name))?;
+        if let Some(parent) = &machine.parent {
+            write!(file, " cloneof=\"{}\"", escape(parent))?;
+        }
+        if let Some(romof) = &machine.romof {
+            write!(file, " romof=\"{}\"", escape(romof))?;
+        }
+        writeln!(file, ">")?;
+        writeln!(file, "\t\t<description>{}</description>", escape(&machine.description))?;
+
+        for problem in wanted {
+            if let Some(rom) = roms.iter().find(|r| r.name == problem.file) {
+                write!(file, "\t\t<rom name=\"{}\" size=\"{}\"", escape(&rom.name), rom.size)?;
+                if let Some(crc) = &rom.crc {
+                    write!(file, " crc=\"{}\"", crc)?;
+                }
+                if let Some(sha1) = &rom.sha1 {
+                    write!(file, " sha1=\"{}\"", sha1)?;
+                }
+                if let Some(merge) = &rom.merge {
+                    write!(file, " merge=\"{}\"", escape(merge))?;
+                }
+                writeln!(file, "/>")?;
+            } else if let Some(disk) = disks.iter().find(|d| format!("{}.chd", d.name) == problem.file) {
+                write!(file, "\t\t<disk name=\"{}\"", escape(&disk.name))?;
+                if let Some(sha1) = &disk.sha1 {
+                    write!(file, " sha1=\"{}\"", sha1)?;
+                }
+                if let Some(merge) = &disk.merge {
+                    write!(file, " merge=\"{}\"", escape(merge))?;
+                }
+                writeln!(file, "/>")?;
+            }
+        }
+
+        writeln!(file, "\t</game>")?;
+        written += 1;
+    }
+
+    writeln!(file, "</datafile>")?;
+    file.flush()?;
+    Ok(written)
+}
+
+/// Persist a full audit report as JSON, replacing any previous report atomically
+pub fn save_audit_report(report: &AuditReport, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let tmp_path = path.with_extension("json.tmp");
+    let writer = std::io::BufWriter::new(File::create(&tmp_path)?);
+    serde_json::to_writer(writer, report)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+pub fn load_audit_report(path: &Path) -> Option<AuditReport> {
+    let file = File::open(path).ok()?;
+    match serde_json::from_reader(std::io::BufReader::new(file)) {
+        Ok(report) => Some(report),
+        Err(e) => {
+            eprintln!("Ignoring unreadable audit report {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rom(name: &str, size: u64, crc: &str) -> RomEntry {
+        RomEntry {
+            name: name.to_string(),
+            size,
+            crc: Some(crc.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn member(name: &str, size: u64, crc: u32) -> ArchiveMember {
+        ArchiveMember { name: name.to_string(), size, crc }
+    }
+
+    fn machine(name: &str, romof: Option<&str>, roms: Vec<RomEntry>) -> (String, GameMetadata) {
+        (name.to_string(), GameMetadata {
+            name: name.to_string(),
+            parent: romof.map(str::to_string),
+            is_clone: romof.is_some(),
+            romof: romof.map(str::to_string),
+            runnable: true,
+            roms,
+            ..Default::default()
+        })
+    }
+
+    fn located(members: Vec<ArchiveMember>) -> SetLocation {
+        SetLocation { archive: Some(PathBuf::from("set.zip")), members, ..Default::default() }
+    }
+
+    #[test]
+    fn roms_match_by_checksum_before_name() {
+        let good = member("renamed.bin", 16, 0x1234abcd);
+        let wrong_size = member("prog.bin", 8, 0x1234abcd);
+        let wrong_crc = member("prog.bin", 16, 0xdeadbeef);
+        let wanted = rom("prog.bin", 16, "1234abcd");
+
+        assert!(matches!(check_rom(&wanted, &[&good]), FileCheck::Good));
+        assert!(matches!(check_rom(&wanted, &[]), FileCheck::Missing(_)));
+        match check_rom(&wanted, &[&wrong_size]) {
+            FileCheck::Incorrect(problem) => assert_eq!(problem.kind, AuditProblemKind::WrongSize),
+            _ => panic!("expected a wrong size"),
+        }
+        match check_rom(&wanted, &[&wrong_crc]) {
+            FileCheck::Incorrect(problem) => {
+                assert_eq!(problem.kind, AuditProblemKind::BadCrc);
+                assert_eq!(problem.found.as_deref(), Some("deadbeef"));
+            }
+            _ => panic!("expected a bad CRC"),
+        }
+    }
+
+    #[test]
+    fn dump_status_and_optional_roms() {
+        let nodump = RomEntry { status: DumpStatus::NoDump, crc: None, ..rom("pal.bin", 260, "") };
+        assert!(matches!(check_rom(&nodump, &[]), FileCheck::BestAvailable));
+
+        let baddump = RomEntry { status: DumpStatus::BadDump, ..rom("gfx.bin", 16, "00000001") };
+        let present = member("gfx.bin", 16, 0x00000002);
+        assert!(matches!(check_rom(&baddump, &[&present]), FileCheck::BestAvailable));
+
+        let optional = RomEntry { optional: true, ..rom("extra.bin", 16, "00000003") };
+        assert!(matches!(check_rom(&optional, &[]), FileCheck::Skipped));
+    }
+
+    #[test]
+    fn clones_find_merged_roms_through_romof() {
+        let shared = rom("shared.bin", 32, "0000aaaa");
+        let merged = RomEntry { merge: Some("shared.bin".to_string()), ..shared.clone() };
+        let metadata: HashMap<String, GameMetadata> = [
+            machine("parent", None, vec![shared, rom("p.bin", 16, "0000bbbb")]),
+            machine("clone", Some("parent"), vec![merged, rom("c.bin", 16, "0000cccc")]),
+        ].into_iter().collect();
+        let clones = clone_map(&metadata);
+
+        // Split sets: the clone's archive holds only its own ROM
+        let index: HashMap<String, SetLocation> = [
+            ("parent".to_string(), located(vec![member("shared.bin", 32, 0xaaaa), member("p.bin", 16, 0xbbbb)])),
+            ("clone".to_string(), located(vec![member("c.bin", 16, 0xcccc)])),
+        ].into_iter().collect();
+        assert_eq!(audit_set("clone", &metadata, &index, &clones).status, AuditStatus::Good);
+        assert_eq!(audit_set("parent", &metadata, &index, &clones).status, AuditStatus::Good);
+
+        // Without the parent only the merged ROM is missing, so the clone's own files still count
+        let without_parent: HashMap<String, SetLocation> = index.into_iter().filter(|(k, _)| k == "clone").collect();
+        let result = audit_set("clone", &metadata, &without_parent, &clones);
+        assert_eq!(result.status, AuditStatus::MissingFiles);
+        assert_eq!(result.problems[0].file, "shared.bin");
+        assert_eq!(audit_set("parent", &metadata, &without_parent, &clones).status, AuditStatus::NotFound);
+    }
+
+    #[test]
+    fn unneeded_files_are_reported_but_clone_files_are_kept() {
+        let metadata: HashMap<String, GameMetadata> = [
+            machine("parent", None, vec![rom("p.bin", 16, "0000bbbb")]),
+            machine("clone", Some("parent"), vec![rom("c.bin", 16, "0000cccc")]),
+        ].into_iter().collect();
+        let clones = clone_map(&metadata);
+
+        // A merged set carries the clone's ROM in the parent's archive
+        let index: HashMap<String, SetLocation> = [(
+            "parent".to_string(),
+            located(vec![member("p.bin", 16, 0xbbbb), member("c.bin", 16, 0xcccc), member("readme.txt", 4, 0x1)]),
+        )].into_iter().collect();
+
+        let result = audit_set("parent", &metadata, &index, &clones);
+        assert_eq!(result.status, AuditStatus::Good);
+        assert_eq!(result.problems.len(), 1);
+        assert_eq!(result.problems[0].kind, AuditProblemKind::Unneeded);
+        assert_eq!(result.problems[0].file, "readme.txt");
+    }
+
+    #[test]
+    fn sets_without_files_need_nothing() {
+        let metadata: HashMap<String, GameMetadata> = [machine("nofiles", None, Vec::new())].into_iter().collect();
+        let result = audit_set("nofiles", &metadata, &HashMap::new(), &HashMap::new());
+        assert_eq!(result.status, AuditStatus::NoRomsNeeded);
+        assert_eq!(audit_set("unknown", &metadata, &HashMap::new(), &HashMap::new()).status, AuditStatus::NotFound);
+    }
+}
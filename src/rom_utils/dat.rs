@@ -0,0 +1,337 @@
+use std::path::Path;
+use std::collections::HashMap;
+use std::fs;
+use crate::models::{GameMetadata, RomEntry, DiskEntry, DumpStatus, CustomCollection, SetAuditResult, AuditReport};
+use crate::mame_utils::parse_listxml;
+use super::audit::{audit_rom_sets, load_archive_cache, save_archive_cache, load_audit_report, save_audit_report};
+
+// ============= DAT IMPORT =============
+//
+// Third-party DATs come in two flavours: Logiqx XML, which is close enough to MAME's
+// listxml to go through the same parser, and the older ClrMamePro text format:
+//
+//     game (
+//         name pacman
+//         description "Pac-Man (Midway)"
+//         rom ( name pacman.6e size 4096 crc c1e6ab10 )
+//     )
+
+/// Windows-1252 characters for bytes 0x80 to 0x9F; the rest match Latin-1. Unassigned
+/// bytes decode to U+FFFD.
+const CP1252_HIGH: [char; 32] = [
+    '€', '\u{FFFD}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{FFFD}', 'Ž', '\u{FFFD}',
+    '\u{FFFD}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{FFFD}', 'ž', 'Ÿ',
+];
+
+/// DAT text as UTF-8, or as Windows-1252 (which covers Latin-1) when it isn't valid
+/// UTF-8, as many older ClrMamePro DATs aren't
+fn decode_dat_text(bytes: Vec<u8>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => e.into_bytes().into_iter()
+        .map(|b| match b {
+            0x80..=0x9F => CP1252_HIGH[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect(),
+    }
+}
+
+/// Load the machines described by a Logiqx XML or ClrMamePro DAT
+pub fn load_dat(path: &Path) -> Result<HashMap<String, GameMetadata>, Box<dyn std::error::Error>> {
+    let text = decode_dat_text(fs::read(path)?);
+
+    let machines = if text.trim_start().starts_with('<') {
+        parse_listxml(text.as_bytes(), |_| true)?
+    } else {
+        parse_clrmamepro(&text)?
+    };
+
+    if machines.is_empty() {
+        return Err("The DAT does not describe any sets".into());
+    }
+    Ok(machines)
+}
+
+/// Split ClrMamePro text into words, quoted strings and parentheses
+fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            tokens.push(c.to_string());
+            chars.next();
+        } else if c == '"' {
+            chars.next();
+            let mut token = String::new();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                token.push(c);
+            }
+            tokens.push(token);
+        } else {
+            let mut token = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' {
+                    break;
+                }
+                token.push(c);
+                chars.next();
+            }
+            tokens.push(token);
+        }
+    }
+
+    tokens
+}
+
+/// A `key value` list inside parentheses, with nested blocks kept in order
+enum Value {
+    Text(String),
+    Block(Vec<(String, Value)>),
+}
+
+fn parse_block(tokens: &[String], pos: &mut usize) -> Result<Vec<(String, Value)>, Box<dyn std::error::Error>> {
+    let mut entries = Vec::new();
+
+    while let Some(key) = tokens.get(*pos) {
+        *pos += 1;
+        if key == ")" {
+            return Ok(entries);
+        }
+
+        let value = tokens.get(*pos).ok_or("unexpected end of DAT")?;
+        *pos += 1;
+        if value == "(" {
+            entries.push((key.to_lowercase(), Value::Block(parse_block(tokens, pos)?)));
+        } else {
+            entries.push((key.to_lowercase(), Value::Text(value.clone())));
+        }
+    }
+
+    Err("unterminated block in DAT".into())
+}
+
+fn text<'a>(entries: &'a [(String, Value)], key: &str) -> Option<&'a str> {
+    entries.iter().find_map(|(k, v)| match v {
+        Value::Text(t) if k == key => Some(t.as_str()),
+        _ => None,
+    })
+}
+
+fn dump_status(entries: &[(String, Value)]) -> DumpStatus {
+    text(entries, "status").or_else(|| text(entries, "flags"))
+    .map(DumpStatus::from_attr)
+    .unwrap_or_default()
+}
+
+fn parse_clrmamepro(text_data: &str) -> Result<HashMap<String, GameMetadata>, Box<dyn std::error::Error>> {
+    let tokens = tokenize(text_data);
+    let mut pos = 0;
+    let mut machines = HashMap::new();
+
+    while let Some(keyword) = tokens.get(pos) {
+        let keyword = keyword.to_lowercase();
+        if tokens.get(pos + 1).map(String::as_str) != Some("(") {
+            return Err(format!("expected '(' after '{}' in DAT", keyword).into());
+        }
+        pos += 2;
+        let entries = parse_block(&tokens, &mut pos)?;
+
+        // The `clrmamepro` header and anything else that isn't a set is skipped
+        if !matches!(keyword.as_str(), "game" | "machine" | "resource") {
+            continue;
+        }
+        let Some(name) = text(&entries, "name") else {
+            continue;
+        };
+
+        let parent = text(&entries, "cloneof").map(String::from);
+        let mut machine = GameMetadata {
+            name: name.to_string(),
+            description: text(&entries, "description").unwrap_or(name).to_string(),
+            year: text(&entries, "year").unwrap_or_default().to_string(),
+            manufacturer: text(&entries, "manufacturer").unwrap_or_default().to_string(),
+            is_bios: keyword == "resource",
+            runnable: keyword != "resource",
+            is_clone: parent.is_some(),
+            parent,
+            romof: text(&entries, "romof").map(String::from),
+            sampleof: text(&entries, "sampleof").map(String::from),
+            ..Default::default()
+        };
+
+        for (key, value) in &entries {
+            match (key.as_str(), value) {
+                ("rom", Value::Block(rom)) => machine.roms.push(RomEntry {
+                    name: text(rom, "name").unwrap_or_default().to_string(),
+                    size: text(rom, "size").and_then(|s| s.parse().ok()).unwrap_or(0),
+                    crc: text(rom, "crc").map(|v| v.to_lowercase()),
+                    sha1: text(rom, "sha1").map(|v| v.to_lowercase()),
+                    merge: text(rom, "merge").map(String::from),
+                    status: dump_status(rom),
+                    ..Default::default()
+                }),
+                ("disk", Value::Block(disk)) => machine.disks.push(DiskEntry {
+                    name: text(disk, "name").unwrap_or_default().to_string(),
+                    sha1: text(disk, "sha1").map(|v| v.to_lowercase()),
+                    merge: text(disk, "merge").map(String::from),
+                    status: dump_status(disk),
+                    ..Default::default()
+                }),
+                ("sample", Value::Text(sample)) => machine.samples.push(sample.clone()),
+                _ => {}
+            }
+        }
+
+        machines.insert(machine.name.clone(), machine);
+    }
+
+    Ok(machines)
+}
+
+// ============= END DAT IMPORT =============
+
+/// A custom collection's sets and its last verification, ready to browse in the ROM list
+pub struct LoadedCollection {
+    pub index: usize,
+    pub metadata: HashMap<String, GameMetadata>,
+    pub roms: Vec<(String, String)>,
+    pub results: HashMap<String, SetAuditResult>,
+    /// Why the archive cache couldn't be saved, so the next verification rereads everything
+    pub cache_error: Option<String>,
+}
+
+pub enum CollectionProgress {
+    Message(String),
+    Loaded(LoadedCollection),
+    Failed(String),
+}
+
+/// Load a collection's DAT and either its saved verification or, with `verify`, a fresh one.
+/// Collections keep their own archive cache so they don't evict the main rompath's entries.
+pub fn load_collection(
+    collection: &CustomCollection,
+    index: usize,
+    report_path: &Path,
+    cache_path: &Path,
+    verify: bool,
+    progress: &(dyn Fn(String) + Sync),
+) -> Result<LoadedCollection, Box<dyn std::error::Error>> {
+    progress(format!("Reading {}...", collection.dat_path.display()));
+    let metadata = load_dat(&collection.dat_path)?;
+
+    let mut cache_error = None;
+    let results = if verify {
+        let mut cache = load_archive_cache(cache_path);
+        let results = audit_rom_sets(std::slice::from_ref(&collection.rom_dir), &metadata, &mut cache, progress);
+        if let Err(e) = save_archive_cache(&cache, cache_path) {
+            eprintln!("Failed to save archive cache {:?}: {}", cache_path, e);
+            cache_error = Some(format!("Failed to save archive cache {}: {}", cache_path.display(), e));
+        }
+
+        let report = AuditReport {
+            mame_version: collection.name.clone(),
+            audited_at: chrono::Local::now().to_rfc3339(),
+            results,
+        };
+        save_audit_report(&report, report_path)?;
+        report.results
+    } else {
+        load_audit_report(report_path).map(|report| report.results).unwrap_or_default()
+    };
+
+    let mut roms: Vec<(String, String)> = metadata.values()
+    .filter(|m| !m.is_bios && !m.is_device)
+    .map(|m| (m.description.clone(), m.name.clone()))
+    .collect();
+    roms.sort_by_key(|(display, _)| display.to_lowercase());
+
+    Ok(LoadedCollection { index, metadata, roms, results, cache_error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAT: &str = r#"clrmamepro (
+	name "Test DAT"
+	version 20240101
+)
+
+resource (
+	name neogeo
+	description "Neo-Geo BIOS"
+	rom ( name sp-s2.sp1 size 131072 crc 9036D879 )
+)
+
+game (
+	name mslug
+	description "Metal Slug (Super Vehicle-001)"
+	year 1996
+	manufacturer Nazca
+	romof neogeo
+	rom ( name sp-s2.sp1 merge sp-s2.sp1 size 131072 crc 9036d879 )
+	rom ( name 201-p1.p1 size 2097152 crc 08d8daa5 sha1 2B1F2B2F8A83D1C1A4CE8D2A3C5FD8B41C60B2AB )
+	rom ( name pal.bin size 260 flags nodump )
+)
+
+game (
+	name mslugb
+	description "Metal Slug (bootleg)"
+	cloneof mslug
+	romof mslug
+	rom ( name 201-p1.p1 merge 201-p1.p1 size 2097152 crc 08d8daa5 )
+	rom ( name bad.bin size 16 crc 12345678 status baddump )
+	disk ( name mslugb sha1 0123456789ABCDEF0123456789ABCDEF01234567 )
+	sample grunt
+)
+"#;
+
+    #[test]
+    fn clrmamepro_sets_and_files() {
+        let machines = parse_clrmamepro(DAT).unwrap();
+        assert_eq!(machines.len(), 3);
+
+        let bios = &machines["neogeo"];
+        assert!(bios.is_bios && !bios.runnable);
+        assert_eq!(bios.roms[0].crc.as_deref(), Some("9036d879"));
+
+        let parent = &machines["mslug"];
+        assert_eq!(parent.description, "Metal Slug (Super Vehicle-001)");
+        assert_eq!(parent.year, "1996");
+        assert_eq!(parent.romof.as_deref(), Some("neogeo"));
+        assert!(!parent.is_clone && parent.runnable);
+        assert_eq!(parent.roms.len(), 3);
+        assert_eq!(parent.roms[0].merge.as_deref(), Some("sp-s2.sp1"));
+        assert_eq!(parent.roms[1].size, 2097152);
+        assert_eq!(parent.roms[1].sha1.as_deref(), Some("2b1f2b2f8a83d1c1a4ce8d2a3c5fd8b41c60b2ab"));
+        assert_eq!(parent.roms[2].status, DumpStatus::NoDump);
+        assert_eq!(parent.roms[2].crc, None);
+
+        let clone = &machines["mslugb"];
+        assert!(clone.is_clone);
+        assert_eq!(clone.parent.as_deref(), Some("mslug"));
+        assert_eq!(clone.roms[1].status, DumpStatus::BadDump);
+        assert_eq!(clone.disks[0].sha1.as_deref(), Some("0123456789abcdef0123456789abcdef01234567"));
+        assert_eq!(clone.samples, ["grunt"]);
+    }
+
+    #[test]
+    fn clrmamepro_errors() {
+        assert!(parse_clrmamepro("game ( name pacman rom ( name a size 1 )").is_err());
+        assert!(parse_clrmamepro("game name pacman").is_err());
+        assert!(parse_clrmamepro("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn dat_text_falls_back_to_windows_1252() {
+        assert_eq!(decode_dat_text("Café".as_bytes().to_vec()), "Café");
+        assert_eq!(decode_dat_text(vec![b'C', b'a', b'f', 0xE9, b' ', 0x93, b'x', 0x94]), "Café “x”");
+    }
+}
@@ -0,0 +1,230 @@
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use zip::{ZipArchive, ZipWriter};
+use crate::models::GameMetadata;
+use super::archive::{archive_set_name, directory_rom_files, is_directory_set};
+use super::audit::{index_rom_dirs, clone_map, unneeded_members, ArchiveCache};
+
+// ============= COLLECTION HYGIENE =============
+//
+// Everything in the ROM directories that the scanners skip over: archives and folders
+// named after no machine, loose files, sets present in several directories, and files
+// inside a set that nothing needs. Each can be moved to a quarantine folder rather than
+// deleted, so a mistake is easy to undo.
+
+/// A set found in more than one place. `copies` are in the order MAME searches them,
+/// so the first is the one it uses.
+#[derive(Clone, Debug)]
+pub struct DuplicateSet {
+    pub name: String,
+    pub copies: Vec<PathBuf>,
+}
+
+/// Files inside one set archive or folder that no set needs
+#[derive(Clone, Debug)]
+pub struct UnneededFiles {
+    pub set: String,
+    pub container: PathBuf,
+    pub files: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct HygieneReport {
+    pub unknown: Vec<PathBuf>,
+    pub stray: Vec<PathBuf>,
+    pub duplicates: Vec<DuplicateSet>,
+    pub unneeded: Vec<UnneededFiles>,
+}
+
+impl HygieneReport {
+    pub fn is_clean(&self) -> bool {
+        self.unknown.is_empty() && self.stray.is_empty() && self.duplicates.is_empty() && self.unneeded.is_empty()
+    }
+}
+
+pub enum HygieneProgress {
+    Message(String),
+    Done(HygieneReport),
+}
+
+/// Within one directory MAME looks in a set's folder, then its zip, then its 7z
+fn search_rank(path: &Path) -> u8 {
+    match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()) {
+        None => 0,
+        Some(ext) if ext == "zip" => 1,
+        _ => 2,
+    }
+}
+
+/// Check the ROM directories, in rompath order, for anything the scanners would ignore
+pub fn scan_rom_hygiene(
+    rom_dirs: &[PathBuf],
+    metadata: &HashMap<String, GameMetadata>,
+    cache: &mut ArchiveCache,
+    progress: &(dyn Fn(String) + Sync),
+) -> HygieneReport {
+    let mut report = HygieneReport::default();
+    let mut copies: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+
+    for dir in rom_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        let mut dir_copies: Vec<(String, PathBuf)> = Vec::new();
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if let Some(set) = archive_set_name(&path) {
+                if metadata.contains_key(set) {
+                    dir_copies.push((set.to_string(), path.clone()));
+                } else {
+                    report.unknown.push(path.clone());
+                }
+            } else if path.is_dir() {
+                // Subfolders scanned as ROM directories in their own right are checked in their turn
+                if rom_dirs.contains(&path) {
+                    continue;
+                }
+                let name = path.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+                if !metadata.contains_key(name) {
+                    report.unknown.push(path.clone());
+                } else if is_directory_set(&path) {
+                    // A folder holding only CHDs complements the set's archive rather than duplicating it
+                    dir_copies.push((name.to_string(), path.clone()));
+                }
+            } else {
+                report.stray.push(path.clone());
+            }
+        }
+
+        dir_copies.sort_by_key(|(_, path)| search_rank(path));
+        for (set, path) in dir_copies {
+            copies.entry(set).or_default().push(path);
+        }
+    }
+
+    report.duplicates = copies.into_iter()
+    .filter(|(_, copies)| copies.len() > 1)
+    .map(|(name, copies)| DuplicateSet { name, copies })
+    .collect();
+
+    // The rompath index refreshes the member listings in the cache for every set in use
+    progress("Checking set contents...".to_string());
+    let index = index_rom_dirs(rom_dirs, metadata, cache, progress);
+    let clones = clone_map(metadata);
+
+    for (name, location) in &index {
+        let folder = location.directory.clone().filter(|_| location.directory_is_set);
+        for container in location.archive.iter().chain(folder.iter()) {
+            let Some(listing) = cache.archives.get(container) else {
+                continue;
+            };
+            let files: Vec<String> = unneeded_members(name, metadata, &listing.members, &clones)
+            .into_iter()
+            .map(|m| m.name.clone())
+            .collect();
+            if !files.is_empty() {
+                report.unneeded.push(UnneededFiles { set: name.clone(), container: container.clone(), files });
+            }
+        }
+    }
+    report.unneeded.sort_by(|a, b| a.set.cmp(&b.set));
+    report.unknown.sort();
+    report.stray.sort();
+
+    progress(format!(
+        "{} unknown, {} stray, {} duplicated sets, {} sets with unneeded files",
+        report.unknown.len(),
+        report.stray.len(),
+        report.duplicates.len(),
+        report.unneeded.len()
+    ));
+    report
+}
+
+/// A free path under the quarantine folder for `path`, grouped by the ROM directory it came from
+fn quarantine_destination(path: &Path, quarantine_dir: &Path) -> std::io::Result<PathBuf> {
+    let source_dir = path.parent().and_then(|p| p.file_name()).unwrap_or_default();
+    let dest_dir = quarantine_dir.join(source_dir);
+    fs::create_dir_all(&dest_dir)?;
+
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let mut dest = dest_dir.join(&file_name);
+    let mut n = 1;
+    while dest.exists() {
+        dest = dest_dir.join(format!("{}.{}", file_name, n));
+        n += 1;
+    }
+    Ok(dest)
+}
+
+/// Move a file or folder into the quarantine folder, returning where it went
+pub fn quarantine_path(path: &Path, quarantine_dir: &Path) -> std::io::Result<PathBuf> {
+    let dest = quarantine_destination(path, quarantine_dir)?;
+
+    if fs::rename(path, &dest).is_err() {
+        // Different drive: copy then remove. Folders are only ever renamed.
+        if path.is_dir() {
+            return Err(std::io::Error::other("folders can only be quarantined on the same drive"));
+        }
+        fs::copy(path, &dest)?;
+        fs::remove_file(path)?;
+    }
+    Ok(dest)
+}
+
+/// Move the named files out of a set zip or folder into a same-named zip or folder in quarantine
+pub fn quarantine_members(container: &Path, files: &[String], quarantine_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let wanted: HashSet<String> = files.iter().map(|f| f.to_lowercase()).collect();
+
+    if container.is_dir() {
+        let dest = quarantine_destination(container, quarantine_dir)?;
+        fs::create_dir_all(&dest)?;
+        for file in directory_rom_files(container) {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            if wanted.contains(&name.to_lowercase()) && fs::rename(&file, dest.join(&name)).is_err() {
+                fs::copy(&file, dest.join(&name))?;
+                fs::remove_file(&file)?;
+            }
+        }
+        return Ok(dest);
+    }
+
+    if !container.extension().is_some_and(|e| e.eq_ignore_ascii_case("zip")) {
+        return Err("Only zip archives and folders can have files moved out of them".into());
+    }
+
+    // Both archives are built from the original's compressed data, then swapped in
+    let mut source = ZipArchive::new(File::open(container)?)?;
+    let dest = quarantine_destination(container, quarantine_dir)?;
+    let tmp_path = container.with_extension("zip.tmp");
+    let mut kept = ZipWriter::new(BufWriter::new(File::create(&tmp_path)?));
+    let mut removed = ZipWriter::new(BufWriter::new(File::create(&dest)?));
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for i in 0..source.len() {
+            let file = source.by_index_raw(i)?;
+            let base = file.name().rsplit(['/', '\\']).next().unwrap_or_default().to_lowercase();
+            if !file.is_dir() && wanted.contains(&base) {
+                removed.raw_copy_file(file)?;
+            } else {
+                kept.raw_copy_file(file)?;
+            }
+        }
+        kept.finish()?.flush()?;
+        removed.finish()?.flush()?;
+        Ok(())
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&tmp_path);
+        let _ = fs::remove_file(&dest);
+        return Err(e);
+    }
+    fs::rename(&tmp_path, container)?;
+    Ok(dest)
+}
+
+// ============= END COLLECTION HYGIENE =============
@@ -1,14 +1,22 @@
 use std::path::{Path, PathBuf};
 use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::fs::File;
 use image;
 //use image::io::Reader as ImageReader; depracated
 use image::ImageReader;
 use image::DynamicImage;
 use eframe::egui;
-use zip::ZipArchive;
-use crate::models::{GameMetadata, FilterSettings, RomSetType, StatusFilter, RomStatus};
+use crate::models::{GameMetadata, FilterSettings, RomSetType, StatusFilter, RomStatus, SetAuditResult};
+
+pub mod archive;
+pub mod audit;
+pub mod dat;
+pub mod hygiene;
+pub mod rebuild;
+pub mod scan;
+pub mod watcher;
+
+use archive::{archive_set_name, list_set_file_names, rom_set_name};
 
 /// Apply filters to ROMs based on filter settings
 pub fn apply_rom_filters(
@@ -17,12 +25,18 @@ pub fn apply_rom_filters(
     display_name: &str,
     rom_name: &str,
     favorites: &HashSet<String>,
+    audit_results: &HashMap<String, SetAuditResult>,
 ) -> bool {
     // Favorites filter
     if filters.show_favorites_only && !favorites.contains(rom_name) {
         return false;
     }
 
+    // Audit status filter
+    if !filters.audit_filter.matches(audit_results.get(rom_name)) {
+        return false;
+    }
+
     // Search text filter
     if !filters.search_text.is_empty() {
         let search_lower = filters.search_text.to_lowercase();
@@ -106,7 +120,7 @@ pub fn apply_rom_filters(
     true
 }
 
-/// Collect ROMs from directories by scanning for ZIP files
+/// Collect ROMs from directories by scanning for ZIP and 7z files
 pub fn collect_roms_from_dirs(
     rom_dirs: &[PathBuf],
     _mame_executable: &str,
@@ -118,13 +132,11 @@ pub fn collect_roms_from_dirs(
         if let Ok(entries) = fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("zip") {
-                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        let display_name = mame_titles.get(stem)
-                        .cloned()
-                        .unwrap_or_else(|| stem.to_string());
-                        roms.push((display_name, stem.to_string()));
-                    }
+                if let Some(stem) = archive_set_name(&path) {
+                    let display_name = mame_titles.get(stem)
+                    .cloned()
+                    .unwrap_or_else(|| stem.to_string());
+                    roms.push((display_name, stem.to_string()));
                 }
             }
         }
@@ -135,7 +147,7 @@ pub fn collect_roms_from_dirs(
     roms
 }
 
-/// Enhanced ROM collection that scans inside ZIPs for merged sets
+/// Enhanced ROM collection that scans inside ZIP and 7z archives and set folders for merged sets
 pub fn collect_roms_with_zip_scan(
     rom_dirs: &[PathBuf],
     mame_titles: &HashMap<String, String>,
@@ -158,7 +170,7 @@ pub fn collect_roms_with_zip_scan(
         }
     }
 
-    println!("Scanning ROM directories with ZIP inspection...");
+    println!("Scanning ROM directories with archive inspection...");
     if assume_merged {
         println!("  Assuming merged ROM sets (all clones in parent ZIPs)");
     }
@@ -167,62 +179,10 @@ pub fn collect_roms_with_zip_scan(
         if let Ok(entries) = fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("zip") {
-                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        // Add the ZIP file itself (parent ROM)
-                        if !found_roms.contains(stem) {
-                            let display_name = mame_titles.get(stem)
-                            .cloned()
-                            .unwrap_or_else(|| stem.to_string());
-                            roms.push((display_name, stem.to_string()));
-                            found_roms.insert(stem.to_string());
-                        }
-
-                        // For merged sets: check if this parent ZIP contains clones
-                        if let Some(clones) = parent_to_clones.get(stem) {
-                            if assume_merged {
-                                // If assume_merged is true, add all clones without checking ZIP contents
-                                for clone in clones {
-                                    if !found_roms.contains(clone) {
-                                        let display_name = mame_titles.get(clone)
-                                        .cloned()
-                                        .unwrap_or_else(|| clone.to_string());
-                                        roms.push((display_name, clone.to_string()));
-                                        found_roms.insert(clone.to_string());
-                                        println!("  Assumed clone {} in {}.zip (merged set mode)", clone, stem);
-                                    }
-                                }
-                            } else {
-                                // Otherwise, check ZIP contents
-                                if let Ok(file) = File::open(&path) {
-                                    if let Ok(mut archive) = ZipArchive::new(file) {
-                                        // Get list of files in the ZIP
-                                        let mut files_in_zip = Vec::new();
-                                        for i in 0..archive.len() {
-                                            if let Ok(file) = archive.by_index(i) {
-                                                let full_name = file.name().to_string();
-                                                if let Some(filename) = full_name.split('/').last() {
-                                                    files_in_zip.push(filename.to_string());
-                                                }
-                                            }
-                                        }
-
-                                        // Check each clone
-                                        for clone in clones {
-                                            if should_show_clone(clone, &files_in_zip, metadata) {
-                                                if !found_roms.contains(clone) {
-                                                    let display_name = mame_titles.get(clone)
-                                                    .cloned()
-                                                    .unwrap_or_else(|| clone.to_string());
-                                                    roms.push((display_name, clone.to_string()));
-                                                    found_roms.insert(clone.to_string());
-                                                    println!("  Found clone {} inside {}.zip", clone, stem);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                if let Some(stem) = rom_set_name(&path, metadata) {
+                    for (display_name, name) in roms_in_set_path(&path, stem, mame_titles, metadata, &parent_to_clones, assume_merged) {
+                        if found_roms.insert(name.clone()) {
+                            roms.push((display_name, name));
                         }
                     }
                 }
@@ -237,7 +197,43 @@ pub fn collect_roms_with_zip_scan(
     roms
 }
 
-/// Check if a clone's ROM files exist in the ZIP
+/// The set stored at `path` plus, for a parent, the clones merged into it
+pub fn roms_in_set_path(
+    path: &Path,
+    stem: &str,
+    mame_titles: &HashMap<String, String>,
+    metadata: &HashMap<String, GameMetadata>,
+    parent_to_clones: &HashMap<String, Vec<String>>,
+    assume_merged: bool,
+) -> Vec<(String, String)> {
+    let display = |name: &str| mame_titles.get(name).cloned().unwrap_or_else(|| name.to_string());
+
+    // Add the archive itself (parent ROM)
+    let mut roms = vec![(display(stem), stem.to_string())];
+
+    // For merged sets: check if this parent archive contains clones
+    if let Some(clones) = parent_to_clones.get(stem) {
+        if assume_merged {
+            // If assume_merged is true, add all clones without checking archive contents
+            for clone in clones {
+                roms.push((display(clone), clone.to_string()));
+                println!("  Assumed clone {} in {:?} (merged set mode)", clone, path.file_name().unwrap_or_default());
+            }
+        } else if let Some(files_in_archive) = list_set_file_names(path) {
+            // Otherwise, check archive or folder contents
+            for clone in clones {
+                if should_show_clone(clone, &files_in_archive, metadata) {
+                    roms.push((display(clone), clone.to_string()));
+                    println!("  Found clone {} inside {:?}", clone, path.file_name().unwrap_or_default());
+                }
+            }
+        }
+    }
+
+    roms
+}
+
+/// Check if a clone's ROM files exist in the parent archive
 fn should_show_clone(clone_name: &str, files_in_zip: &[String], metadata: &HashMap<String, GameMetadata>) -> bool {
     // For merged ROM sets, clone ROMs can be in the parent ZIP with various naming patterns:
     // 1. Named after the clone (e.g., "1944j.ic42" for clone 1944j)
@@ -518,15 +514,13 @@ pub fn detect_rom_set_type(
         if let Ok(entries) = fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("zip") {
+                if let Some(stem) = rom_set_name(&path, metadata) {
                     total_files += 1;
 
-                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        if let Some(meta) = metadata.get(stem) {
-                            if meta.is_clone {
-                                clone_files += 1;
-                                has_separate_clones = true;
-                            }
+                    if let Some(meta) = metadata.get(stem) {
+                        if meta.is_clone {
+                            clone_files += 1;
+                            has_separate_clones = true;
                         }
                     }
 
@@ -554,7 +548,7 @@ pub fn detect_rom_set_type(
     }
 }
 
-/// Alternative: Detect ROM set type by looking inside ZIPs
+/// Alternative: Detect ROM set type by looking inside ZIP and 7z archives and set folders
 pub fn detect_rom_set_type_enhanced(rom_dirs: &[PathBuf], metadata: &HashMap<String, GameMetadata>) -> RomSetType {
     let mut has_separate_clone_zips = false;
     let mut has_clones_in_parent_zips = false;
@@ -574,40 +568,25 @@ pub fn detect_rom_set_type_enhanced(rom_dirs: &[PathBuf], metadata: &HashMap<Str
         if let Ok(entries) = fs::read_dir(dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
-                if path.extension().and_then(|s| s.to_str()) == Some("zip") {
-                    if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                        sample_count += 1;
+                if let Some(stem) = rom_set_name(&path, metadata) {
+                    sample_count += 1;
 
-                        // Check if this is a clone ZIP
-                        if metadata.get(stem).map(|m| m.is_clone).unwrap_or(false) {
-                            has_separate_clone_zips = true;
-                        }
+                    // Check if this is a clone archive
+                    if metadata.get(stem).map(|m| m.is_clone).unwrap_or(false) {
+                        has_separate_clone_zips = true;
+                    }
 
-                        // Check if this parent ZIP contains clones
-                        if parent_to_clones.contains_key(stem) {
-                            if let Ok(file) = File::open(&path) {
-                                if let Ok(mut archive) = ZipArchive::new(file) {
-                                    // Look for clone files inside
-                                    for i in 0..archive.len() {
-                                        if let Ok(file) = archive.by_index(i) {
-                                            let file_name = file.name().to_string();
-                                            // Check if this file belongs to a clone
-                                            for clone in parent_to_clones[stem].iter() {
-                                                if file_name.contains(clone) {
-                                                    has_clones_in_parent_zips = true;
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+                    // Check if this parent archive contains clone files
+                    if let Some(clones) = parent_to_clones.get(stem) {
+                        let files = list_set_file_names(&path).unwrap_or_default();
+                        if files.iter().any(|f| clones.iter().any(|clone| f.contains(clone.as_str()))) {
+                            has_clones_in_parent_zips = true;
                         }
+                    }
 
-                        // Sample enough files
-                        if sample_count > 20 {
-                            break 'outer;
-                        }
+                    // Sample enough files
+                    if sample_count > 20 {
+                        break 'outer;
                     }
                 }
             }
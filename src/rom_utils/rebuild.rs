@@ -0,0 +1,520 @@
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Read, Write};
+use rayon::prelude::*;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+use crate::models::{GameMetadata, DumpStatus, RomSetType};
+use super::archive::{directory_set_name, is_directory_set, is_rom_archive, list_7z_members, list_directory_members, read_7z_files, UnpackedMembers};
+use super::audit::clone_map;
+
+// ============= ROM SET REBUILDER =============
+//
+// Files are matched by CRC and size, so a source set can be in any layout (or none).
+// Zip members are copied without recompressing; files from 7z archives and set folders
+// are deflated.
+
+/// What to rebuild and where. Source directories are read, never written to, unless
+/// `keep_source` is off. They are only scanned flat, like rompath entries.
+#[derive(Clone, Debug)]
+pub struct RebuildOptions {
+    pub source_dirs: Vec<PathBuf>,
+    pub target_dir: Option<PathBuf>,
+    pub layout: RomSetType,
+    pub dry_run: bool,
+    pub keep_source: bool,
+}
+
+impl Default for RebuildOptions {
+    fn default() -> Self {
+        Self {
+            source_dirs: Vec::new(),
+            target_dir: None,
+            layout: RomSetType::NonMerged,
+            dry_run: true,
+            keep_source: true,
+        }
+    }
+}
+
+/// Summary of a rebuild, or of what a dry run would have done
+#[derive(Clone, Debug, Default)]
+pub struct RebuildReport {
+    pub dry_run: bool,
+    pub sets_written: usize,
+    pub files_written: usize,
+    /// Sets that could only be written in part, with the files no source had
+    pub incomplete: BTreeMap<String, Vec<String>>,
+    pub sources_removed: usize,
+    pub errors: Vec<String>,
+}
+
+pub enum RebuildProgress {
+    Message(String),
+    Done(RebuildReport),
+    Failed(String),
+}
+
+/// One file a target set should contain
+struct TargetFile {
+    name: String,
+    crc: u32,
+    size: u64,
+}
+
+/// Where a file with a given CRC and size can be read from
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum SourceFile {
+    Zip { archive: PathBuf, index: usize },
+    SevenZip { archive: PathBuf, index: usize },
+    Folder { path: PathBuf },
+}
+
+impl SourceFile {
+    /// The archive or set folder the file lives in
+    fn container(&self) -> PathBuf {
+        match self {
+            SourceFile::Zip { archive, .. } | SourceFile::SevenZip { archive, .. } => archive.clone(),
+            SourceFile::Folder { path } => path.parent().map(Path::to_path_buf).unwrap_or_default(),
+        }
+    }
+}
+
+/// A source file keyed by CRC and size
+type ListedFile = ((u32, u64), SourceFile);
+
+/// Every file the rebuild can draw from
+struct SourceIndex {
+    pool: HashMap<(u32, u64), SourceFile>,
+    /// Files in each archive or folder, to tell when all of them were rebuilt
+    member_counts: HashMap<PathBuf, usize>,
+}
+
+fn parse_crc(crc: &Option<String>) -> Option<u32> {
+    crc.as_deref().and_then(|c| u32::from_str_radix(c, 16).ok())
+}
+
+/// Whether a merged ROM comes from a BIOS set in the machine's romof chain
+fn provided_by_bios(machine: &GameMetadata, crc: u32, metadata: &HashMap<String, GameMetadata>) -> bool {
+    let mut next = machine.romof.as_deref();
+    let mut seen = HashSet::new();
+
+    while let Some(name) = next {
+        if !seen.insert(name) {
+            break;
+        }
+        let Some(ancestor) = metadata.get(name) else {
+            break;
+        };
+        if ancestor.is_bios && ancestor.roms.iter().any(|r| parse_crc(&r.crc) == Some(crc)) {
+            return true;
+        }
+        next = ancestor.romof.as_deref();
+    }
+
+    false
+}
+
+/// The ROMs a machine needs, optionally limited to those not merged from a parent or BIOS
+fn machine_files(machine: &GameMetadata, own_only: bool, metadata: &HashMap<String, GameMetadata>) -> Vec<TargetFile> {
+    machine.roms.iter()
+    .filter(|rom| rom.status != DumpStatus::NoDump)
+    .filter_map(|rom| {
+        let crc = parse_crc(&rom.crc)?;
+        if rom.merge.is_some() && (own_only || provided_by_bios(machine, crc, metadata)) {
+            return None;
+        }
+        Some(TargetFile { name: rom.name.clone(), crc, size: rom.size })
+    })
+    .collect()
+}
+
+/// Work out the contents of every target set for a layout
+fn plan_layout(layout: &RomSetType, metadata: &HashMap<String, GameMetadata>) -> BTreeMap<String, Vec<TargetFile>> {
+    let mut plan: BTreeMap<String, Vec<TargetFile>> = BTreeMap::new();
+    let clones = clone_map(metadata);
+
+    for (name, machine) in metadata {
+        match layout {
+            RomSetType::NonMerged => {
+                plan.insert(name.clone(), machine_files(machine, false, metadata));
+            }
+            RomSetType::Merged if machine.parent.is_some() => {}
+            RomSetType::Merged => {
+                let mut files = machine_files(machine, true, metadata);
+                let own_clones = clones.get(name).into_iter().flatten().filter_map(|c| metadata.get(c));
+
+                for clone in own_clones {
+                    for file in machine_files(clone, true, metadata) {
+                        match files.iter().find(|f| f.name.eq_ignore_ascii_case(&file.name)) {
+                            Some(existing) if existing.crc == file.crc => {}
+                            // Same name, different data: keep the clone's copy in its own folder
+                            Some(_) => files.push(TargetFile { name: format!("{}/{}", clone.name, file.name), ..file }),
+                            None => files.push(file),
+                        }
+                    }
+                }
+                plan.insert(name.clone(), files);
+            }
+            _ => {
+                plan.insert(name.clone(), machine_files(machine, true, metadata));
+            }
+        }
+    }
+
+    plan.retain(|_, files| !files.is_empty());
+    plan
+}
+
+/// Index every readable file in the source directories by CRC and size
+fn index_sources(
+    source_dirs: &[PathBuf],
+    metadata: &HashMap<String, GameMetadata>,
+    progress: &(dyn Fn(String) + Sync),
+) -> SourceIndex {
+    let mut containers = Vec::new();
+
+    for dir in source_dirs {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if is_rom_archive(&path) || (directory_set_name(&path, metadata).is_some() && is_directory_set(&path)) {
+                containers.push(path);
+            }
+        }
+    }
+
+    progress(format!("Reading {} source archives and folders...", containers.len()));
+    let listed: Vec<(PathBuf, Vec<ListedFile>)> = containers.par_iter()
+    .map(|path| {
+        let files = if path.is_dir() {
+            list_directory_members(path).map(|members| {
+                members.into_iter()
+                .map(|m| ((m.crc, m.size), SourceFile::Folder { path: path.join(&m.name) }))
+                .collect()
+            })
+        } else if is_7z(path) {
+            list_7z_members(path).map(|members| {
+                members.into_iter().enumerate()
+                .map(|(index, m)| ((m.crc, m.size), SourceFile::SevenZip { archive: path.clone(), index }))
+                .collect()
+            })
+        } else {
+            list_zip_files(path)
+        };
+        let files = files.unwrap_or_else(|e| {
+            eprintln!("Failed to read {:?}: {}", path, e);
+            Vec::new()
+        });
+        (path.clone(), files)
+    })
+    .collect();
+
+    // Earlier directories win, as on the rompath
+    let mut pool = HashMap::new();
+    let mut member_counts = HashMap::new();
+    for (container, files) in listed {
+        member_counts.insert(container, files.len());
+        for (key, source) in files {
+            pool.entry(key).or_insert(source);
+        }
+    }
+
+    SourceIndex { pool, member_counts }
+}
+
+fn is_7z(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e.eq_ignore_ascii_case("7z"))
+}
+
+fn list_zip_files(path: &Path) -> Result<Vec<ListedFile>, Box<dyn std::error::Error>> {
+    let mut archive = ZipArchive::new(File::open(path)?)?;
+    let mut files = Vec::new();
+
+    for index in 0..archive.len() {
+        let file = archive.by_index_raw(index)?;
+        if !file.is_dir() {
+            files.push(((file.crc32(), file.size()), SourceFile::Zip { archive: path.to_path_buf(), index }));
+        }
+    }
+
+    Ok(files)
+}
+
+/// Write one target zip. It is built under a temporary name so a failed write never
+/// leaves a truncated set behind.
+fn write_set(
+    target: &Path,
+    files: &[(&TargetFile, &SourceFile)],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let tmp_path = target.with_extension("zip.tmp");
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(&tmp_path)?));
+    let mut open_archives: HashMap<PathBuf, ZipArchive<File>> = HashMap::new();
+    let mut unpacked_7z: HashMap<PathBuf, UnpackedMembers> = HashMap::new();
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let result = (|| -> Result<(), Box<dyn std::error::Error>> {
+        for (file, source) in files {
+            match source {
+                SourceFile::Zip { archive, index } => {
+                    if !open_archives.contains_key(archive) {
+                        open_archives.insert(archive.clone(), ZipArchive::new(File::open(archive)?)?);
+                    }
+                    let zip = open_archives.get_mut(archive).ok_or("source archive not open")?;
+                    writer.raw_copy_file_rename(zip.by_index_raw(*index)?, &file.name)?;
+                }
+                SourceFile::SevenZip { archive, index } => {
+                    if !unpacked_7z.contains_key(archive) {
+                        unpacked_7z.insert(archive.clone(), read_7z_files(archive)?);
+                    }
+                    let (_, data) = unpacked_7z.get(archive).and_then(|files| files.get(*index)).ok_or("source 7z member not found")?;
+                    writer.start_file(&file.name, deflated)?;
+                    writer.write_all(data)?;
+                }
+                SourceFile::Folder { path } => {
+                    let mut data = Vec::with_capacity(file.size as usize);
+                    File::open(path)?.read_to_end(&mut data)?;
+                    writer.start_file(&file.name, deflated)?;
+                    writer.write_all(&data)?;
+                }
+            }
+        }
+        writer.finish()?.flush()?;
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => {
+            fs::rename(&tmp_path, target)?;
+            Ok(())
+        }
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            Err(e)
+        }
+    }
+}
+
+/// Remove source archives and folder files whose every file went into a written set
+fn remove_used_sources(
+    used: &HashMap<PathBuf, HashSet<SourceFile>>,
+    member_counts: &HashMap<PathBuf, usize>,
+    report: &mut RebuildReport,
+) {
+    for (container, files) in used {
+        if member_counts.get(container) != Some(&files.len()) {
+            continue;
+        }
+
+        let result = if container.is_dir() {
+            // Only the ROM files go; CHDs in the same folder are left alone
+            files.iter()
+            .filter_map(|file| match file {
+                SourceFile::Folder { path } => Some(path),
+                SourceFile::Zip { .. } | SourceFile::SevenZip { .. } => None,
+            })
+            .try_for_each(fs::remove_file)
+            .map(|_| {
+                let _ = fs::remove_dir(container);
+            })
+        } else {
+            fs::remove_file(container)
+        };
+
+        match result {
+            Ok(()) => report.sources_removed += 1,
+            Err(e) => report.errors.push(format!("Failed to remove {:?}: {}", container, e)),
+        }
+    }
+}
+
+/// `path` with links and `..` resolved, as far as it exists; the rest is appended as given
+fn resolved_path(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return rest.iter().rev().fold(resolved, |path, part| path.join(part));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Rebuild the sets found in `options.source_dirs` into `options.target_dir` in the chosen layout
+pub fn rebuild_rom_sets(
+    options: &RebuildOptions,
+    metadata: &HashMap<String, GameMetadata>,
+    progress: &(dyn Fn(String) + Sync),
+) -> Result<RebuildReport, Box<dyn std::error::Error>> {
+    let target_dir = options.target_dir.as_ref().ok_or("No target directory chosen")?;
+    if matches!(options.layout, RomSetType::Unknown) {
+        return Err("Choose merged, split or non-merged".into());
+    }
+    if options.source_dirs.is_empty() {
+        return Err("No source directories chosen".into());
+    }
+    let target = resolved_path(target_dir);
+    if options.source_dirs.iter().any(|dir| target.starts_with(resolved_path(dir))) {
+        return Err("The target directory must not be inside one of the source directories".into());
+    }
+    if metadata.is_empty() {
+        return Err("No MAME metadata loaded".into());
+    }
+
+    let sources = index_sources(&options.source_dirs, metadata, progress);
+    let plan = plan_layout(&options.layout, metadata);
+
+    let mut report = RebuildReport {
+        dry_run: options.dry_run,
+        ..Default::default()
+    };
+    if !options.dry_run {
+        fs::create_dir_all(target_dir)?;
+    }
+
+    // Containers a written set drew from, and which of their files it used
+    let mut used: HashMap<PathBuf, HashSet<SourceFile>> = HashMap::new();
+    let total = plan.len();
+
+    for (done, (name, files)) in plan.iter().enumerate() {
+        if done % 200 == 0 {
+            progress(format!("Rebuilding sets: {} / {}", done, total));
+        }
+
+        let mut found = Vec::new();
+        let mut missing = Vec::new();
+        for file in files {
+            match sources.pool.get(&(file.crc, file.size)) {
+                Some(source) => found.push((file, source)),
+                None => missing.push(file.name.clone()),
+            }
+        }
+
+        // Nothing to build from: the set simply isn't in the collection
+        if found.is_empty() {
+            continue;
+        }
+        if !missing.is_empty() {
+            report.incomplete.insert(name.clone(), missing);
+        }
+
+        if !options.dry_run {
+            if let Err(e) = write_set(&target_dir.join(format!("{}.zip", name)), &found) {
+                report.errors.push(format!("{}: {}", name, e));
+                continue;
+            }
+        }
+
+        report.sets_written += 1;
+        report.files_written += found.len();
+        for (_, source) in found {
+            used.entry(source.container()).or_default().insert(source.clone());
+        }
+    }
+
+    if !options.dry_run && !options.keep_source && report.errors.is_empty() {
+        progress("Removing rebuilt source files...".to_string());
+        remove_used_sources(&used, &sources.member_counts, &mut report);
+    }
+
+    progress(format!(
+        "{} {} sets ({} files), {} incomplete",
+        if options.dry_run { "Would write" } else { "Wrote" },
+        report.sets_written,
+        report.files_written,
+        report.incomplete.len()
+    ));
+
+    Ok(report)
+}
+
+// ============= END ROM SET REBUILDER =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RomEntry;
+
+    fn rom(name: &str, crc: &str, merge: Option<&str>) -> RomEntry {
+        RomEntry {
+            name: name.to_string(),
+            size: 16,
+            crc: Some(crc.to_string()),
+            merge: merge.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn machine(name: &str, cloneof: Option<&str>, romof: Option<&str>, roms: Vec<RomEntry>) -> (String, GameMetadata) {
+        (name.to_string(), GameMetadata {
+            name: name.to_string(),
+            parent: cloneof.map(str::to_string),
+            is_clone: cloneof.is_some(),
+            romof: romof.map(str::to_string),
+            roms,
+            ..Default::default()
+        })
+    }
+
+    /// A BIOS, a parent that uses it and a clone whose `prog.bin` differs from the parent's
+    fn metadata() -> HashMap<String, GameMetadata> {
+        let mut bios = machine("bios", None, None, vec![rom("bios.bin", "000000b1", None)]);
+        bios.1.is_bios = true;
+        [
+            bios,
+            machine("parent", None, Some("bios"), vec![
+                rom("bios.bin", "000000b1", Some("bios.bin")),
+                rom("prog.bin", "000000a1", None),
+                rom("gfx.bin", "000000a2", None),
+            ]),
+            machine("clone", Some("parent"), Some("parent"), vec![
+                rom("bios.bin", "000000b1", Some("bios.bin")),
+                rom("prog.bin", "000000c1", None),
+                rom("gfx.bin", "000000a2", Some("gfx.bin")),
+                rom("extra.bin", "000000c2", None),
+            ]),
+            machine("nodump", None, None, vec![RomEntry { status: DumpStatus::NoDump, ..rom("pal.bin", "0", None) }]),
+        ].into_iter().collect()
+    }
+
+    fn names(plan: &BTreeMap<String, Vec<TargetFile>>, set: &str) -> Vec<String> {
+        let mut names: Vec<String> = plan[set].iter().map(|f| f.name.clone()).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn split_sets_hold_only_their_own_files() {
+        let plan = plan_layout(&RomSetType::Split, &metadata());
+        assert_eq!(names(&plan, "bios"), ["bios.bin"]);
+        assert_eq!(names(&plan, "parent"), ["gfx.bin", "prog.bin"]);
+        assert_eq!(names(&plan, "clone"), ["extra.bin", "prog.bin"]);
+        assert!(!plan.contains_key("nodump"));
+    }
+
+    #[test]
+    fn merged_sets_fold_clones_into_the_parent() {
+        let plan = plan_layout(&RomSetType::Merged, &metadata());
+        assert!(!plan.contains_key("clone"));
+        assert_eq!(names(&plan, "parent"), ["clone/prog.bin", "extra.bin", "gfx.bin", "prog.bin"]);
+        assert_eq!(names(&plan, "bios"), ["bios.bin"]);
+    }
+
+    #[test]
+    fn non_merged_sets_include_parent_files_but_not_bios_files() {
+        let plan = plan_layout(&RomSetType::NonMerged, &metadata());
+        assert_eq!(names(&plan, "parent"), ["gfx.bin", "prog.bin"]);
+        assert_eq!(names(&plan, "clone"), ["extra.bin", "gfx.bin", "prog.bin"]);
+        let clone_prog = plan["clone"].iter().find(|f| f.name == "prog.bin").unwrap();
+        assert_eq!(clone_prog.crc, 0xc1);
+    }
+}
@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use std::fs;
+use crate::models::{DirScanSettings, GameMetadata};
+
+// ============= DIRECTORY SCAN SETTINGS =============
+//
+// MAME only looks for sets directly inside each -rompath entry, so a library sorted into
+// subfolders is scanned by adding those subfolders to the list of ROM directories. Every
+// scanner and the rompath passed at launch work from the same expanded list, which keeps
+// what the launcher shows in step with what MAME can find.
+
+/// Case-insensitive glob match where `*` matches any run of characters and `?` any one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            // Let the last `*` swallow one more character and try again
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn matches_any(patterns: &[String], relative: &str) -> bool {
+    patterns.iter()
+    .map(|p| p.trim())
+    .filter(|p| !p.is_empty())
+    .any(|p| glob_match(p, relative))
+}
+
+fn has_patterns(patterns: &[String]) -> bool {
+    patterns.iter().any(|p| !p.trim().is_empty())
+}
+
+/// Add the subfolders of `dir` that `settings` lets through, depth first in name order
+fn collect_subfolders(
+    root: &Path,
+    dir: &Path,
+    depth: usize,
+    settings: &DirScanSettings,
+    metadata: &HashMap<String, GameMetadata>,
+    out: &mut Vec<PathBuf>,
+) {
+    if depth >= settings.max_depth {
+        return;
+    }
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut subdirs: Vec<PathBuf> = entries.flatten()
+    .filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+    .map(|e| e.path())
+    .collect();
+    subdirs.sort();
+
+    for path in subdirs {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        // Folder sets and CHD folders are found by MAME through the directory holding them
+        if name.starts_with('.') || metadata.contains_key(name) {
+            continue;
+        }
+
+        let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+        if matches_any(&settings.exclude, &relative) {
+            continue;
+        }
+        if !has_patterns(&settings.include) || matches_any(&settings.include, &relative) {
+            out.push(path.clone());
+        }
+        // Folders that aren't included themselves can still lead to ones that are
+        collect_subfolders(root, &path, depth + 1, settings, metadata, out);
+    }
+}
+
+/// The directories to scan for `dirs`, in rompath order: each directory followed by the
+/// subfolders its settings include. Directories without settings are scanned flat.
+pub fn expand_rom_dirs(
+    dirs: &[PathBuf],
+    settings: &HashMap<PathBuf, DirScanSettings>,
+    metadata: &HashMap<String, GameMetadata>,
+) -> Vec<PathBuf> {
+    let mut expanded = Vec::new();
+
+    for dir in dirs {
+        let dir_settings = settings.get(dir).cloned().unwrap_or_default();
+
+        let mut found = vec![dir.clone()];
+        collect_subfolders(dir, dir, 0, &dir_settings, metadata, &mut found);
+        for path in found {
+            if !expanded.contains(&path) {
+                expanded.push(path);
+            }
+        }
+    }
+
+    expanded
+}
+
+// ============= END DIRECTORY SCAN SETTINGS =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns_match_whole_names_ignoring_case() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "anything"));
+        assert!(glob_match("Arcade", "arcade"));
+        assert!(glob_match("arc*", "Arcade/Neo-Geo"));
+        assert!(glob_match("*/neo*", "arcade/Neo-Geo"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("a*c", "abcbc"));
+        assert!(glob_match("disk?", "disk2"));
+        assert!(glob_match("**x", "x"));
+
+        assert!(!glob_match("arc", "arcade"));
+        assert!(!glob_match("arcade", "arc"));
+        assert!(!glob_match("disk?", "disk"));
+        assert!(!glob_match("disk?", "disk10"));
+        assert!(!glob_match("a*b*c", "aXbYd"));
+        assert!(!glob_match("", "x"));
+    }
+}
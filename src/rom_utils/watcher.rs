@@ -0,0 +1,360 @@
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+use crate::models::{DirScanSettings, GameMetadata};
+use super::archive::{is_directory_set, rom_set_name, ROM_ARCHIVE_EXTENSIONS};
+use super::audit::clone_map;
+use super::roms_in_set_path;
+use super::scan::expand_rom_dirs;
+
+// ============= DIRECTORY WATCHER =============
+//
+// A polling watcher: a directory's mtime moves whenever an entry is added, removed or
+// renamed, so only directories are checked on each pass and their entries are re-read
+// only when that happens. Files that appeared or changed are re-checked until their size
+// and mtime settle, which lets large copies finish before the list is updated.
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Sets whose files changed, with what the ROM list should now show for them
+pub struct RomsDelta {
+    pub affected: HashSet<String>,
+    pub roms: Vec<(String, String)>,
+    pub folder_sets: HashSet<String>,
+}
+
+pub enum WatchEvent {
+    Roms(RomsDelta),
+    AssetsChanged,
+}
+
+/// Everything the watcher depends on; a change means it has to be restarted.
+/// Sets are listed from `rom_dirs` as in a full scan. `extra_rom_dirs` (the CHD
+/// directories) are full rompath entries that MAME searches for any set file, but the
+/// ROM list isn't built from them, so changes there only re-check the sets they affect.
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchKey {
+    pub rom_dirs: Vec<PathBuf>,
+    pub extra_rom_dirs: Vec<PathBuf>,
+    pub asset_dirs: Vec<PathBuf>,
+    pub scan_settings: HashMap<PathBuf, DirScanSettings>,
+    pub mame_version: String,
+    pub metadata_len: usize,
+    pub assume_merged: bool,
+}
+
+/// A running watcher thread, stopped when dropped
+pub struct DirWatcher {
+    pub key: WatchKey,
+    pub rx: Receiver<WatchEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Drop for DirWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn stat(path: &Path) -> Option<(u64, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+    Some((meta.len(), mtime))
+}
+
+/// A directory's entries, and which of them are directories
+fn list_entries(dir: &Path, all_entries: bool) -> (HashMap<PathBuf, (u64, u64)>, HashSet<PathBuf>) {
+    let mut entries = HashMap::new();
+    let mut subdirs = HashSet::new();
+
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+        if !is_dir && !all_entries {
+            continue;
+        }
+        if let Some(state) = stat(&path) {
+            if is_dir {
+                subdirs.insert(path.clone());
+            }
+            entries.insert(path, state);
+        }
+    }
+
+    (entries, subdirs)
+}
+
+/// What the watcher last saw of one directory
+struct WatchedDir {
+    stat: Option<(u64, u64)>,
+    entries: HashMap<PathBuf, (u64, u64)>,
+    subdirs: HashSet<PathBuf>,
+}
+
+impl WatchedDir {
+    fn new(dir: &Path, all_entries: bool) -> Self {
+        let (entries, subdirs) = list_entries(dir, all_entries);
+        Self { stat: stat(dir), entries, subdirs }
+    }
+
+    /// Re-read the directory if it changed, returning the directory itself and entries that
+    /// were added, removed or changed. Subdirectories are always re-checked since files
+    /// inside them don't touch this directory's mtime. Asset directories only track
+    /// their subdirectories (`all_entries` off).
+    fn poll(&mut self, dir: &Path, all_entries: bool) -> Vec<PathBuf> {
+        let mut changed = Vec::new();
+        let current = stat(dir);
+
+        if current != self.stat {
+            self.stat = current;
+            changed.push(dir.to_path_buf());
+
+            let (entries, subdirs) = list_entries(dir, all_entries);
+            for (path, state) in &entries {
+                if self.entries.get(path) != Some(state) {
+                    changed.push(path.clone());
+                }
+            }
+            changed.extend(self.entries.keys().filter(|p| !entries.contains_key(*p)).cloned());
+            self.entries = entries;
+            self.subdirs = subdirs;
+        } else {
+            for path in &self.subdirs {
+                let now = stat(path);
+                if let (Some(now), Some(before)) = (now, self.entries.get_mut(path)) {
+                    if now != *before {
+                        *before = now;
+                        changed.push(path.clone());
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+}
+
+/// Work out what the ROM list should show for the sets behind `paths`
+fn rom_delta(
+    paths: &HashSet<PathBuf>,
+    rom_dirs: &[PathBuf],
+    mame_titles: &HashMap<String, String>,
+    metadata: &HashMap<String, GameMetadata>,
+    parent_to_clones: &HashMap<String, Vec<String>>,
+    assume_merged: bool,
+) -> RomsDelta {
+    let mut affected = HashSet::new();
+    for path in paths {
+        let name = if path.extension().is_some_and(|e| ROM_ARCHIVE_EXTENSIONS.iter().any(|x| e.eq_ignore_ascii_case(x))) {
+            path.file_stem()
+        } else {
+            path.file_name()
+        };
+        if let Some(name) = name.and_then(|n| n.to_str()).filter(|n| metadata.contains_key(*n)) {
+            affected.insert(name.to_string());
+            // Clones can be listed because of what's inside their parent's archive
+            affected.extend(parent_to_clones.get(name).into_iter().flatten().cloned());
+        }
+    }
+
+    // Each affected set can come from its own files or from its parent's
+    let mut sources: Vec<&str> = affected.iter().map(String::as_str).collect();
+    sources.extend(affected.iter().filter_map(|n| metadata.get(n).and_then(|m| m.parent.as_deref())));
+    sources.sort_unstable();
+    sources.dedup();
+
+    let mut roms = Vec::new();
+    let mut seen = HashSet::new();
+    let mut folder_sets = HashSet::new();
+    for dir in rom_dirs {
+        for source in &sources {
+            let candidates = ROM_ARCHIVE_EXTENSIONS.iter()
+            .map(|ext| dir.join(format!("{}.{}", source, ext)))
+            .chain(std::iter::once(dir.join(source)))
+            .filter(|path| path.exists());
+
+            for path in candidates {
+                let Some(stem) = rom_set_name(&path, metadata) else {
+                    continue;
+                };
+                if path.is_dir() && is_directory_set(&path) && affected.contains(*source) {
+                    folder_sets.insert(source.to_string());
+                }
+                for (display, name) in roms_in_set_path(&path, stem, mame_titles, metadata, parent_to_clones, assume_merged) {
+                    if affected.contains(&name) && seen.insert(name.clone()) {
+                        roms.push((display, name));
+                    }
+                }
+            }
+        }
+    }
+
+    RomsDelta { affected, roms, folder_sets }
+}
+
+/// Watch the ROM and asset directories in the background. Changes are reported once the
+/// directories have been quiet for a full poll interval.
+pub fn start_dir_watcher(
+    key: WatchKey,
+    mame_titles: HashMap<String, String>,
+    metadata: Arc<HashMap<String, GameMetadata>>,
+) -> DirWatcher {
+    let (tx, rx) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_key = key.clone();
+
+    thread::spawn(move || watch_loop(thread_key, mame_titles, metadata, tx, thread_stop));
+
+    DirWatcher { key, rx, stop }
+}
+
+fn watch_loop(
+    key: WatchKey,
+    mame_titles: HashMap<String, String>,
+    metadata: Arc<HashMap<String, GameMetadata>>,
+    tx: Sender<WatchEvent>,
+    stop: Arc<AtomicBool>,
+) {
+    let parent_to_clones = clone_map(&metadata);
+    // Subfolders created after this point are only picked up by a full rescan
+    let list_dirs = expand_rom_dirs(&key.rom_dirs, &key.scan_settings, &metadata);
+    let extra_dirs = expand_rom_dirs(&key.extra_rom_dirs, &key.scan_settings, &metadata);
+    let watched_rom_dirs: Vec<&PathBuf> = list_dirs.iter().chain(&extra_dirs).collect();
+    let mut rom_dirs: Vec<WatchedDir> = watched_rom_dirs.iter().map(|d| WatchedDir::new(d, true)).collect();
+    let mut asset_dirs: Vec<WatchedDir> = key.asset_dirs.iter().map(|d| WatchedDir::new(d, false)).collect();
+
+    let mut pending: HashSet<PathBuf> = HashSet::new();
+    let mut assets_dirty = false;
+
+    while !stop.load(Ordering::Relaxed) {
+        // Sleep in short steps so a stopped watcher exits promptly
+        for _ in 0..8 {
+            thread::sleep(POLL_INTERVAL / 8);
+            if stop.load(Ordering::Relaxed) {
+                return;
+            }
+        }
+
+        let mut changed = false;
+        for (watched, dir) in rom_dirs.iter_mut().zip(&watched_rom_dirs) {
+            let paths = watched.poll(dir, true);
+            changed |= !paths.is_empty();
+            pending.extend(paths);
+        }
+        for (watched, dir) in asset_dirs.iter_mut().zip(&key.asset_dirs) {
+            let paths = watched.poll(dir, false);
+            changed |= !paths.is_empty();
+            assets_dirty |= !paths.is_empty();
+        }
+
+        // Files still being written keep the quiet period from starting
+        for path in &pending {
+            let dir = rom_dirs.iter_mut().find(|w| w.entries.contains_key(path));
+            if let Some(watched) = dir {
+                let now = stat(path);
+                if let (Some(now), Some(before)) = (now, watched.entries.get_mut(path)) {
+                    if now != *before {
+                        *before = now;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        if changed {
+            continue;
+        }
+
+        if !pending.is_empty() {
+            let delta = rom_delta(&pending, &list_dirs, &mame_titles, &metadata, &parent_to_clones, key.assume_merged);
+            pending.clear();
+            if !delta.affected.is_empty() && tx.send(WatchEvent::Roms(delta)).is_err() {
+                return;
+            }
+        }
+        if assets_dirty {
+            assets_dirty = false;
+            if tx.send(WatchEvent::AssetsChanged).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+// ============= END DIRECTORY WATCHER =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh temporary directory, removed again when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("rmameui-watcher-{}-{}", std::process::id(), name));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            TempDir(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn polling_reports_new_files_and_folders_only_once() {
+        let dir = TempDir::new("poll");
+        fs::create_dir(dir.0.join("pacman")).unwrap();
+        let mut watched = WatchedDir::new(&dir.0, true);
+        assert!(watched.poll(&dir.0, true).is_empty());
+
+        fs::write(dir.0.join("galaga.zip"), b"new").unwrap();
+        let changed = watched.poll(&dir.0, true);
+        assert!(changed.contains(&dir.0));
+        assert!(changed.contains(&dir.0.join("galaga.zip")));
+        assert!(watched.poll(&dir.0, true).is_empty());
+
+        // Files inside a set folder only touch the folder's own mtime
+        fs::write(dir.0.join("pacman").join("pacman.6e"), b"rom").unwrap();
+        assert_eq!(watched.poll(&dir.0, true), [dir.0.join("pacman")]);
+        assert!(watched.poll(&dir.0, true).is_empty());
+    }
+
+    #[test]
+    fn watcher_sends_changed_sets_and_stays_quiet_otherwise() {
+        let dir = TempDir::new("thread");
+        let metadata: HashMap<String, GameMetadata> = [(
+            "pacman".to_string(),
+            GameMetadata { name: "pacman".to_string(), description: "Pac-Man".to_string(), ..Default::default() },
+        )].into_iter().collect();
+        let key = WatchKey {
+            rom_dirs: vec![dir.0.clone()],
+            extra_rom_dirs: Vec::new(),
+            asset_dirs: Vec::new(),
+            scan_settings: HashMap::new(),
+            mame_version: String::new(),
+            metadata_len: metadata.len(),
+            assume_merged: false,
+        };
+        let watcher = start_dir_watcher(key, HashMap::new(), Arc::new(metadata));
+
+        assert!(watcher.rx.recv_timeout(POLL_INTERVAL * 2).is_err());
+
+        fs::write(dir.0.join("pacman.zip"), b"").unwrap();
+        match watcher.rx.recv_timeout(POLL_INTERVAL * 5) {
+            Ok(WatchEvent::Roms(delta)) => assert!(delta.affected.contains("pacman")),
+            Ok(WatchEvent::AssetsChanged) => panic!("expected a ROM change"),
+            Err(e) => panic!("no change reported: {}", e),
+        }
+    }
+}
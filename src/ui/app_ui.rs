@@ -1,5 +1,6 @@
 use eframe::egui;
 use crate::app::MyApp;
+use crate::config::DirCheck;
 
 pub fn update(app: &mut MyApp, ctx: &egui::Context, _frame: &mut eframe::Frame) {
     // Apply the selected theme
@@ -11,7 +12,10 @@ pub fn update(app: &mut MyApp, ctx: &egui::Context, _frame: &mut eframe::Frame)
     }
 
     // Show loading overlay if needed
-    if app.roms_loading || app.audit_in_progress {
+    if app.metadata_loading {
+        show_metadata_loading_overlay(app, ctx);
+        ctx.request_repaint(); // Keep animating
+    } else if app.roms_loading || app.audit_in_progress {
         egui::Area::new(egui::Id::new("loading_overlay"))
         .fixed_pos(egui::pos2(0.0, 0.0))
         .order(egui::Order::Foreground)
@@ -131,8 +135,8 @@ pub fn update(app: &mut MyApp, ctx: &egui::Context, _frame: &mut eframe::Frame)
                 let response = ui.label("Details");
                 response.on_hover_ui(|ui| {
                     ui.label("Running games:");
-                    for (rom_name, (_, start_time)) in &app.running_games {
-                        let elapsed = start_time.elapsed();
+                    for (rom_name, game) in &app.running_games {
+                        let elapsed = game.started.elapsed();
                         let minutes = elapsed.as_secs() / 60;
                         let seconds = elapsed.as_secs() % 60;
                         ui.label(format!("• {} ({}:{:02})", rom_name, minutes, seconds));
@@ -167,8 +171,75 @@ pub fn update(app: &mut MyApp, ctx: &egui::Context, _frame: &mut eframe::Frame)
     super::status_bar::show_status_bar(app, ctx);
 }
 
+fn show_metadata_loading_overlay(app: &mut MyApp, ctx: &egui::Context) {
+    egui::Area::new(egui::Id::new("metadata_loading_overlay"))
+    .fixed_pos(egui::pos2(0.0, 0.0))
+    .order(egui::Order::Foreground)
+    .show(ctx, |ui| {
+        let rect = ctx.available_rect();
+        ui.painter().rect_filled(
+            rect,
+            0.0,
+            egui::Color32::from_black_alpha(180),
+        );
+
+        egui::CentralPanel::default()
+        .frame(egui::Frame::NONE)
+        .show_inside(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.spinner();
+                ui.add_space(20.0);
+
+                ui.heading("Loading MAME metadata...");
+                if let Some(name) = app.metadata_loading_path.as_ref()
+                    .and_then(|path| app.config.mame_executables.iter().find(|m| &m.path == path))
+                    .map(|m| m.name.clone())
+                {
+                    ui.label(name);
+                }
+                ui.add_space(10.0);
+                ui.label(&app.metadata_progress);
+
+                ui.add_space(10.0);
+                match app.metadata_fraction {
+                    Some(fraction) => {
+                        ui.add(egui::ProgressBar::new(fraction).show_percentage().desired_width(300.0));
+                    }
+                    None => {
+                        ui.add(egui::ProgressBar::new(0.0).animate(true).desired_width(300.0));
+                    }
+                }
+
+                if let Some(start_time) = app.metadata_start_time {
+                    ui.add_space(10.0);
+                    ui.label(format!("Time elapsed: {}s", start_time.elapsed().as_secs()));
+                }
+
+                let queued = app.metadata_load_queue.len();
+                if queued > 0 {
+                    ui.label(format!("{} more executable{} queued", queued, if queued == 1 { "" } else { "s" }));
+                }
+
+                ui.add_space(10.0);
+                if ui.button("❌ Cancel").clicked() {
+                    app.cancel_metadata_load();
+                }
+            });
+        });
+    });
+}
+
 // NEW: Check for background tasks and update progress
 fn check_background_tasks(app: &mut MyApp, ctx: &egui::Context) {
+    // Check for metadata loading updates
+    app.handle_metadata_progress();
+    app.handle_rebuild_progress();
+    app.handle_collection_progress();
+    app.handle_hygiene_progress();
+    app.handle_directory_checks();
+    app.handle_mame_config_progress();
+    app.update_dir_watcher();
+
     // Check for ROM loading updates
     if let Some(rx) = &app.audit_tx {
         if let Ok(progress) = rx.try_recv() {
@@ -189,6 +260,7 @@ fn check_background_tasks(app: &mut MyApp, ctx: &egui::Context) {
                 }
 
                 app.save_config();
+                app.load_audit_report();
                 app.reload_roms();
                 app.audit_tx = None;
             } else if progress.contains("AUDIT_FAILED") {
@@ -202,8 +274,11 @@ fn check_background_tasks(app: &mut MyApp, ctx: &egui::Context) {
     app.check_running_games();
 
     // Request repaint if we have background tasks or running games
-    if app.roms_loading || app.audit_in_progress || !app.icon_load_queue.is_empty() || !app.running_games.is_empty() {
+    if app.metadata_loading || app.roms_loading || app.audit_in_progress || app.rebuild_tx.is_some() || app.collection_tx.is_some() || app.hygiene_tx.is_some() || app.mame_config_tx.is_some() || app.directory_checks.values().any(|c| matches!(c, DirCheck::Checking)) || !app.icon_load_queue.is_empty() || !app.running_games.is_empty() {
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
+    } else if app.dir_watcher.is_some() {
+        // Wake up now and then to pick up changes on disk
+        ctx.request_repaint_after(std::time::Duration::from_secs(1));
     }
 }
 
@@ -65,7 +65,7 @@ pub fn show_artwork_panel(app: &mut MyApp, ctx: &egui::Context) {
                 }
 
                 ui.separator();
-                if let Some(meta) = app.game_metadata.get(selected_rom) {
+                if let Some(meta) = app.list_metadata().get(selected_rom) {
                     ui.heading(&meta.description);
                     ui.label(format!("Year: {}", meta.year));
                     ui.label(format!("Manufacturer: {}", meta.manufacturer));
@@ -1,12 +1,32 @@
+use std::path::PathBuf;
 use eframe::egui;
 use crate::app::MyApp;
-use crate::models::{MameExecutable, RomSetType, VideoSettings, AppConfig};
-use crate::mame_utils::{get_mame_version, load_mame_metadata_parallel_with_exec};
+use crate::models::{MameExecutable, RomSetType, VideoSettings, AppConfig, DataFolder, DataLayout, DirRole, LaunchProfile, ManagedDir};
+use crate::config::{assign_data_roots, get_data_folder, get_executable_data_dir, get_executables_dir, get_managed_ini_dir, DirCheck};
+use crate::mame_utils::get_mame_version;
+use crate::rom_utils::hygiene::{quarantine_path, quarantine_members};
+use crate::diagnosis::{suggestions, Problem};
+use crate::launch::{join_args, parse_args};
+use crate::mame_ini::{DiffLine, OptionKind};
 use chrono;
 
 pub fn show_dialogs(app: &mut MyApp, ctx: &egui::Context) {
     show_close_dialog(app, ctx);
     show_audit_progress_dialog(app, ctx);
+    show_metadata_error_dialog(app, ctx);
+    show_audit_details_dialog(app, ctx);
+    show_rebuild_dialog(app, ctx);
+    show_collections_dialog(app, ctx);
+    show_hygiene_dialog(app, ctx);
+    show_scan_settings_dialog(app, ctx);
+    show_directories_dialog(app, ctx);
+    show_launch_command_dialog(app, ctx);
+    show_game_settings_dialog(app, ctx);
+    show_mame_config_dialog(app, ctx);
+    show_ini_files_dialog(app, ctx);
+    show_launch_failure_dialog(app, ctx);
+    show_pending_launch_dialog(app, ctx);
+    show_session_logs_dialog(app, ctx);
     show_mame_manager_dialog(app, ctx);
     show_about_dialog(app, ctx);
     show_debug_window(app, ctx);
@@ -16,9 +36,34 @@ pub fn show_dialogs(app: &mut MyApp, ctx: &egui::Context) {
     show_video_settings_dialog(app, ctx);  // ADD THIS LINE
 }
 
+/// A text field for a list of MAME arguments, written with shell quoting. The text being
+/// typed is kept between frames so half-finished quotes survive; `args` is only updated
+/// while the text parses, and an error is shown next to the field when it doesn't.
+/// Also returns whether the text parses, so dialogs can hold off saving until it does.
+fn args_field(ui: &mut egui::Ui, id_salt: &str, args: &mut Vec<String>, hint: &str, width: f32) -> (egui::Response, bool) {
+    let id = ui.make_persistent_id(id_salt);
+    // Start over from `args` whenever they were changed from somewhere else
+    let mut text = ui.data(|d| d.get_temp::<(String, Vec<String>)>(id))
+    .filter(|(_, based_on)| based_on == args)
+    .map(|(text, _)| text)
+    .unwrap_or_else(|| join_args(args));
+
+    let response = ui.add(egui::TextEdit::singleline(&mut text).hint_text(hint).desired_width(width));
+    let parsed = parse_args(&text);
+    match &parsed {
+        Ok(parsed) => *args = parsed.clone(),
+        Err(e) => {
+            ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("⚠ {}", e));
+        }
+    }
+    ui.data_mut(|d| d.insert_temp(id, (text, args.clone())));
+    (response, parsed.is_ok())
+}
+
 // FIXED VERSION OF show_video_settings_dialog
 pub fn show_video_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
     let mut show_dialog = app.show_video_settings;
+    let mut args_ok = true;
 
     if show_dialog {
         egui::Window::new("Video Settings")
@@ -36,12 +81,7 @@ pub fn show_video_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
                 egui::ComboBox::from_label("")
                 .selected_text(&app.config.video_settings.video_backend)
                 .show_ui(ui, |ui| {
-                    ui.selectable_value(&mut app.config.video_settings.video_backend, "auto".to_string(), "Auto");
-                    ui.selectable_value(&mut app.config.video_settings.video_backend, "soft".to_string(), "Software");
-                    ui.selectable_value(&mut app.config.video_settings.video_backend, "opengl".to_string(), "OpenGL");
-                    #[cfg(target_os = "windows")]
-                    ui.selectable_value(&mut app.config.video_settings.video_backend, "d3d".to_string(), "Direct3D");
-                    ui.selectable_value(&mut app.config.video_settings.video_backend, "bgfx".to_string(), "BGFX");
+                    video_backend_choices(ui, &mut app.config.video_settings.video_backend);
                 });
             });
 
@@ -91,8 +131,8 @@ pub fn show_video_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
 
             // Custom arguments
             ui.label("Custom arguments:");
-            ui.text_edit_singleline(&mut app.config.video_settings.custom_args);
-            ui.label("(Additional MAME command-line arguments)");
+            args_ok = args_field(ui, "video_custom_args", &mut app.config.video_settings.custom_args, "e.g. -bgfx_screen_chains \"hlsl,unfiltered\"", 380.0).1;
+            ui.label("(Additional MAME command-line arguments, quoted as in a shell)");
 
             ui.add_space(20.0);
 
@@ -104,7 +144,8 @@ pub fn show_video_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
             });
         });
 
-        app.show_video_settings = show_dialog;
+        // The dialog stays open until the custom arguments parse, so what is saved is what was typed
+        app.show_video_settings = show_dialog || !args_ok;
 
         // Save config when dialog is closed
         if !app.show_video_settings {
@@ -122,6 +163,7 @@ pub fn video_settings_dialog(
     _config: &AppConfig,
 ) {
     let mut should_close = false;  // Track if we should close the dialog
+    let mut args_ok = true;
 
     egui::Window::new("Video Settings")
     .open(show_video_settings)  // Pass the reference directly
@@ -179,154 +221,1662 @@ pub fn video_settings_dialog(
             }
         });
 
-        ui.add_space(10.0);
+        ui.add_space(10.0);
+
+        // Display options
+        ui.group(|ui| {
+            ui.label("Display Options:");
+            ui.checkbox(&mut video_settings.keep_aspect, "Keep aspect ratio");
+            ui.checkbox(&mut video_settings.filter, "Bilinear filtering");
+        });
+
+        ui.add_space(10.0);
+
+        // Custom arguments
+        ui.label("Custom arguments:");
+        args_ok = args_field(ui, "video_custom_args", &mut video_settings.custom_args, "", 380.0).1;
+        ui.label("(Additional MAME command-line arguments, quoted as in a shell)");
+
+        ui.add_space(20.0);
+
+        // Buttons
+        ui.horizontal(|ui| {
+            if ui.add_enabled(args_ok, egui::Button::new("Save")).clicked() {
+                should_close = true;
+            }
+
+            if ui.button("Cancel").clicked() {
+                should_close = true;
+            }
+
+            if ui.button("Reset to Defaults").clicked() {
+                *video_settings = VideoSettings::default();
+            }
+        });
+    });
+
+    // Handle closing after the window is shown
+    if should_close {
+        *show_video_settings = false;
+    }
+}
+
+fn show_close_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if app.show_close_dialog {
+        egui::Window::new("Confirm Exit")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui| {
+                ui.heading("What would you like to do?");
+                ui.add_space(20.0);
+
+                ui.horizontal(|ui| {
+                    if ui.button("🚪 Quit Application").clicked() {
+                        app.save_config();
+                        app.pending_close = true;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("🔽 Minimize to Taskbar").clicked() {
+                        app.show_close_dialog = false;
+                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
+                    }
+
+                    ui.add_space(10.0);
+
+                    if ui.button("❌ Cancel").clicked() {
+                        app.show_close_dialog = false;
+                    }
+                });
+            });
+        });
+    }
+}
+
+fn show_audit_progress_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if app.audit_in_progress {
+        egui::Window::new("ROM Audit Progress")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.heading("Auditing ROM Collection");
+            ui.separator();
+
+            // Show current progress text
+            ui.label(&app.audit_progress);
+
+            // Add a spinning indicator with more visual prominence
+            ui.add_space(10.0);
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Please wait...");
+            });
+
+            // Add a progress bar (if you can estimate progress)
+            let progress = if app.audit_progress.contains("complete") {
+                1.0
+            } else if app.audit_progress.contains("scanning") {
+                0.5
+            } else {
+                0.1
+            };
+            ui.add_space(10.0);
+            ui.add(egui::ProgressBar::new(progress).show_percentage());
+
+            ui.add_space(10.0);
+            ui.label("This may take several minutes for large collections...");
+            ui.label("Checksums are read from every ROM archive on the rompath.");
+
+            // Add a cancel button
+            ui.add_space(10.0);
+            if ui.button("❌ Cancel Audit").clicked() {
+                app.audit_in_progress = false;
+                app.audit_tx = None;
+                app.audit_start_time = None;
+            }
+        });
+
+        // Check for audit updates
+        if let Some(rx) = &app.audit_tx {
+            if let Ok(msg) = rx.try_recv() {
+                if msg == "AUDIT_COMPLETE" {
+                    app.audit_in_progress = false;
+                    app.audit_tx = None;
+                    app.audit_start_time = None;
+                    app.config.use_mame_audit = true;
+
+                    // Update per-MAME audit time
+                    if let Some(current_mame) = app.config.mame_executables.get(app.config.selected_mame_index) {
+                        let mame_id = app.get_mame_identifier(current_mame);
+                        let audit_time = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
+                        app.config.mame_audit_times.insert(mame_id, audit_time);
+                    }
+
+                    app.save_config();
+                    // Reload ROMs with audit data
+                    app.load_audit_report();
+                    app.reload_roms();
+                } else if msg == "AUDIT_FAILED" {
+                    app.audit_in_progress = false;
+                    app.audit_tx = None;
+                    app.audit_start_time = None;
+                } else {
+                    app.audit_progress = msg;
+                }
+            }
+        }
+    }
+}
+
+fn show_metadata_error_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some((mame_path, message)) = app.metadata_error.clone() else {
+        return;
+    };
+
+    let mame_name = app.config.mame_executables.iter()
+    .find(|m| m.path == mame_path)
+    .map(|m| m.name.clone())
+    .unwrap_or_else(|| mame_path.clone());
+
+    egui::Window::new("Failed to Load MAME Metadata")
+    .collapsible(false)
+    .resizable(false)
+    .default_width(450.0)
+    .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+    .show(ctx, |ui| {
+        ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("❌ {}", mame_name));
+        ui.label(format!("Path: {}", mame_path));
+        ui.add_space(10.0);
+        ui.label(&message);
+        ui.add_space(10.0);
+        ui.label("Check that the executable exists and runs, then try again.");
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("🔄 Retry").clicked() {
+                app.retry_metadata_load();
+            }
+            if ui.button("Dismiss").clicked() {
+                app.metadata_error = None;
+            }
+        });
+    });
+}
+
+fn show_audit_details_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(rom_name) = app.audit_details_rom.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let title = app.list_metadata().get(&rom_name)
+    .map(|m| format!("Audit Details - {} ({})", m.description, rom_name))
+    .unwrap_or_else(|| format!("Audit Details - {}", rom_name));
+
+    egui::Window::new(title)
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(550.0)
+    .show(ctx, |ui| {
+        let Some(result) = app.list_audit_results().get(&rom_name) else {
+            ui.label("This set has not been audited yet.");
+            ui.add_space(10.0);
+            if let Some(index) = app.collection.as_ref().map(|c| c.index) {
+                if ui.add_enabled(app.collection_tx.is_none(), egui::Button::new("🔍 Verify collection")).clicked() {
+                    app.open_collection(index, true);
+                }
+            } else if ui.button("🔍 Run ROM Audit").clicked() {
+                app.run_rom_audit();
+            }
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.label("Status:");
+            ui.colored_label(result.status.to_color(), result.status.label());
+        });
+        if let Some(current_mame) = app.config.mame_executables.get(app.config.selected_mame_index) {
+            let mame_id = app.get_mame_identifier(current_mame);
+            if let Some(last_audit) = app.config.mame_audit_times.get(&mame_id) {
+                ui.label(format!("Last audit: {}", last_audit));
+            }
+        }
+
+        ui.separator();
+
+        if result.problems.is_empty() {
+            ui.label("All files verified, nothing to report.");
+            return;
+        }
+
+        egui::ScrollArea::vertical().max_height(350.0).show(ui, |ui| {
+            egui::Grid::new("audit_problems_grid")
+            .num_columns(4)
+            .striped(true)
+            .spacing([15.0, 4.0])
+            .show(ui, |ui| {
+                ui.strong("File");
+                ui.strong("Problem");
+                ui.strong("Expected");
+                ui.strong("Found");
+                ui.end_row();
+
+                for problem in &result.problems {
+                    ui.monospace(&problem.file);
+                    ui.label(problem.kind.label());
+                    ui.monospace(problem.expected.as_deref().unwrap_or("-"));
+                    ui.monospace(problem.found.as_deref().unwrap_or("-"));
+                    ui.end_row();
+                }
+            });
+        });
+    });
+
+    if !open {
+        app.audit_details_rom = None;
+    }
+}
+
+fn show_rebuild_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_rebuild_dialog {
+        return;
+    }
+
+    let mut open = true;
+    let running = app.rebuild_tx.is_some();
+
+    egui::Window::new("Rebuild ROM Sets")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(500.0)
+    .show(ctx, |ui| {
+        ui.label("Sets are read from the source folders and written as zips to the target folder.");
+        ui.label("Files are matched by CRC, so the source layout doesn't matter. CHDs are not moved.");
+        ui.separator();
+
+        ui.add_enabled_ui(!running, |ui| {
+            ui.label("Source folders:");
+            let mut remove = None;
+            for (i, dir) in app.rebuild_options.source_dirs.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✖").on_hover_text("Don't read from this folder").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.monospace(dir.display().to_string());
+                });
+            }
+            if let Some(i) = remove {
+                app.rebuild_options.source_dirs.remove(i);
+            }
+            ui.horizontal(|ui| {
+                if ui.button("Add...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        if !app.rebuild_options.source_dirs.contains(&path) {
+                            app.rebuild_options.source_dirs.push(path);
+                        }
+                    }
+                }
+                if ui.button("Use ROM paths").on_hover_text("Read from every ROM directory, as MAME would").clicked() {
+                    app.rebuild_options.source_dirs = app.rompath_dirs();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Target folder:");
+                let target = app.rebuild_options.target_dir.as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "(not chosen)".to_string());
+                ui.monospace(target);
+                if ui.button("Browse...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        app.rebuild_options.target_dir = Some(path);
+                    }
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Layout:");
+                for (layout, label) in [
+                    (RomSetType::NonMerged, "Non-merged"),
+                    (RomSetType::Split, "Split"),
+                    (RomSetType::Merged, "Merged"),
+                ] {
+                    ui.radio_value(&mut app.rebuild_options.layout, layout, label);
+                }
+            });
+
+            ui.checkbox(&mut app.rebuild_options.dry_run, "Dry run (report only, write nothing)");
+            ui.checkbox(&mut app.rebuild_options.keep_source, "Leave source files untouched")
+            .on_hover_text("When off, source archives whose every file was rebuilt are deleted");
+        });
+
+        ui.add_space(10.0);
+
+        ui.horizontal(|ui| {
+            let can_start = !running
+                && app.rebuild_options.target_dir.is_some()
+                && !app.rebuild_options.source_dirs.is_empty()
+                && !app.game_metadata.is_empty();
+            let label = if app.rebuild_options.dry_run { "🔍 Preview" } else { "🧱 Rebuild" };
+            if ui.add_enabled(can_start, egui::Button::new(label)).clicked() {
+                app.start_rom_rebuild();
+            }
+            if running {
+                ui.spinner();
+            }
+            ui.label(&app.rebuild_progress);
+        });
+
+        match &app.rebuild_report {
+            Some(Ok(report)) => {
+                ui.separator();
+                let verb = if report.dry_run { "Would write" } else { "Wrote" };
+                ui.label(format!("{} {} sets ({} files)", verb, report.sets_written, report.files_written));
+                if report.sources_removed > 0 {
+                    ui.label(format!("Removed {} rebuilt source archives and folders", report.sources_removed));
+                }
+                for error in &report.errors {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), error);
+                }
+
+                if !report.incomplete.is_empty() {
+                    ui.label(format!("{} sets are incomplete:", report.incomplete.len()));
+                    egui::ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                        for (set, missing) in &report.incomplete {
+                            ui.monospace(format!("{}: missing {}", set, missing.join(", ")));
+                        }
+                    });
+                }
+            }
+            Some(Err(e)) => {
+                ui.separator();
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("Rebuild failed: {}", e));
+            }
+            None => {}
+        }
+    });
+
+    if !open {
+        app.show_rebuild_dialog = false;
+    }
+}
+
+fn show_collections_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_collections_dialog {
+        return;
+    }
+
+    let mut open = true;
+    let busy = app.collection_tx.is_some();
+
+    egui::Window::new("DAT Collections")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(600.0)
+    .show(ctx, |ui| {
+        ui.label("Verify a ROM folder against a Logiqx XML or ClrMamePro DAT and browse it in the ROM list.");
+        ui.separator();
+
+        if app.config.custom_collections.is_empty() {
+            ui.label("No collections yet.");
+        }
+
+        let mut open_request = None;
+        let mut remove_index = None;
+        egui::Grid::new("collections_grid")
+        .num_columns(4)
+        .striped(true)
+        .spacing([15.0, 4.0])
+        .show(ui, |ui| {
+            for (index, collection) in app.config.custom_collections.iter().enumerate() {
+                let is_open = app.collection.as_ref().map(|c| c.index) == Some(index);
+                if is_open {
+                    ui.strong(format!("▶ {}", collection.name));
+                } else {
+                    ui.label(&collection.name);
+                }
+                ui.monospace(collection.dat_path.display().to_string())
+                .on_hover_text(format!("ROMs: {}", collection.rom_dir.display()));
+
+                ui.add_enabled_ui(!busy, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.button("Open").clicked() {
+                            open_request = Some((index, false));
+                        }
+                        if ui.button("🔍 Verify").clicked() {
+                            open_request = Some((index, true));
+                        }
+                    });
+                });
+                if ui.button("🗑 Remove").clicked() {
+                    remove_index = Some(index);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some((index, verify)) = open_request {
+            app.open_collection(index, verify);
+        }
+        if let Some(index) = remove_index {
+            app.remove_collection(index);
+        }
+
+        if busy || !app.collection_progress.is_empty() {
+            ui.horizontal(|ui| {
+                if busy {
+                    ui.spinner();
+                }
+                ui.label(&app.collection_progress);
+            });
+        }
+
+        ui.separator();
+        ui.strong("Add collection");
+
+        egui::Grid::new("new_collection_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Name:");
+            ui.text_edit_singleline(&mut app.collection_draft.name);
+            ui.end_row();
+
+            ui.label("DAT file:");
+            ui.horizontal(|ui| {
+                if ui.button("Browse...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new()
+                    .add_filter("DAT", &["dat", "xml"])
+                    .pick_file() {
+                        if app.collection_draft.name.is_empty() {
+                            app.collection_draft.name = path.file_stem()
+                            .map(|s| s.to_string_lossy().into_owned())
+                            .unwrap_or_default();
+                        }
+                        app.collection_draft.dat_path = path;
+                    }
+                }
+                ui.monospace(app.collection_draft.dat_path.display().to_string());
+            });
+            ui.end_row();
+
+            ui.label("ROM folder:");
+            ui.horizontal(|ui| {
+                if ui.button("Browse...").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                        app.collection_draft.rom_dir = path;
+                    }
+                }
+                ui.monospace(app.collection_draft.rom_dir.display().to_string());
+            });
+            ui.end_row();
+        });
+
+        let draft = &app.collection_draft;
+        let name_taken = app.config.custom_collections.iter().any(|c| c.slug() == draft.slug());
+        let ready = !draft.name.trim().is_empty()
+            && draft.dat_path.is_file()
+            && draft.rom_dir.is_dir()
+            && !name_taken;
+        if name_taken {
+            ui.colored_label(egui::Color32::from_rgb(255, 200, 100), "A collection with this name already exists");
+        }
+        if ui.add_enabled(ready && !busy, egui::Button::new("➕ Add and verify")).clicked() {
+            let mut collection = std::mem::take(&mut app.collection_draft);
+            collection.name = collection.name.trim().to_string();
+            app.config.custom_collections.push(collection);
+            app.save_config();
+            app.open_collection(app.config.custom_collections.len() - 1, true);
+        }
+    });
+
+    if !open {
+        app.show_collections_dialog = false;
+    }
+}
+
+/// The video backends offered in the video settings and game settings dialogs
+fn video_backend_choices(ui: &mut egui::Ui, backend: &mut String) {
+    ui.selectable_value(backend, "auto".to_string(), "Auto");
+    ui.selectable_value(backend, "soft".to_string(), "Software");
+    ui.selectable_value(backend, "opengl".to_string(), "OpenGL");
+    #[cfg(target_os = "windows")]
+    ui.selectable_value(backend, "d3d".to_string(), "Direct3D");
+    ui.selectable_value(backend, "bgfx".to_string(), "BGFX");
+}
+
+/// A grid row for a game setting that either follows the global value, shown greyed
+/// out, or overrides it with its own
+fn override_row<T: Clone>(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<T>,
+    global: &T,
+    edit: impl FnOnce(&mut egui::Ui, &mut T),
+) {
+    let mut overridden = value.is_some();
+    if ui.checkbox(&mut overridden, label).changed() {
+        *value = overridden.then(|| global.clone());
+    }
+    let mut current = value.clone().unwrap_or_else(|| global.clone());
+    ui.add_enabled_ui(overridden, |ui| edit(ui, &mut current));
+    if overridden {
+        *value = Some(current);
+    }
+    ui.end_row();
+}
+
+fn show_game_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(rom) = app.game_settings_rom.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut apply = false;
+    let mut cancel = false;
+    let mut args_ok = true;
+
+    let executables: Vec<String> = app.config.mame_executables.iter().map(|m| m.name.clone()).collect();
+    let default_exe = app.config.game_preferred_mame.get(&rom)
+    .copied()
+    .unwrap_or(app.config.selected_mame_index);
+    let graphics = &app.config.graphics_config;
+    let presets: Vec<String> = graphics.presets.iter().chain(graphics.custom_presets.iter()).map(|p| p.name.clone()).collect();
+    let global_preset = graphics.global_preset.clone();
+    let global_video = app.config.video_settings.clone();
+
+    egui::Window::new(format!("Game Settings: {}", rom))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(600.0)
+    .show(ctx, |ui| {
+        let profile = &mut app.game_settings_draft;
+        ui.label("Anything left unticked or on its default follows the global settings.");
+        ui.separator();
+
+        egui::Grid::new("game_settings_launch").num_columns(2).spacing([10.0, 6.0]).show(ui, |ui| {
+            ui.label("MAME executable:");
+            let default_name = executables.get(default_exe).map(String::as_str).unwrap_or("none");
+            let selected = profile.mame_index
+            .and_then(|i| executables.get(i).cloned())
+            .unwrap_or_else(|| format!("Default ({})", default_name));
+            egui::ComboBox::from_id_salt("game_settings_exe").selected_text(selected).show_ui(ui, |ui| {
+                ui.selectable_value(&mut profile.mame_index, None, format!("Default ({})", default_name));
+                for (index, name) in executables.iter().enumerate() {
+                    ui.selectable_value(&mut profile.mame_index, Some(index), name);
+                }
+            });
+            ui.end_row();
+
+            ui.label("Graphics preset:");
+            let selected = profile.graphics_preset.clone().unwrap_or_else(|| format!("Global ({})", global_preset));
+            egui::ComboBox::from_id_salt("game_settings_preset").selected_text(selected).show_ui(ui, |ui| {
+                ui.selectable_value(&mut profile.graphics_preset, None, format!("Global ({})", global_preset));
+                for name in &presets {
+                    ui.selectable_value(&mut profile.graphics_preset, Some(name.clone()), name);
+                }
+            });
+            ui.end_row();
+        });
+
+        ui.add_space(6.0);
+        egui::CollapsingHeader::new("Video settings").default_open(true).show(ui, |ui| {
+            let video = &mut profile.video;
+            egui::Grid::new("game_settings_video").num_columns(2).spacing([10.0, 4.0]).show(ui, |ui| {
+                override_row(ui, "Video backend", &mut video.video_backend, &global_video.video_backend, |ui, backend| {
+                    egui::ComboBox::from_id_salt("game_settings_backend").selected_text(backend.clone()).show_ui(ui, |ui| {
+                        video_backend_choices(ui, backend);
+                    });
+                });
+                override_row(ui, "Run in window", &mut video.window_mode, &global_video.window_mode, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Start maximized", &mut video.maximize, &global_video.maximize, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Wait for V-Sync", &mut video.wait_vsync, &global_video.wait_vsync, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Sync to monitor refresh", &mut video.sync_refresh, &global_video.sync_refresh, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Prescale", &mut video.prescale, &global_video.prescale, |ui, prescale| {
+                    ui.add(egui::Slider::new(prescale, 0..=3).text("x"));
+                });
+                override_row(ui, "Keep aspect ratio", &mut video.keep_aspect, &global_video.keep_aspect, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Bilinear filtering", &mut video.filter, &global_video.filter, |ui, on| {
+                    ui.checkbox(on, "");
+                });
+                override_row(ui, "Number of screens", &mut video.num_screens, &global_video.num_screens, |ui, screens| {
+                    ui.add(egui::Slider::new(screens, 1..=4));
+                });
+                override_row(ui, "Custom arguments", &mut video.custom_args, &global_video.custom_args, |ui, args| {
+                    ui.vertical(|ui| {
+                        args_ok &= args_field(ui, "game_settings_custom_args", args, "", 300.0).1;
+                    });
+                });
+            });
+        });
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            ui.label("Extra arguments:");
+            args_ok &= args_field(ui, "game_settings_extra_args", &mut profile.extra_args, "applied after everything else", 350.0).1;
+        });
+
+        ui.add_space(6.0);
+        ui.label("Environment variables:");
+        let mut remove_env = None;
+        for (index, (key, value)) in profile.env.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                ui.add(egui::TextEdit::singleline(key).hint_text("NAME").desired_width(150.0));
+                ui.label("=");
+                ui.add(egui::TextEdit::singleline(value).desired_width(300.0));
+                if ui.small_button("🗑").clicked() {
+                    remove_env = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove_env {
+            profile.env.remove(index);
+        }
+        if ui.button("➕ Add variable").clicked() {
+            profile.env.push((String::new(), String::new()));
+        }
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            ui.label("Working directory:");
+            let mut text = profile.working_dir.as_ref().map(|d| d.display().to_string()).unwrap_or_default();
+            let edit = egui::TextEdit::singleline(&mut text).hint_text("launcher's own").desired_width(300.0);
+            if ui.add(edit).changed() {
+                profile.working_dir = (!text.is_empty()).then(|| PathBuf::from(text));
+            }
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    profile.working_dir = Some(path);
+                }
+            }
+            if let Some(dir) = &profile.working_dir {
+                if !dir.is_dir() {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), "⚠ Not found");
+                }
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(args_ok, egui::Button::new("✔ Apply")).on_disabled_hover_text("Fix the arguments marked ⚠ first").clicked() {
+                apply = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+            if ui.button("Reset to global settings").clicked() {
+                *profile = LaunchProfile::default();
+            }
+        });
+    });
+
+    if apply {
+        app.apply_game_settings();
+    }
+    if apply || cancel || !open {
+        app.game_settings_rom = None;
+        app.game_settings_draft = LaunchProfile::default();
+    }
+}
+
+fn show_ini_files_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(files) = app.ini_preview.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut write = false;
+    let mut refresh = false;
+    let mut close = false;
+    let changed = files.iter().filter(|f| f.is_changed()).count();
+
+    egui::Window::new("MAME ini Files")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_size([750.0, 500.0])
+    .show(ctx, |ui| {
+        ui.label("The launcher's settings as ini files, so they also apply when games are started from MAME's own menus.");
+        ui.label(format!("Folder: {}", get_managed_ini_dir().display()));
+        if app.mame_config_tx.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Reading MAME's own settings...");
+            });
+        } else if !matches!(app.mame_own_config, Some((_, Ok(_)))) {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 200, 100),
+                "MAME's own settings couldn't be read, so mame.ini only holds the launcher's. MAME stops reading your own mame.ini once it finds this one.",
+            );
+        }
+        if ui.checkbox(&mut app.config.use_ini_files, "Pass -inipath so MAME reads these files").changed() {
+            app.save_config();
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical().max_height(380.0).show(ui, |ui| {
+            for file in &files {
+                let name = file.path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                let color = match file.status() {
+                    "new" => egui::Color32::from_rgb(100, 200, 100),
+                    "removed" => egui::Color32::from_rgb(255, 100, 100),
+                    "changed" => egui::Color32::from_rgb(255, 200, 100),
+                    _ => ui.visuals().weak_text_color(),
+                };
+                let header = egui::RichText::new(format!("{} ({})", name, file.status())).color(color);
+                egui::CollapsingHeader::new(header)
+                .id_salt(&file.path)
+                .default_open(file.is_changed() && changed <= 3)
+                .show(ui, |ui| {
+                    for line in file.diff() {
+                        let (text, color) = match line {
+                            DiffLine::Same(text) => (format!("  {}", text), ui.visuals().text_color()),
+                            DiffLine::Added(text) => (format!("+ {}", text), egui::Color32::from_rgb(100, 200, 100)),
+                            DiffLine::Removed(text) => (format!("- {}", text), egui::Color32::from_rgb(255, 100, 100)),
+                        };
+                        ui.label(egui::RichText::new(text).monospace().color(color));
+                    }
+                });
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            let label = format!("💾 Write {} changed file{}", changed, if changed == 1 { "" } else { "s" });
+            // Writing before MAME's own settings are in would leave them out of mame.ini
+            if ui.add_enabled(changed > 0 && app.mame_config_tx.is_none(), egui::Button::new(label)).clicked() {
+                write = true;
+            }
+            if ui.button("🔄 Refresh").clicked() {
+                refresh = true;
+            }
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+        });
+    });
+
+    if write {
+        app.write_ini_preview();
+    } else if refresh {
+        app.open_ini_preview();
+    }
+    if close || !open {
+        app.ini_preview = None;
+    }
+}
+
+/// An editor for one option's value that suits its kind
+fn option_value_editor(ui: &mut egui::Ui, kind: OptionKind, value: &mut String) {
+    match kind {
+        OptionKind::Switch => {
+            let mut on = value == "1";
+            if ui.checkbox(&mut on, "").changed() {
+                *value = if on { "1" } else { "0" }.to_string();
+            }
+        }
+        OptionKind::Integer => {
+            let mut number: i64 = value.parse().unwrap_or_default();
+            if ui.add(egui::DragValue::new(&mut number)).changed() {
+                *value = number.to_string();
+            }
+        }
+        OptionKind::Float => {
+            let mut number: f64 = value.parse().unwrap_or_default();
+            if ui.add(egui::DragValue::new(&mut number).speed(0.01)).changed() {
+                *value = number.to_string();
+            }
+        }
+        OptionKind::PathList | OptionKind::Text => {
+            ui.add(egui::TextEdit::singleline(value).desired_width(220.0));
+        }
+    }
+}
+
+fn show_mame_config_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_mame_config {
+        return;
+    }
+
+    let mut open = true;
+    let mut reload = false;
+    let mut save = false;
+    let mut import = false;
+    let mut close = false;
+
+    let mame_name = app.config.mame_executables.get(app.config.selected_mame_index)
+    .map(|m| m.name.clone())
+    .unwrap_or_default();
+
+    egui::Window::new(format!("MAME Configuration: {}", mame_name))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_size([900.0, 550.0])
+    .show(ctx, |ui| {
+        ui.label("The options MAME ends up with after reading its own ini files (-showconfig). Edited options are written to the launcher's managed mame.ini.");
+        if !app.config.use_ini_files {
+            ui.colored_label(
+                egui::Color32::from_rgb(255, 200, 100),
+                "MAME only reads the managed mame.ini when -inipath is passed; turn it on in Options > MAME ini Files.",
+            );
+        }
+        ui.horizontal(|ui| {
+            ui.label("Filter:");
+            ui.text_edit_singleline(&mut app.mame_config_filter);
+            if ui.button("🔄 Reload").clicked() {
+                reload = true;
+            }
+            if ui.button("📥 Import rompath into directories").clicked() {
+                import = true;
+            }
+        });
+        if let Some(message) = &app.mame_config_message {
+            ui.label(message);
+        }
+        ui.separator();
+
+        if app.mame_config_tx.is_some() {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("Running MAME -showconfig...");
+            });
+            return;
+        }
+        let options = match &app.mame_config {
+            Ok(options) => options,
+            Err(e) => {
+                ui.colored_label(egui::Color32::from_rgb(255, 100, 100), e);
+                return;
+            }
+        };
+        let filter = app.mame_config_filter.to_lowercase();
+
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            egui::Grid::new("mame_config_grid")
+            .num_columns(4)
+            .striped(true)
+            .spacing([12.0, 4.0])
+            .show(ui, |ui| {
+                ui.strong("Option");
+                ui.strong("MAME value");
+                ui.strong("Managed mame.ini");
+                ui.strong("Launcher");
+                ui.end_row();
+
+                let mut section = "";
+                for option in options.iter().filter(|o| filter.is_empty() || o.name.contains(&filter)) {
+                    if option.section != section {
+                        section = &option.section;
+                        ui.label(egui::RichText::new(section).weak());
+                        ui.end_row();
+                    }
+
+                    ui.monospace(&option.name);
+                    ui.label(&option.value);
+
+                    let launch = app.mame_config_launch.iter().find(|(name, _, _)| *name == option.name);
+                    ui.horizontal(|ui| {
+                        if launch.is_some() {
+                            ui.label(egui::RichText::new("set by the launcher").weak());
+                        } else if let Some(value) = app.mame_config_edits.get_mut(&option.name) {
+                            option_value_editor(ui, option.kind, value);
+                            if ui.small_button("↺").on_hover_text("Use MAME's value").clicked() {
+                                app.mame_config_edits.remove(&option.name);
+                            }
+                        } else if ui.small_button("✏").on_hover_text("Set in the managed mame.ini").clicked() {
+                            app.mame_config_edits.insert(option.name.clone(), option.value.clone());
+                        }
+                    });
+
+                    match launch {
+                        Some((_, value, layer)) if *value == option.value => {
+                            ui.label(egui::RichText::new("passes the same value").weak()).on_hover_text(layer);
+                        }
+                        Some((_, value, layer)) => {
+                            ui.colored_label(egui::Color32::from_rgb(255, 200, 100), format!("overrides with {}", value))
+                            .on_hover_text(format!("From {}", layer));
+                        }
+                        None => {
+                            ui.label("");
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("💾 Save to managed mame.ini...").clicked() {
+                save = true;
+            }
+            if ui.button("Close").clicked() {
+                close = true;
+            }
+        });
+    });
+
+    if reload {
+        app.load_mame_config();
+    }
+    if import {
+        app.import_mame_rompath();
+    }
+    if save {
+        app.save_mame_config();
+    }
+    if close || !open {
+        app.show_mame_config = false;
+    }
+}
+
+fn show_launch_command_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(plan) = app.launch_preview.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut launch = None;
+
+    egui::Window::new(format!("Launch Command: {}", plan.rom))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(650.0)
+    .show(ctx, |ui| {
+        egui::Grid::new("launch_command_grid")
+        .num_columns(2)
+        .spacing([10.0, 4.0])
+        .show(ui, |ui| {
+            ui.label("Program:");
+            ui.monospace(&plan.program);
+            ui.end_row();
+
+            ui.label("Working directory:");
+            match &plan.working_dir {
+                Some(dir) => ui.monospace(dir.display().to_string()),
+                None => ui.label("(the launcher's own)"),
+            };
+            ui.end_row();
+
+            ui.label("Environment:");
+            if plan.env.is_empty() {
+                ui.label("(inherited, nothing added)");
+            } else {
+                ui.vertical(|ui| {
+                    for (key, value) in &plan.env {
+                        ui.monospace(format!("{}={}", key, value));
+                    }
+                });
+            }
+            ui.end_row();
+        });
+
+        if !plan.ignored_args.is_empty() {
+            ui.separator();
+            for (arg, layer) in &plan.ignored_args {
+                let problem = Problem::IgnoredArgument { arg: arg.clone(), layer: layer.clone() };
+                ui.colored_label(egui::Color32::from_rgb(255, 200, 100), format!("⚠ {}", problem.summary()))
+                .on_hover_text(problem.suggestion(&plan.rom));
+            }
+        }
+
+        ui.separator();
+        ui.label("Shell command:");
+        let command = plan.shell_command();
+        // A &str buffer can be selected but not edited
+        ui.add(egui::TextEdit::multiline(&mut command.as_str())
+            .code_editor()
+            .desired_rows(3)
+            .desired_width(f32::INFINITY));
+        if ui.button("📋 Copy").clicked() {
+            ui.ctx().copy_text(command.clone());
+        }
+
+        ui.separator();
+        egui::CollapsingHeader::new("Launch with edited arguments")
+        .show(ui, |ui| {
+            ui.label("One argument per line, machine name last. Changes only apply to this launch.");
+            ui.add(egui::TextEdit::multiline(&mut app.launch_preview_args)
+                .code_editor()
+                .desired_rows(10)
+                .desired_width(f32::INFINITY));
+            ui.horizontal(|ui| {
+                if ui.button("▶ Launch").clicked() {
+                    let mut edited = plan.clone();
+                    edited.args = app.launch_preview_args.lines()
+                    .map(|line| line.trim_end_matches('\r').to_string())
+                    .filter(|line| !line.trim().is_empty())
+                    .collect();
+                    // The arguments go to MAME as written, so nothing is left out
+                    edited.ignored_args.clear();
+                    launch = Some(edited);
+                }
+                if ui.button("Reset").clicked() {
+                    app.launch_preview_args = plan.args.join("\n");
+                }
+            });
+        });
+
+        ui.separator();
+        if ui.button("▶ Launch as shown").clicked() {
+            launch = Some(plan.clone());
+        }
+    });
+
+    if let Some(plan) = launch {
+        app.spawn_launch(&plan);
+        open = false;
+    }
+    if !open {
+        app.launch_preview = None;
+        app.launch_preview_args.clear();
+    }
+}
+
+fn show_pending_launch_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(plan) = app.pending_launch.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut launch = false;
+    let mut cancel = false;
+
+    egui::Window::new(format!("{} is missing files", plan.rom))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(550.0)
+    .show(ctx, |ui| {
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 200, 100),
+            "These weren't found in the configured directories, so MAME will probably refuse to start:",
+        );
+        ui.add_space(4.0);
+        for problem in &app.pending_launch_problems {
+            ui.label(format!("• {}", problem.summary()));
+        }
+
+        ui.add_space(6.0);
+        ui.strong("Suggestions:");
+        for suggestion in suggestions(&app.pending_launch_problems, &plan.rom) {
+            ui.colored_label(egui::Color32::from_rgb(150, 200, 255), format!("→ {}", suggestion));
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("▶ Launch anyway").clicked() {
+                launch = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
+        });
+    });
+
+    if launch && app.spawn_launch(&plan) {
+        if let Some(idx) = app.pending_launch_mame {
+            app.config.game_preferred_mame.insert(plan.rom.clone(), idx);
+            app.save_config();
+        }
+    }
+    if launch || cancel || !open {
+        app.pending_launch = None;
+        app.pending_launch_mame = None;
+        app.pending_launch_problems.clear();
+    }
+}
+
+fn show_launch_failure_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(failure) = app.launch_failure.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut show_logs = false;
+
+    egui::Window::new(format!("{} did not run", failure.rom))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(650.0)
+    .show(ctx, |ui| {
+        ui.colored_label(
+            egui::Color32::from_rgb(255, 100, 100),
+            format!("MAME stopped with {} after {}s.", failure.exit, failure.run_secs),
+        );
+        ui.label(format!("Log: {}", failure.log_path.display()));
+        ui.separator();
+
+        if !failure.problems.is_empty() {
+            ui.strong("What went wrong:");
+            egui::ScrollArea::vertical().id_salt("failure_problems").max_height(180.0).show(ui, |ui| {
+                for problem in &failure.problems {
+                    ui.label(format!("• {}", problem.summary()));
+                }
+            });
+            ui.add_space(6.0);
+            ui.strong("Suggestions:");
+            for suggestion in suggestions(&failure.problems, &failure.rom) {
+                ui.colored_label(egui::Color32::from_rgb(150, 200, 255), format!("→ {}", suggestion));
+            }
+            ui.separator();
+        }
+
+        ui.label("Last lines of output:");
+        egui::ScrollArea::vertical()
+        .max_height(300.0)
+        .stick_to_bottom(true)
+        .show(ui, |ui| {
+            ui.add(egui::TextEdit::multiline(&mut failure.tail.as_str())
+                .code_editor()
+                .desired_width(f32::INFINITY));
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("📜 All sessions").clicked() {
+                show_logs = true;
+            }
+            if ui.button("Close").clicked() {
+                app.launch_failure = None;
+            }
+        });
+    });
+
+    if show_logs {
+        app.open_session_logs(&failure.rom);
+        app.launch_failure = None;
+    }
+    if !open {
+        app.launch_failure = None;
+    }
+}
+
+fn show_session_logs_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    let Some(rom) = app.session_logs_rom.clone() else {
+        return;
+    };
+
+    let mut open = true;
+    let mut select = None;
+
+    egui::Window::new(format!("Session Logs: {}", rom))
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_size([800.0, 450.0])
+    .show(ctx, |ui| {
+        if app.session_logs.is_empty() {
+            ui.label("This game has no session logs yet.");
+            return;
+        }
+
+        ui.horizontal_top(|ui| {
+            ui.vertical(|ui| {
+                ui.set_width(230.0);
+                egui::ScrollArea::vertical().id_salt("session_list").show(ui, |ui| {
+                    for (index, log) in app.session_logs.iter().enumerate() {
+                        let started = chrono::DateTime::parse_from_rfc3339(&log.started)
+                        .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                        .unwrap_or_else(|_| log.path.file_stem().unwrap_or_default().to_string_lossy().into_owned());
+                        let exit = log.exit.as_deref().unwrap_or("running or interrupted");
+                        let ok = log.exit.as_deref().is_some_and(|e| e.starts_with("exit code 0 "));
+                        let text = egui::RichText::new(format!("{}\n{}", started, exit));
+                        let text = if ok { text } else { text.color(egui::Color32::from_rgb(255, 150, 100)) };
+                        if ui.selectable_label(app.session_log_selected == index, text).clicked() {
+                            select = Some(index);
+                        }
+                    }
+                });
+            });
+
+            ui.separator();
+
+            egui::ScrollArea::both().id_salt("session_text").show(ui, |ui| {
+                ui.add(egui::TextEdit::multiline(&mut app.session_log_text.as_str())
+                    .code_editor()
+                    .desired_width(f32::INFINITY));
+            });
+        });
+    });
+
+    if let Some(index) = select {
+        app.select_session_log(index);
+    }
+    if !open {
+        app.session_logs_rom = None;
+        app.session_logs.clear();
+        app.session_log_text.clear();
+    }
+}
 
-        // Display options
-        ui.group(|ui| {
-            ui.label("Display Options:");
-            ui.checkbox(&mut video_settings.keep_aspect, "Keep aspect ratio");
-            ui.checkbox(&mut video_settings.filter, "Bilinear filtering");
-        });
+/// A change to one row of the directories manager, applied after the list is drawn
+enum DirectoryEdit {
+    MoveUp(usize),
+    MoveDown(usize),
+    Browse(usize),
+    Remove(usize),
+}
 
-        ui.add_space(10.0);
+fn show_directories_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_directories_dialog {
+        return;
+    }
+    app.check_draft_directories();
+
+    let mut open = true;
+    let mut apply = false;
+    let mut cancel = false;
+    let mut edit = None;
+
+    egui::Window::new("Directories")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(750.0)
+    .show(ctx, |ui| {
+        ui.label("ROM and CHD directories are searched from the top down, which is the -rompath order MAME uses.");
+        ui.separator();
 
-        // Custom arguments
-        ui.label("Custom arguments:");
-        ui.text_edit_singleline(&mut video_settings.custom_args);
-        ui.label("(Additional MAME command-line arguments)");
+        if app.directories_draft.is_empty() {
+            ui.label("No directories yet.");
+        }
 
-        ui.add_space(20.0);
+        egui::ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            egui::Grid::new("directories_grid")
+            .num_columns(5)
+            .striped(true)
+            .spacing([10.0, 4.0])
+            .show(ui, |ui| {
+                let count = app.directories_draft.len();
+                for (index, dir) in app.directories_draft.iter_mut().enumerate() {
+                    match app.directory_checks.get(&(dir.path.clone(), dir.role)).unwrap_or(&DirCheck::Checking) {
+                        DirCheck::Ok(summary) => {
+                            ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "✔").on_hover_text(summary.as_str());
+                        }
+                        DirCheck::Warning(message) => {
+                            ui.colored_label(egui::Color32::from_rgb(255, 200, 100), "⚠").on_hover_text(message.as_str());
+                        }
+                        DirCheck::Missing => {
+                            ui.colored_label(egui::Color32::from_rgb(255, 100, 100), "✖").on_hover_text("Directory not found");
+                        }
+                        DirCheck::Checking => {
+                            ui.spinner().on_hover_text("Checking directory...");
+                        }
+                    }
 
-        // Buttons
+                    ui.checkbox(&mut dir.enabled, "").on_hover_text("Use this directory");
+
+                    egui::ComboBox::from_id_salt(("directory_role", index))
+                    .selected_text(dir.role.name())
+                    .show_ui(ui, |ui| {
+                        for role in DirRole::all() {
+                            ui.selectable_value(&mut dir.role, role, role.name());
+                        }
+                    });
+
+                    let path_text = dir.path.display().to_string();
+                    if dir.enabled {
+                        ui.monospace(path_text);
+                    } else {
+                        ui.add(egui::Label::new(egui::RichText::new(path_text).monospace().weak()));
+                    }
+
+                    ui.horizontal(|ui| {
+                        if ui.add_enabled(index > 0, egui::Button::new("⬆")).clicked() {
+                            edit = Some(DirectoryEdit::MoveUp(index));
+                        }
+                        if ui.add_enabled(index + 1 < count, egui::Button::new("⬇")).clicked() {
+                            edit = Some(DirectoryEdit::MoveDown(index));
+                        }
+                        if ui.button("📂").on_hover_text("Change path").clicked() {
+                            edit = Some(DirectoryEdit::Browse(index));
+                        }
+                        if ui.button("🗑").on_hover_text("Remove").clicked() {
+                            edit = Some(DirectoryEdit::Remove(index));
+                        }
+                    });
+                    ui.end_row();
+                }
+            });
+        });
+
+        ui.separator();
         ui.horizontal(|ui| {
-            if ui.button("Save").clicked() {
-                should_close = true;
+            ui.label("Add:");
+            egui::ComboBox::from_id_salt("new_directory_role")
+            .selected_text(app.new_directory_role.name())
+            .show_ui(ui, |ui| {
+                for role in DirRole::all() {
+                    ui.selectable_value(&mut app.new_directory_role, role, role.name());
+                }
+            });
+            if ui.button("➕ Add directory...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    let role = app.new_directory_role;
+                    if !app.directories_draft.iter().any(|d| d.path == path && d.role == role) {
+                        app.directories_draft.push(ManagedDir { path, role, enabled: true });
+                    }
+                }
             }
+        });
 
-            if ui.button("Cancel").clicked() {
-                should_close = true;
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("✔ Apply").clicked() {
+                apply = true;
             }
-
-            if ui.button("Reset to Defaults").clicked() {
-                *video_settings = VideoSettings::default();
+            if ui.button("Cancel").clicked() {
+                cancel = true;
             }
         });
     });
 
-    // Handle closing after the window is shown
-    if should_close {
-        *show_video_settings = false;
+    match edit {
+        Some(DirectoryEdit::MoveUp(index)) => app.directories_draft.swap(index - 1, index),
+        Some(DirectoryEdit::MoveDown(index)) => app.directories_draft.swap(index, index + 1),
+        Some(DirectoryEdit::Browse(index)) => {
+            let current = app.directories_draft[index].path.clone();
+            if let Some(path) = rfd::FileDialog::new().set_directory(&current).pick_folder() {
+                app.directories_draft[index].path = path;
+            }
+        }
+        Some(DirectoryEdit::Remove(index)) => {
+            app.directories_draft.remove(index);
+        }
+        None => {}
+    }
+
+    if apply {
+        app.apply_directories();
+    }
+    if apply || cancel || !open {
+        app.directories_draft.clear();
+        app.show_directories_dialog = false;
     }
 }
 
-fn show_close_dialog(app: &mut MyApp, ctx: &egui::Context) {
-    if app.show_close_dialog {
-        egui::Window::new("Confirm Exit")
-        .collapsible(false)
-        .resizable(false)
-        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-        .show(ctx, |ui| {
-            ui.vertical_centered(|ui| {
-                ui.heading("What would you like to do?");
-                ui.add_space(20.0);
+/// Edit a list of glob patterns as one `;`-separated line. The pieces are kept as typed
+/// until the settings are applied, so separators and spaces survive mid-edit.
+fn pattern_field(ui: &mut egui::Ui, patterns: &mut Vec<String>, hint: &str) {
+    let mut text = patterns.join(";");
+    let edit = egui::TextEdit::singleline(&mut text).hint_text(hint).desired_width(160.0);
+    if ui.add(edit).changed() {
+        *patterns = text.split(';').map(String::from).collect();
+    }
+}
 
-                ui.horizontal(|ui| {
-                    if ui.button("🚪 Quit Application").clicked() {
-                        app.save_config();
-                        app.pending_close = true;
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Close);
-                    }
+fn show_scan_settings_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_scan_settings_dialog {
+        return;
+    }
 
-                    ui.add_space(10.0);
+    let mut open = true;
+    let mut apply = false;
+    let mut cancel = false;
 
-                    if ui.button("🔽 Minimize to Taskbar").clicked() {
-                        app.show_close_dialog = false;
-                        ctx.send_viewport_cmd(egui::ViewportCommand::Minimized(true));
-                    }
+    let mut dirs: Vec<PathBuf> = Vec::new();
+    for dir in app.config.rom_dirs.iter().chain(app.config.extra_rom_dirs.iter()) {
+        if !dirs.contains(dir) {
+            dirs.push(dir.clone());
+        }
+    }
 
-                    ui.add_space(10.0);
+    egui::Window::new("ROM Directory Scan Settings")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(700.0)
+    .show(ctx, |ui| {
+        ui.label("Subfolders down to the chosen depth are scanned and added to the rompath.");
+        ui.label("Patterns match a subfolder's path inside the directory, separated by ';' (e.g. Capcom/*; */Prototypes).");
+        ui.separator();
 
-                    if ui.button("❌ Cancel").clicked() {
-                        app.show_close_dialog = false;
-                    }
+        if dirs.is_empty() {
+            ui.label("No ROM directories configured.");
+        }
+
+        egui::Grid::new("scan_settings_grid")
+        .num_columns(4)
+        .striped(true)
+        .spacing([10.0, 4.0])
+        .show(ui, |ui| {
+            ui.strong("Directory");
+            ui.strong("Depth");
+            ui.strong("Include");
+            ui.strong("Exclude");
+            ui.end_row();
+
+            for dir in &dirs {
+                let settings = app.scan_settings_draft.entry(dir.clone()).or_default();
+                let label = ui.monospace(dir.display().to_string());
+                if !dir.is_dir() {
+                    label.on_hover_text("Directory not found");
+                }
+                ui.add(egui::DragValue::new(&mut settings.max_depth).range(0..=8))
+                .on_hover_text("0 scans only the directory itself");
+                ui.add_enabled_ui(settings.max_depth > 0, |ui| {
+                    pattern_field(ui, &mut settings.include, "all subfolders");
                 });
-            });
+                ui.add_enabled_ui(settings.max_depth > 0, |ui| {
+                    pattern_field(ui, &mut settings.exclude, "none");
+                });
+                ui.end_row();
+            }
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            if ui.button("✔ Apply and rescan").clicked() {
+                apply = true;
+            }
+            if ui.button("Cancel").clicked() {
+                cancel = true;
+            }
         });
+    });
+
+    if apply {
+        app.apply_scan_settings();
+    }
+    if apply || cancel || !open {
+        app.scan_settings_draft.clear();
+        app.show_scan_settings_dialog = false;
     }
 }
 
-fn show_audit_progress_dialog(app: &mut MyApp, ctx: &egui::Context) {
-    if app.audit_in_progress {
-        egui::Window::new("ROM Audit Progress")
-        .collapsible(false)
-        .resizable(false)
-        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
-        .show(ctx, |ui| {
-            ui.heading("Auditing ROM Collection");
-            ui.separator();
+/// A quarantine request from the hygiene dialog, applied once the report is no longer borrowed
+enum HygieneAction {
+    Path(PathBuf),
+    Members(PathBuf, Vec<String>),
+}
 
-            // Show current progress text
-            ui.label(&app.audit_progress);
+fn show_hygiene_dialog(app: &mut MyApp, ctx: &egui::Context) {
+    if !app.show_hygiene_dialog {
+        return;
+    }
 
-            // Add a spinning indicator with more visual prominence
-            ui.add_space(10.0);
-            ui.horizontal(|ui| {
+    let mut open = true;
+    let scanning = app.hygiene_tx.is_some();
+    let mut actions: Vec<HygieneAction> = Vec::new();
+
+    egui::Window::new("ROM Folder Check")
+    .open(&mut open)
+    .collapsible(false)
+    .resizable(true)
+    .default_width(650.0)
+    .show(ctx, |ui| {
+        ui.horizontal(|ui| {
+            let can_scan = !scanning && !app.game_metadata.is_empty() && !app.config.rom_dirs.is_empty();
+            if ui.add_enabled(can_scan, egui::Button::new("🔍 Scan")).clicked() {
+                app.start_hygiene_scan();
+            }
+            if scanning {
                 ui.spinner();
-                ui.label("Please wait...");
+            }
+            ui.label(&app.hygiene_progress);
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Quarantine folder:");
+            ui.monospace(app.quarantine_dir.display().to_string());
+            if ui.button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    app.quarantine_dir = path;
+                }
+            }
+        });
+        ui.separator();
+
+        let Some(report) = &app.hygiene_report else {
+            ui.label("Scan to list files the ROM scanner ignores.");
+            return;
+        };
+        if report.is_clean() {
+            ui.label("✅ Nothing to clean up.");
+            return;
+        }
+
+        egui::ScrollArea::vertical().max_height(450.0).show(ui, |ui| {
+            egui::CollapsingHeader::new(format!("Unknown sets ({})", report.unknown.len()))
+            .id_salt("hygiene_unknown")
+            .show(ui, |ui| {
+                ui.label("Archives and folders named after no machine in the current MAME.");
+                if !report.unknown.is_empty() && ui.button("Quarantine all").clicked() {
+                    actions.extend(report.unknown.iter().cloned().map(HygieneAction::Path));
+                }
+                for path in &report.unknown {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Quarantine").clicked() {
+                            actions.push(HygieneAction::Path(path.clone()));
+                        }
+                        ui.monospace(path.display().to_string());
+                    });
+                }
             });
 
-            // Add a progress bar (if you can estimate progress)
-            let progress = if app.audit_progress.contains("complete") {
-                1.0
-            } else if app.audit_progress.contains("scanning") {
-                0.5
-            } else {
-                0.1
-            };
-            ui.add_space(10.0);
-            ui.add(egui::ProgressBar::new(progress).show_percentage());
+            egui::CollapsingHeader::new(format!("Stray files ({})", report.stray.len()))
+            .id_salt("hygiene_stray")
+            .show(ui, |ui| {
+                ui.label("Loose files that are neither ROM archives nor set folders.");
+                if !report.stray.is_empty() && ui.button("Quarantine all").clicked() {
+                    actions.extend(report.stray.iter().cloned().map(HygieneAction::Path));
+                }
+                for path in &report.stray {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Quarantine").clicked() {
+                            actions.push(HygieneAction::Path(path.clone()));
+                        }
+                        ui.monospace(path.display().to_string());
+                    });
+                }
+            });
 
-            ui.add_space(10.0);
-            ui.label("This may take several minutes for large collections...");
-            ui.label("MAME is scanning inside all ROM files.");
+            egui::CollapsingHeader::new(format!("Duplicated sets ({})", report.duplicates.len()))
+            .id_salt("hygiene_duplicates")
+            .show(ui, |ui| {
+                ui.label("Sets in more than one place. MAME uses the first copy in rompath order.");
+                if !report.duplicates.is_empty() && ui.button("Quarantine all unused copies").clicked() {
+                    actions.extend(report.duplicates.iter()
+                    .flat_map(|d| d.copies.iter().skip(1).cloned())
+                    .map(HygieneAction::Path));
+                }
+                for duplicate in &report.duplicates {
+                    ui.strong(&duplicate.name);
+                    for (i, copy) in duplicate.copies.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if i == 0 {
+                                ui.colored_label(egui::Color32::from_rgb(100, 200, 100), "used");
+                            } else if ui.small_button("Quarantine").clicked() {
+                                actions.push(HygieneAction::Path(copy.clone()));
+                            }
+                            ui.monospace(copy.display().to_string());
+                        });
+                    }
+                }
+            });
 
-            // Add a cancel button
-            ui.add_space(10.0);
-            if ui.button("❌ Cancel Audit").clicked() {
-                app.audit_in_progress = false;
-                app.audit_tx = None;
-                app.audit_start_time = None;
-            }
+            egui::CollapsingHeader::new(format!("Unneeded files in sets ({})", report.unneeded.len()))
+            .id_salt("hygiene_unneeded")
+            .show(ui, |ui| {
+                ui.label("Files inside a set that neither it, its clones nor its devices use.");
+                if !report.unneeded.is_empty() && ui.button("Quarantine all").clicked() {
+                    actions.extend(report.unneeded.iter().map(|u| HygieneAction::Members(u.container.clone(), u.files.clone())));
+                }
+                for unneeded in &report.unneeded {
+                    ui.horizontal(|ui| {
+                        if ui.small_button("Quarantine").clicked() {
+                            actions.push(HygieneAction::Members(unneeded.container.clone(), unneeded.files.clone()));
+                        }
+                        ui.monospace(unneeded.container.display().to_string());
+                    });
+                    ui.label(format!("    {}", unneeded.files.join(", ")));
+                }
+            });
         });
+    });
 
-        // Check for audit updates
-        if let Some(rx) = &app.audit_tx {
-            if let Ok(msg) = rx.try_recv() {
-                if msg == "AUDIT_COMPLETE" {
-                    app.audit_in_progress = false;
-                    app.audit_tx = None;
-                    app.audit_start_time = None;
-                    app.config.use_mame_audit = true;
+    for action in actions {
+        apply_hygiene_action(app, action);
+    }
 
-                    // Update per-MAME audit time
-                    if let Some(current_mame) = app.config.mame_executables.get(app.config.selected_mame_index) {
-                        let mame_id = app.get_mame_identifier(current_mame);
-                        let audit_time = chrono::Local::now().format("%Y-%m-%d %H:%M").to_string();
-                        app.config.mame_audit_times.insert(mame_id, audit_time);
-                    }
+    if !open {
+        app.show_hygiene_dialog = false;
+    }
+}
 
-                    app.save_config();
-                    // Reload ROMs with audit data
-                    app.reload_roms();
-                } else if msg == "AUDIT_FAILED" {
-                    app.audit_in_progress = false;
-                    app.audit_tx = None;
-                    app.audit_start_time = None;
-                } else {
-                    app.audit_progress = msg;
+fn apply_hygiene_action(app: &mut MyApp, action: HygieneAction) {
+    let quarantine_dir = app.quarantine_dir.clone();
+    let result = match &action {
+        HygieneAction::Path(path) => quarantine_path(path, &quarantine_dir).map_err(|e| e.into()),
+        HygieneAction::Members(container, files) => quarantine_members(container, files, &quarantine_dir),
+    };
+
+    match result {
+        Ok(dest) => {
+            println!("Quarantined to {:?}", dest);
+            app.hygiene_progress = format!("Moved to {}", dest.display());
+            if let Some(report) = app.hygiene_report.as_mut() {
+                match &action {
+                    HygieneAction::Path(path) => {
+                        report.unknown.retain(|p| p != path);
+                        report.stray.retain(|p| p != path);
+                        for duplicate in report.duplicates.iter_mut() {
+                            duplicate.copies.retain(|p| p != path);
+                        }
+                        report.duplicates.retain(|d| d.copies.len() > 1);
+                        report.unneeded.retain(|u| &u.container != path);
+                    }
+                    HygieneAction::Members(container, _) => report.unneeded.retain(|u| &u.container != container),
                 }
             }
         }
+        Err(e) => {
+            eprintln!("Failed to quarantine: {}", e);
+            app.hygiene_progress = format!("Failed to quarantine: {}", e);
+        }
     }
 }
 
@@ -356,33 +1906,27 @@ fn show_mame_manager_dialog(app: &mut MyApp, ctx: &egui::Context) {
                         let version = get_mame_version(path_str);
                         let name = format!("MAME {}", version.split_whitespace().nth(1).unwrap_or("Unknown"));
 
-                        // Load metadata for this MAME
-                        let metadata = load_mame_metadata_parallel_with_exec(path_str);
-                        let total = metadata.iter()
-                        .filter(|(_, meta)| !meta.is_device && !meta.is_bios)
-                        .count();
-                        let working = metadata.iter()
-                        .filter(|(_, meta)| !meta.is_device && !meta.is_bios && !meta.is_mechanical && !meta.runnable)
-                        .count();
-
                         app.config.mame_executables.push(MameExecutable {
                             name,
                             path: path_str.to_string(),
-                                                         version,
-                                                         total_games: total,
-                                                         working_games: working,
+                            version: version.clone(),
+                            total_games: 0,
+                            working_games: 0,
+                            default_args: Vec::new(),
+                            data_layout: DataLayout::default(),
+                            ini_path: None,
                         });
+                        let new_idx = app.config.mame_executables.len() - 1;
+                        assign_data_roots(&mut app.config.mame_executables, &get_executables_dir());
 
-                        // If this is the first MAME, load its metadata
-                        if app.config.mame_executables.len() == 1 {
+                        // If this is the first MAME, make it the active one
+                        if new_idx == 0 {
                             app.config.selected_mame_index = 0;
-                            app.game_metadata = metadata;
-                            app.mame_titles = app.game_metadata.iter().map(|(k, v)| (k.clone(), v.description.clone())).collect();
-                            app.total_games_count = total;
-                            app.working_games_count = working;
-                            app.mame_version = app.config.mame_executables[0].version.clone();
                         }
 
+                        // Load metadata in the background (this also seeds the metadata cache)
+                        app.load_mame_data(new_idx, false);
+
                         app.save_config();
                     }
                 }
@@ -401,11 +1945,13 @@ fn show_mame_manager_dialog(app: &mut MyApp, ctx: &egui::Context) {
                 let mut to_remove = None;
                 let mut to_select = None;
 
-                for (idx, mame) in app.config.mame_executables.iter().enumerate() {
+                let mut args_changed = false;
+                let selected_index = app.config.selected_mame_index;
+                for (idx, mame) in app.config.mame_executables.iter_mut().enumerate() {
                     ui.group(|ui| {
                         ui.horizontal(|ui| {
                             // Radio button for default selection
-                            if ui.radio(app.config.selected_mame_index == idx, &mame.name).clicked() {
+                            if ui.radio(selected_index == idx, &mame.name).clicked() {
                                 to_select = Some(idx);
                             }
 
@@ -415,6 +1961,16 @@ fn show_mame_manager_dialog(app: &mut MyApp, ctx: &egui::Context) {
                                 ui.label(format!("Path: {}", mame.path));
                                 ui.label(format!("Version: {}", mame.version));
                                 ui.label(format!("Games: {} total, {} working", mame.total_games, mame.working_games));
+                                ui.horizontal(|ui| {
+                                    ui.label("Default arguments:");
+                                    let id_salt = format!("default_args_{}", idx);
+                                    let (response, parsed) = args_field(ui, &id_salt, &mut mame.default_args, "e.g. -skip_gameinfo", 250.0);
+                                    // Half-typed arguments aren't saved; the last ones that parsed stay in use
+                                    args_changed |= response
+                                    .on_hover_text("Used for every launch with this executable; video settings and presets take precedence")
+                                    .lost_focus() && parsed;
+                                });
+                                args_changed |= data_layout_editor(ui, idx, mame);
                             });
 
                             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
@@ -427,24 +1983,42 @@ fn show_mame_manager_dialog(app: &mut MyApp, ctx: &egui::Context) {
                     ui.add_space(5.0);
                 }
 
+                if args_changed {
+                    // An own folder put back to the default is fixed again from the current name
+                    assign_data_roots(&mut app.config.mame_executables, &get_executables_dir());
+                    app.save_config();
+                }
+
                 // Handle removal
                 if let Some(idx) = to_remove {
                     // Clean up the audit file for this MAME
                     app.cleanup_audit_file(idx);
 
+                    // Stop a metadata load that is still running for it
+                    if app.metadata_loading_path.as_deref() == Some(app.config.mame_executables[idx].path.as_str()) {
+                        app.metadata_cancel.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+
                     app.config.mame_executables.remove(idx);
                     if app.config.selected_mame_index >= app.config.mame_executables.len() && !app.config.mame_executables.is_empty() {
                         app.config.selected_mame_index = app.config.mame_executables.len() - 1;
                     }
+                    // Launch profiles keep pointing at the same executables
+                    for profile in app.config.game_profiles.values_mut() {
+                        profile.mame_index = match profile.mame_index {
+                            Some(i) if i == idx => None,
+                            Some(i) if i > idx => Some(i - 1),
+                            other => other,
+                        };
+                    }
+                    app.config.game_profiles.retain(|_, p| !p.is_empty());
                     app.save_config();
                 }
 
                 // Handle selection change
                 if let Some(idx) = to_select {
                     app.config.selected_mame_index = idx;
-                    let mame_path = app.config.mame_executables[idx].path.clone();
-                    app.load_mame_data(&mame_path);
-                    app.reload_roms();
+                    app.load_mame_data(idx, false);
                     app.save_config();
                 }
             }
@@ -459,6 +2033,90 @@ fn show_mame_manager_dialog(app: &mut MyApp, ctx: &egui::Context) {
     }
 }
 
+/// Where a data folder goes, as picked in the data folders editor
+#[derive(Clone, Copy, PartialEq)]
+enum FolderPlacement {
+    Mame,
+    Shared,
+    Isolated,
+}
+
+/// Where an executable keeps NVRAM, configuration, save states and the like. Returns
+/// whether the layout was changed.
+fn data_layout_editor(ui: &mut egui::Ui, idx: usize, mame: &mut MameExecutable) -> bool {
+    let mut changed = false;
+
+    egui::CollapsingHeader::new("Data folders").id_salt(("data_layout", idx)).show(ui, |ui| {
+        ui.label("MAME's own folders are whatever its ini files say. Shared folders are used by every executable; isolated ones only by this one.");
+        ui.horizontal(|ui| {
+            ui.label(format!("Own folder: {}", get_executable_data_dir(mame).display()));
+            if ui.small_button("Browse...").clicked() {
+                if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                    mame.data_layout.root = Some(path);
+                    changed = true;
+                }
+            }
+            if ui.small_button("Default").on_hover_text("A folder named after the executable").clicked() {
+                mame.data_layout.root = None;
+                changed = true;
+            }
+        });
+
+        egui::Grid::new(("data_layout_grid", idx)).num_columns(3).spacing([10.0, 2.0]).show(ui, |ui| {
+            for folder in DataFolder::all() {
+                ui.label(folder.name());
+                let layout = &mut mame.data_layout;
+                let mut placement = if layout.isolated.contains(&folder) {
+                    FolderPlacement::Isolated
+                } else if layout.shared.contains(&folder) {
+                    FolderPlacement::Shared
+                } else {
+                    FolderPlacement::Mame
+                };
+                ui.horizontal(|ui| {
+                    changed |= ui.radio_value(&mut placement, FolderPlacement::Mame, "MAME's own").changed();
+                    changed |= ui.radio_value(&mut placement, FolderPlacement::Shared, "Shared").changed();
+                    changed |= ui.radio_value(&mut placement, FolderPlacement::Isolated, "Isolated").changed();
+                });
+                layout.shared.retain(|f| *f != folder);
+                layout.isolated.retain(|f| *f != folder);
+                match placement {
+                    FolderPlacement::Mame => {}
+                    FolderPlacement::Shared => layout.shared.push(folder),
+                    FolderPlacement::Isolated => layout.isolated.push(folder),
+                }
+                let shown = match get_data_folder(mame, folder) {
+                    Some(path) => path.display().to_string(),
+                    None => "(not passed)".to_string(),
+                };
+                ui.label(egui::RichText::new(shown).weak().monospace())
+                .on_hover_text(format!("-{}", folder.option()));
+                ui.end_row();
+            }
+        });
+
+        ui.horizontal(|ui| {
+            if ui.button("MAME's own for all").clicked() {
+                mame.data_layout.shared.clear();
+                mame.data_layout.isolated.clear();
+                changed = true;
+            }
+            if ui.button("Share all").clicked() {
+                mame.data_layout.shared = DataFolder::all().to_vec();
+                mame.data_layout.isolated.clear();
+                changed = true;
+            }
+            if ui.button("Isolate all").clicked() {
+                mame.data_layout.shared.clear();
+                mame.data_layout.isolated = DataFolder::all().to_vec();
+                changed = true;
+            }
+        });
+    });
+
+    changed
+}
+
 fn show_about_dialog(app: &mut MyApp, ctx: &egui::Context) {
     if app.show_about {
         egui::Window::new("About")
@@ -551,7 +2209,7 @@ fn show_rom_diagnostics_window(app: &mut MyApp, ctx: &egui::Context) {
             ui.separator();
             ui.horizontal(|ui| {
                 if ui.button("Run Audit Now").clicked() {
-                    app.run_mame_audit();
+                    app.run_rom_audit();
                     app.show_rom_diagnostics = false;
                 }
                 if ui.button("Close").clicked() {
@@ -666,7 +2324,7 @@ fn show_rom_set_info_dialog(app: &mut MyApp, ctx: &egui::Context) {
                                              "❌ No audit file found - Run ROM Audit now!"
                             );
                             if ui.button("🔍 Run ROM Audit").clicked() {
-                                app.run_mame_audit();
+                                app.run_rom_audit();
                                 app.show_rom_set_info = false;
                             }
                         }
@@ -732,7 +2390,7 @@ fn show_rom_set_info_dialog(app: &mut MyApp, ctx: &egui::Context) {
 
                 if matches!(rom_set_type, RomSetType::Merged) && !app.has_audit_file() {
                     if ui.button("🔍 Run Audit Now").clicked() {
-                        app.run_mame_audit();
+                        app.run_rom_audit();
                         app.show_rom_set_info = false;
                     }
                 }
@@ -750,22 +2408,17 @@ fn show_context_menu(app: &mut MyApp, ctx: &egui::Context) {
                 ui.label("Launch with:");
                 ui.separator();
 
-                if let Some(rom) = &app.context_menu_rom {
+                if let Some(rom) = app.context_menu_rom.clone() {
+                    let mut launch_with = None;
                     for (idx, mame) in app.config.mame_executables.iter().enumerate() {
                         if ui.button(&mame.name).clicked() {
-                            crate::mame_utils::launch_rom_with_mame(
-                                rom,
-                                &app.config.rom_dirs,
-                                &app.config.extra_rom_dirs,
-                                &mame.path,
-                                &app.config.graphics_config,
-                                &app.config.video_settings  // ADD THIS PARAMETER
-                            );
-                            app.config.game_preferred_mame.insert(rom.clone(), idx);
-                            app.save_config();
-                            app.show_context_menu = false;
+                            launch_with = Some(idx);
                         }
                     }
+                    if let Some(idx) = launch_with {
+                        app.launch_rom_with(&rom, idx);
+                        app.show_context_menu = false;
+                    }
 
                     ui.separator();
 
@@ -1,6 +1,6 @@
 use eframe::egui;
 use crate::app::MyApp;
-use crate::models::{FilterSettings, Theme};
+use crate::models::Theme;
 use crate::config::get_mame_data_dir;
 use std::process::Command;
 
@@ -14,6 +14,11 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
                     ui.close_menu();
                 }
 
+                if ui.button("DAT Collections...").clicked() {
+                    app.show_collections_dialog = true;
+                    ui.close_menu();
+                }
+
                 ui.separator();
 
                 if ui.button("Save Settings").clicked() {
@@ -32,23 +37,14 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
             // Options Menu
             ui.menu_button("Options", |ui| {
                 ui.menu_button("Directories", |ui| {
-                    if ui.button("Add ROM Path...").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                            app.config.rom_dirs.push(path.clone());
-                            app.config.extra_rom_dirs.push(path); // Same path for ROMs, CHDs, and BIOS
-                            app.save_config();
-
-                            // Reload ROMs if we have at least one MAME configured
-                            app.reload_roms();
-                        }
+                    if ui.button("Manage Directories...").clicked() {
+                        app.open_directories_dialog();
                         ui.close_menu();
                     }
 
-                    if ui.button("Add Extras Path...").clicked() {
-                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
-                            app.config.extra_asset_dirs.push(path);
-                            app.save_config();
-                        }
+                    if ui.button("Scan Settings...").clicked() {
+                        app.scan_settings_draft = app.config.dir_scan_settings.clone();
+                        app.show_scan_settings_dialog = true;
                         ui.close_menu();
                     }
 
@@ -65,22 +61,6 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
                         let _ = Command::new("open").arg(&mame_data_dir).spawn();
                         ui.close_menu();
                     }
-
-                    ui.separator();
-
-                    if ui.button("Clear All Paths").clicked() {
-                        app.config.rom_dirs.clear();
-                        app.config.extra_rom_dirs.clear();
-                        app.config.extra_asset_dirs.clear();
-                        app.roms.clear();
-                        app.config.selected_rom = None;
-                        app.screenshot = None;
-                        app.config.filter_settings = FilterSettings::default();
-                        app.all_manufacturers.clear();
-                        app.art_texture = None;
-                        app.save_config();
-                        ui.close_menu();
-                    }
                 });
 
                 ui.separator();
@@ -91,6 +71,16 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
                     ui.close_menu();
                 }
 
+                if ui.add_enabled(!app.config.mame_executables.is_empty(), egui::Button::new("⚙ MAME Configuration...")).clicked() {
+                    app.open_mame_config();
+                    ui.close_menu();
+                }
+
+                if ui.button("📝 MAME ini Files...").clicked() {
+                    app.open_ini_preview();
+                    ui.close_menu();
+                }
+
                 ui.separator();
 
                 ui.menu_button("🎨 Theme", |ui| {
@@ -124,8 +114,15 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
                     }
 
                     if ui.button("🔍 Run ROM Audit").clicked() {
-                        app.run_mame_audit();
+                        app.run_rom_audit();
+                        ui.close_menu();
+                    }
+
+                    if ui.add_enabled(!app.audit_results.is_empty(), egui::Button::new("📤 Export fix-DAT..."))
+                        .on_hover_text("Save the missing and bad files from the last audit as a Logiqx DAT")
+                        .clicked() {
                         ui.close_menu();
+                        app.export_fix_dat();
                     }
 
                     ui.separator();
@@ -149,6 +146,30 @@ pub fn show_menu_bar(app: &mut MyApp, ctx: &egui::Context) {
                 ui.separator();
 
                 ui.menu_button("Maintenance", |ui| {
+                    if ui.button("🔄 Rebuild metadata cache")
+                        .on_hover_text("Re-run MAME -listxml for the active MAME and refresh its cache")
+                        .clicked() {
+                        app.rebuild_metadata_cache();
+                        ui.close_menu();
+                    }
+
+                    if ui.button("🧹 Check ROM folders...")
+                        .on_hover_text("Find unknown, duplicated and unneeded files in the ROM directories")
+                        .clicked() {
+                        app.show_hygiene_dialog = true;
+                        ui.close_menu();
+                    }
+
+                    if ui.button("🧱 Rebuild ROM sets...")
+                        .on_hover_text("Write the ROM directories out as merged, split or non-merged sets")
+                        .clicked() {
+                        if app.rebuild_options.source_dirs.is_empty() {
+                            app.rebuild_options.source_dirs = app.rompath_dirs();
+                        }
+                        app.show_rebuild_dialog = true;
+                        ui.close_menu();
+                    }
+
                     if ui.button("Clean up orphaned audit files").clicked() {
                         app.cleanup_orphaned_audit_files();
                         ui.close_menu();
@@ -1,6 +1,6 @@
 use eframe::egui;
 use crate::app::MyApp;
-use crate::models::{FilterSettings, StatusFilter};
+use crate::models::{FilterSettings, StatusFilter, AuditFilter};
 
 pub fn show_filters(app: &mut MyApp, ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
@@ -193,6 +193,16 @@ fn show_status_filter(app: &mut MyApp, ui: &mut egui::Ui) -> bool {
         changed |= ui.radio_value(&mut app.config.filter_settings.status_filter, StatusFilter::WorkingOnly, "✅ Working").changed();
         changed |= ui.radio_value(&mut app.config.filter_settings.status_filter, StatusFilter::ImperfectOnly, "⚠️ Imperfect").changed();
         changed |= ui.radio_value(&mut app.config.filter_settings.status_filter, StatusFilter::NotWorkingOnly, "⛔ Not Working").changed();
+
+        ui.separator();
+        ui.label("Audit:");
+        egui::ComboBox::from_id_salt("audit_filter_combo")
+        .selected_text(app.config.filter_settings.audit_filter.label())
+        .show_ui(ui, |ui| {
+            for filter in AuditFilter::ALL {
+                changed |= ui.selectable_value(&mut app.config.filter_settings.audit_filter, filter, filter.label()).changed();
+            }
+        });
     });
     changed
 }
@@ -1,6 +1,5 @@
 use eframe::egui;
 use crate::app::MyApp;
-use crate::mame_utils::launch_rom_with_mame_tracked;
 
 pub fn show_launch_button(app: &mut MyApp, ui: &mut egui::Ui) {
     ui.horizontal(|ui| {
@@ -19,30 +18,10 @@ pub fn show_launch_button(app: &mut MyApp, ui: &mut egui::Ui) {
 }
 
 fn launch_selected_rom(app: &mut MyApp) {
-    if let Some(rom_name) = &app.config.selected_rom {
+    if let Some(rom_name) = app.config.selected_rom.clone() {
         if !app.config.mame_executables.is_empty() {
-            let mame_idx = app.config.game_preferred_mame.get(rom_name)
-                .copied()
-                .unwrap_or(app.config.selected_mame_index);
-
-            if let Some(mame) = app.config.mame_executables.get(mame_idx) {
-                match launch_rom_with_mame_tracked(
-                    rom_name,
-                    &app.config.rom_dirs,
-                    &app.config.extra_rom_dirs,
-                    &mame.path,
-                    &app.config.graphics_config,
-                    &app.config.video_settings  // ADD THIS LINE
-                ) {
-                    Ok(child) => {
-                        app.running_games.insert(rom_name.clone(), (child, std::time::Instant::now()));
-                        println!("Started tracking game: {}", rom_name);
-                    }
-                    Err(e) => {
-                        println!("Failed to launch game: {}", e);
-                    }
-                }
-            }
+            let mame_idx = app.preferred_mame_index(&rom_name);
+            app.launch_rom(&rom_name, mame_idx);
         }
     }
 }
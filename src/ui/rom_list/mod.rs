@@ -24,6 +24,11 @@ pub fn show_rom_list(app: &mut MyApp, ctx: &egui::Context) {
         // Show configured paths
         status::show_path_status(app, ui);
 
+        // Custom DAT collections are browsed in place of the MAME sets
+        if !app.config.custom_collections.is_empty() {
+            show_collection_selector(app, ui);
+        }
+
         // Warning for merged ROMs without audit
         if app.config.use_mame_audit && app.roms.is_empty() && !app.config.rom_dirs.is_empty() {
             ui.colored_label(egui::Color32::from_rgb(255, 200, 100),
@@ -47,7 +52,7 @@ pub fn show_rom_list(app: &mut MyApp, ctx: &egui::Context) {
 
         // Check for loaded ROMs
         if let Some(rx) = &app.roms_tx {
-            if let Ok(loaded_roms) = rx.try_recv() {
+            if let Ok((loaded_roms, directory_sets)) = rx.try_recv() {
                 println!("\n=== ROMS LOADED ===");
                 println!("Total ROMs loaded: {}", loaded_roms.len());
 
@@ -58,6 +63,7 @@ pub fn show_rom_list(app: &mut MyApp, ctx: &egui::Context) {
                 }
 
                 app.roms = loaded_roms;
+                app.directory_sets = directory_sets;
                 app.roms_loading = false;
                 app.roms_tx = None;
             }
@@ -67,3 +73,36 @@ pub fn show_rom_list(app: &mut MyApp, ctx: &egui::Context) {
         launch::show_launch_button(app, ui);
     });
 }
+
+fn show_collection_selector(app: &mut MyApp, ui: &mut egui::Ui) {
+    let current = app.collection.as_ref().map(|c| c.index);
+    let selected_text = current
+    .and_then(|index| app.config.custom_collections.get(index))
+    .map(|c| c.name.clone())
+    .unwrap_or_else(|| "MAME".to_string());
+
+    ui.horizontal(|ui| {
+        ui.label("Collection:");
+        let mut choice = current;
+        egui::ComboBox::from_id_salt("collection_selector")
+        .selected_text(selected_text)
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut choice, None, "MAME");
+            for (index, collection) in app.config.custom_collections.iter().enumerate() {
+                ui.selectable_value(&mut choice, Some(index), &collection.name);
+            }
+        });
+
+        if choice != current {
+            match choice {
+                Some(index) => app.open_collection(index, false),
+                None => app.close_collection(),
+            }
+        }
+
+        if app.collection_tx.is_some() {
+            ui.spinner();
+            ui.label(&app.collection_progress);
+        }
+    });
+}
@@ -65,7 +65,7 @@ fn build_filtered_rom_list(app: &mut MyApp) -> (Vec<SortableRom>, HashMap<String
     // Apply sorting
     sort_rom_list(
         &mut filtered_roms,
-        &app.game_metadata,
+        app.list_metadata(),
         &app.config.game_stats,
         app.config.sort_column,
         app.config.sort_direction
@@ -83,10 +83,10 @@ fn build_parent_clone_maps(app: &MyApp) -> (
     let mut clone_to_parent: HashMap<String, String> = HashMap::new();
     let mut all_roms_map: HashMap<String, String> = HashMap::new();
 
-    for (display, rom_name) in &app.roms {
+    for (display, rom_name) in app.list_roms() {
         all_roms_map.insert(rom_name.clone(), display.clone());
 
-        if let Some(metadata) = app.game_metadata.get(rom_name) {
+        if let Some(metadata) = app.list_metadata().get(rom_name) {
             if let Some(parent) = &metadata.parent {
                 clone_to_parent.insert(rom_name.clone(), parent.clone());
                 parent_to_clones.entry(parent.clone())
@@ -108,11 +108,11 @@ fn find_virtual_parents(
 
     for (parent, clones) in parent_to_clones {
         if !all_roms_map.contains_key(parent) && !clones.is_empty() {
-            let parent_title = app.game_metadata.get(parent)
+            let parent_title = app.list_metadata().get(parent)
             .map(|m| m.description.clone())
             .unwrap_or_else(|| {
                 if let Some((_, clone_name)) = clones.first() {
-                    if let Some(clone_meta) = app.game_metadata.get(clone_name) {
+                    if let Some(clone_meta) = app.list_metadata().get(clone_name) {
                         return clone_meta.description.split('(').next()
                         .unwrap_or(parent).trim().to_string() + " (Parent ROM)";
                     }
@@ -141,7 +141,7 @@ fn build_display_list(
     let mut all_entries: Vec<(String, String, bool)> = Vec::new();
 
     // Add real ROMs
-    for (display, rom_name) in &app.roms {
+    for (display, rom_name) in app.list_roms() {
         let is_clone = clone_to_parent.contains_key(rom_name);
         all_entries.push((display.clone(), rom_name.clone(), is_clone));
     }
@@ -175,7 +175,7 @@ fn build_display_list(
 
         // Apply filters
         if !is_clone && !virtual_parents.contains_key(&rom_name) {
-            if !apply_rom_filters(&app.config.filter_settings, &app.game_metadata, &display, &rom_name, &app.config.favorite_games) {
+            if !apply_rom_filters(&app.config.filter_settings, app.list_metadata(), &display, &rom_name, &app.config.favorite_games, app.list_audit_results()) {
                 continue;
             }
         }
@@ -188,7 +188,7 @@ fn build_display_list(
         if has_clones && *app.expanded_parents.get(&rom_name).unwrap_or(&false) {
             if let Some(clones) = parent_to_clones.get(&rom_name) {
                 for (clone_display, clone_name) in clones {
-                    if !processed.contains(clone_name) && apply_rom_filters(&app.config.filter_settings, &app.game_metadata, clone_display, clone_name, &app.config.favorite_games) {
+                    if !processed.contains(clone_name) && apply_rom_filters(&app.config.filter_settings, app.list_metadata(), clone_display, clone_name, &app.config.favorite_games, app.list_audit_results()) {
                         display_list.push((clone_display.clone(), clone_name.clone(), true, false));
                         processed.insert(clone_name.clone());
                     }
@@ -234,7 +234,7 @@ fn log_parent_clone_analysis(
                              virtual_parents: &HashMap<String, String>
 ) {
     println!("\n=== Parent/Clone Analysis ===");
-    println!("Total ROMs in collection: {}", app.roms.len());
+    println!("Total ROMs in collection: {}", app.list_roms().len());
     println!("Total unique games with clones: {}", parent_to_clones.len());
     println!("Virtual parents created: {}", virtual_parents.len());
 
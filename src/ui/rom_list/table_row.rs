@@ -2,7 +2,6 @@ use eframe::egui;
 use std::collections::HashMap;
 use crate::app::MyApp;
 use crate::models::RomStatus;
-use crate::mame_utils::launch_rom_with_mame_tracked;
 use super::sorting::SortableRom;
 
 pub fn render_rom_row(
@@ -22,11 +21,12 @@ pub fn render_rom_row(
     draw_row_background(ui, row, row_height);
 
     // Get metadata
-    let metadata_cloned = app.game_metadata.get(filename).cloned();
+    let metadata_cloned = app.list_metadata().get(filename).cloned();
     let clean_title = get_clean_title(display_name, filename, virtual_parents, &metadata_cloned);
     let year = metadata_cloned.as_ref().map(|m| m.year.clone()).unwrap_or_default();
     let manuf = metadata_cloned.as_ref().map(|m| m.manufacturer.clone()).unwrap_or_default();
     let is_virtual = virtual_parents.contains_key(filename);
+    let is_folder_set = app.directory_sets.contains(filename);
 
     ui.horizontal(|ui| {
         ui.set_min_height(row_height);
@@ -62,7 +62,7 @@ pub fn render_rom_row(
         handle_title_interactions(app, response, filename, is_virtual, &metadata_cloned);
 
         // ROM name
-        render_rom_name(ui, filename, is_virtual, row_height);
+        render_rom_name(ui, filename, is_virtual, is_folder_set, row_height);
 
         // Year
         render_year(ui, &year, row_height);
@@ -261,27 +261,8 @@ fn handle_title_interactions(
     }
 
     if response.double_clicked() && !is_virtual && !app.config.mame_executables.is_empty() {
-        let mame_idx = app.config.game_preferred_mame.get(filename)
-        .copied()
-        .unwrap_or(app.config.selected_mame_index);
-        if let Some(mame) = app.config.mame_executables.get(mame_idx) {
-            match launch_rom_with_mame_tracked(
-                filename,
-                &app.config.rom_dirs,
-                &app.config.extra_rom_dirs,
-                &mame.path,
-                &app.config.graphics_config,
-                &app.config.video_settings  // ADD THIS LINE
-            ) {
-                Ok(child) => {
-                    app.running_games.insert(filename.to_string(), (child, std::time::Instant::now()));
-                    println!("Started tracking game: {}", filename);
-                }
-                Err(e) => {
-                    println!("Failed to launch game: {}", e);
-                }
-            }
-        }
+        let mame_idx = app.preferred_mame_index(filename);
+        app.launch_rom(filename, mame_idx);
     }
 
     // Clone metadata for the closures
@@ -347,52 +328,68 @@ fn show_rom_context_menu(app: &mut MyApp, ui: &mut egui::Ui, filename: &str) {
         ui.close_menu();
     }
 
+    if ui.button("📋 Audit details").clicked() {
+        app.audit_details_rom = Some(filename.to_string());
+        ui.close_menu();
+    }
+
+    if ui.add_enabled(!app.config.mame_executables.is_empty(), egui::Button::new("🖥 Show launch command")).clicked() {
+        app.show_launch_command(filename);
+        ui.close_menu();
+    }
+
+    if ui.button("⚙ Game settings...").clicked() {
+        app.open_game_settings(filename);
+        ui.close_menu();
+    }
+
+    if ui.button("📜 Session logs").clicked() {
+        app.open_session_logs(filename);
+        ui.close_menu();
+    }
+
     ui.separator();
 
     ui.label("Launch with:");
     ui.separator();
+    let mut launch_with = None;
     for (idx, mame) in app.config.mame_executables.iter().enumerate() {
         if ui.button(&mame.name).clicked() {
-            match launch_rom_with_mame_tracked(
-                filename,
-                &app.config.rom_dirs,
-                &app.config.extra_rom_dirs,
-                &mame.path,
-                &app.config.graphics_config,
-                &app.config.video_settings  // ADD THIS LINE
-            ) {
-                Ok(child) => {
-                    app.running_games.insert(filename.to_string(), (child, std::time::Instant::now()));
-                    app.config.game_preferred_mame.insert(filename.to_string(), idx);
-                    app.save_config();
-                    println!("Started tracking game: {}", filename);
-                }
-                Err(e) => {
-                    println!("Failed to launch game: {}", e);
-                }
-            }
+            launch_with = Some(idx);
             ui.close_menu();
         }
     }
+    if let Some(idx) = launch_with {
+        app.launch_rom_with(filename, idx);
+    }
 }
 
-fn render_rom_name(ui: &mut egui::Ui, filename: &str, is_virtual: bool, row_height: f32) {
+fn render_rom_name(ui: &mut egui::Ui, filename: &str, is_virtual: bool, is_folder_set: bool, row_height: f32) {
     let rom_text = if is_virtual {
         "(missing)".to_string()
     } else {
-        let max_width = 12;
-        if filename.len() > max_width {
-            format!("{}…", &filename[..max_width-1])
+        // Folder sets give up one column to the 📁 marker
+        let (filename, max_width) = if is_folder_set {
+            (format!("📁{}", filename), 13)
+        } else {
+            (filename.to_string(), 12)
+        };
+        let chars: Vec<char> = filename.chars().collect();
+        if chars.len() > max_width {
+            format!("{}…", chars[..max_width-1].iter().collect::<String>())
         } else {
             format!("{:<width$}", filename, width = max_width)
         }
     };
 
-    ui.add_sized([100.0, row_height], egui::Label::new(
+    let response = ui.add_sized([100.0, row_height], egui::Label::new(
         egui::RichText::new(rom_text)
         .color(egui::Color32::from_rgb(200, 200, 200))
         .monospace()
     ));
+    if is_folder_set {
+        response.on_hover_text("Uncompressed folder set");
+    }
 }
 
 fn render_year(ui: &mut egui::Ui, year: &str, row_height: f32) {
@@ -7,11 +7,11 @@ pub fn show_status_bar(app: &mut MyApp, ctx: &egui::Context) {
     egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
         ui.horizontal(|ui| {
             // Filter status
-            let total_roms = app.roms.len();
+            let total_roms = app.list_roms().len();
 
             // Calculate filtered count using the same logic as the main filter
-            let filtered_roms_count = app.roms.iter()
-            .filter(|(display, filename)| apply_rom_filters(&app.config.filter_settings, &app.game_metadata, display, filename, &app.config.favorite_games))
+            let filtered_roms_count = app.list_roms().iter()
+            .filter(|(display, filename)| apply_rom_filters(&app.config.filter_settings, app.list_metadata(), display, filename, &app.config.favorite_games, app.list_audit_results()))
             .count();
 
             let status_text = if app.config.filter_settings.search_text.is_empty() &&